@@ -0,0 +1,2 @@
+ALTER TABLE contents DROP COLUMN version;
+ALTER TABLE pages DROP COLUMN version;
@@ -0,0 +1,3 @@
+-- Row versions for optimistic concurrency (ETag / If-Match)
+ALTER TABLE pages ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE contents ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_pages_scope;
+ALTER TABLE contents DROP COLUMN display_order;
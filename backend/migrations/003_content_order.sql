@@ -0,0 +1,4 @@
+-- Explicit ordering of contents under a page
+ALTER TABLE contents ADD COLUMN display_order INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_pages_scope ON pages(page_name, section_name, display_order);
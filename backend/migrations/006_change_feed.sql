@@ -0,0 +1,12 @@
+-- Short rolling log of page, content and agent changes backing the live feed
+CREATE TABLE IF NOT EXISTS change_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type VARCHAR(30) NOT NULL,
+    entity VARCHAR(20) NOT NULL,
+    entity_id VARCHAR(100) NOT NULL,
+    page_name VARCHAR(255),
+    section_name VARCHAR(255),
+    lang VARCHAR(10),
+    payload TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_pages_translation_group;
+ALTER TABLE pages DROP COLUMN translation_of;
+ALTER TABLE pages DROP COLUMN translation_group;
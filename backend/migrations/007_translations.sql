@@ -0,0 +1,6 @@
+-- Links a page to its counterparts in other languages
+ALTER TABLE pages ADD COLUMN translation_group VARCHAR(36);
+-- Page a translation was created from; NULL for originals
+ALTER TABLE pages ADD COLUMN translation_of INTEGER REFERENCES pages(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_pages_translation_group ON pages(translation_group);
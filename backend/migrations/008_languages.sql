@@ -0,0 +1,19 @@
+-- Languages pages may be written in, keyed by BCP 47 tag
+CREATE TABLE IF NOT EXISTS languages (
+    code VARCHAR(35) PRIMARY KEY,
+    name VARCHAR(50) NOT NULL,
+    direction VARCHAR(3) NOT NULL DEFAULT 'ltr' CHECK (direction IN ('ltr', 'rtl')),
+    is_default BOOLEAN NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    -- Comma-separated codes tried in order before the default language
+    fallback TEXT NOT NULL DEFAULT '',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- At most one default language
+CREATE UNIQUE INDEX IF NOT EXISTS idx_languages_default ON languages(is_default) WHERE is_default = 1;
+
+INSERT OR IGNORE INTO languages (code, name, is_default) VALUES ('en', 'English', 1);
+-- Register every language already in use so existing pages stay valid
+INSERT OR IGNORE INTO languages (code, name) SELECT DISTINCT lang, lang FROM pages;
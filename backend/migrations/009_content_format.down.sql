@@ -0,0 +1,2 @@
+ALTER TABLE contents DROP COLUMN long_desc_html;
+ALTER TABLE contents DROP COLUMN format;
@@ -0,0 +1,9 @@
+-- Format of long_desc: plain, markdown or html
+ALTER TABLE contents ADD COLUMN format VARCHAR(10) NOT NULL DEFAULT 'plain';
+-- long_desc rendered to sanitized HTML on every write
+ALTER TABLE contents ADD COLUMN long_desc_html TEXT;
+
+-- Existing text is plain; render it the way rich_text::render does
+UPDATE contents SET long_desc_html = '<p>' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+    long_desc, char(13), ''), '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'), char(10), '<br>') || '</p>'
+WHERE long_desc IS NOT NULL;
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_pages_path;
+DROP INDEX IF EXISTS idx_pages_sibling_slug;
+DROP INDEX IF EXISTS idx_pages_parent;
+ALTER TABLE pages DROP COLUMN path;
+ALTER TABLE pages DROP COLUMN slug;
+ALTER TABLE pages DROP COLUMN parent_id;
@@ -0,0 +1,13 @@
+-- Parent in the page tree; NULL for top-level pages
+ALTER TABLE pages ADD COLUMN parent_id INTEGER REFERENCES pages(id) ON DELETE SET NULL;
+-- URL segment, unique among a parent's children in one language
+ALTER TABLE pages ADD COLUMN slug VARCHAR(100);
+-- The page's language and the slugs from the root joined with '/', e.g.
+-- '/en/about/team'; NULL unless the page and all of its ancestors have a
+-- slug. Maintained by the application.
+ALTER TABLE pages ADD COLUMN path TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_pages_parent ON pages(parent_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pages_sibling_slug
+    ON pages(COALESCE(parent_id, 0), lang, slug) WHERE slug IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_pages_path ON pages(lang, path);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS menu_item_labels;
+DROP INDEX IF EXISTS idx_menu_items_page;
+DROP INDEX IF EXISTS idx_menu_items_menu;
+DROP TABLE IF EXISTS menu_items;
+DROP TABLE IF EXISTS menus;
@@ -0,0 +1,37 @@
+-- Navigation menus, each shown in one location of the site
+CREATE TABLE IF NOT EXISTS menus (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    location VARCHAR(10) NOT NULL CHECK (location IN ('header', 'footer', 'sidebar')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Menu entries linking to a page, an external URL or an anchor
+CREATE TABLE IF NOT EXISTS menu_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    menu_id INTEGER NOT NULL REFERENCES menus(id) ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES menu_items(id) ON DELETE CASCADE,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    link_type VARCHAR(6) NOT NULL CHECK (link_type IN ('page', 'url', 'anchor')),
+    page_id INTEGER REFERENCES pages(id) ON DELETE SET NULL,
+    url TEXT,
+    anchor VARCHAR(100),
+    -- Shown where no label in menu_item_labels matches the language
+    label VARCHAR(100) NOT NULL,
+    visible BOOLEAN NOT NULL DEFAULT 1,
+    -- Comma-separated codes the item is limited to; empty for every language
+    langs TEXT NOT NULL DEFAULT '',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_menu_items_menu ON menu_items(menu_id, parent_id, display_order);
+CREATE INDEX IF NOT EXISTS idx_menu_items_page ON menu_items(page_id);
+
+CREATE TABLE IF NOT EXISTS menu_item_labels (
+    item_id INTEGER NOT NULL REFERENCES menu_items(id) ON DELETE CASCADE,
+    lang VARCHAR(35) NOT NULL REFERENCES languages(code) ON DELETE CASCADE,
+    label VARCHAR(100) NOT NULL,
+    PRIMARY KEY (item_id, lang)
+);
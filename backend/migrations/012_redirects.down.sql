@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_redirects_target_page;
+DROP INDEX IF EXISTS idx_redirects_target_path;
+DROP TABLE IF EXISTS redirects;
@@ -0,0 +1,18 @@
+-- Redirects from dead URLs to a path, a URL or a page's current path
+CREATE TABLE IF NOT EXISTS redirects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_path TEXT NOT NULL UNIQUE,
+    target_path TEXT,
+    target_page_id INTEGER REFERENCES pages(id) ON DELETE CASCADE,
+    status_code INTEGER NOT NULL DEFAULT 301 CHECK (status_code IN (301, 302)),
+    hits INTEGER NOT NULL DEFAULT 0,
+    last_hit_at DATETIME,
+    -- Created by the server when a page's URL changed
+    automatic BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((target_path IS NULL) != (target_page_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_redirects_target_path ON redirects(target_path);
+CREATE INDEX IF NOT EXISTS idx_redirects_target_page ON redirects(target_page_id);
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_page_terms_term;
+DROP TABLE IF EXISTS page_terms;
+DROP INDEX IF EXISTS idx_content_terms_term;
+DROP TABLE IF EXISTS content_terms;
+DROP TABLE IF EXISTS term_names;
+DROP INDEX IF EXISTS idx_terms_parent;
+DROP TABLE IF EXISTS terms;
+DROP TABLE IF EXISTS taxonomies;
@@ -0,0 +1,47 @@
+-- Vocabularies such as categories or tags
+CREATE TABLE IF NOT EXISTS taxonomies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    -- Whether terms may nest
+    hierarchical BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS terms (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    taxonomy_id INTEGER NOT NULL REFERENCES taxonomies(id) ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES terms(id) ON DELETE CASCADE,
+    slug VARCHAR(100) NOT NULL,
+    -- Shown where no name in term_names matches the language
+    name VARCHAR(100) NOT NULL,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (taxonomy_id, slug)
+);
+
+CREATE INDEX IF NOT EXISTS idx_terms_parent ON terms(parent_id);
+
+CREATE TABLE IF NOT EXISTS term_names (
+    term_id INTEGER NOT NULL REFERENCES terms(id) ON DELETE CASCADE,
+    lang VARCHAR(35) NOT NULL REFERENCES languages(code) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    PRIMARY KEY (term_id, lang)
+);
+
+CREATE TABLE IF NOT EXISTS content_terms (
+    content_id INTEGER NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+    term_id INTEGER NOT NULL REFERENCES terms(id) ON DELETE CASCADE,
+    PRIMARY KEY (content_id, term_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_content_terms_term ON content_terms(term_id);
+
+CREATE TABLE IF NOT EXISTS page_terms (
+    page_id INTEGER NOT NULL REFERENCES pages(id) ON DELETE CASCADE,
+    term_id INTEGER NOT NULL REFERENCES terms(id) ON DELETE CASCADE,
+    PRIMARY KEY (page_id, term_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_page_terms_term ON page_terms(term_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_block_placements_page;
+DROP TABLE IF EXISTS block_placements;
+DROP TABLE IF EXISTS blocks;
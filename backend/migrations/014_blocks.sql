@@ -0,0 +1,29 @@
+-- Shared content blocks, edited once and placed on any number of pages
+CREATE TABLE IF NOT EXISTS blocks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    short_desc VARCHAR(150),
+    long_desc TEXT,
+    format VARCHAR(10) NOT NULL DEFAULT 'plain',
+    long_desc_html TEXT,
+    image_path VARCHAR(100),
+    title VARCHAR(50),
+    version INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- A block shown on a page among its contents; a placed block cannot be deleted
+CREATE TABLE IF NOT EXISTS block_placements (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    block_id INTEGER NOT NULL,
+    page_id INTEGER NOT NULL,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (block_id, page_id),
+    FOREIGN KEY (block_id) REFERENCES blocks(id),
+    FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_block_placements_page ON block_placements(page_id);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_contents_type;
+ALTER TABLE contents DROP COLUMN fields;
+ALTER TABLE contents DROP COLUMN type_id;
+DROP TABLE IF EXISTS content_type_fields;
+DROP TABLE IF EXISTS content_types;
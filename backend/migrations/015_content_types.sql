@@ -0,0 +1,31 @@
+-- Admin-defined content types with typed custom fields
+CREATE TABLE IF NOT EXISTS content_types (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    label VARCHAR(100) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS content_type_fields (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    type_id INTEGER NOT NULL REFERENCES content_types(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    label VARCHAR(100) NOT NULL,
+    field_type VARCHAR(10) NOT NULL
+        CHECK (field_type IN ('text', 'rich_text', 'number', 'date', 'boolean', 'image', 'page', 'list')),
+    -- Type of each element of a list field
+    item_type VARCHAR(10)
+        CHECK (item_type IN ('text', 'rich_text', 'number', 'date', 'boolean', 'image', 'page')),
+    required BOOLEAN NOT NULL DEFAULT 0,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (type_id, name),
+    CHECK ((field_type = 'list') = (item_type IS NOT NULL))
+);
+
+-- A typed content keeps its field values as a JSON object; a type in use
+-- cannot be deleted
+ALTER TABLE contents ADD COLUMN type_id INTEGER REFERENCES content_types(id);
+ALTER TABLE contents ADD COLUMN fields TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_contents_type ON contents(type_id);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_content_images_content;
+DROP TABLE IF EXISTS content_images;
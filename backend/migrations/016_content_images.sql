@@ -0,0 +1,15 @@
+-- Ordered image attachments of a content, each file owned by its content
+CREATE TABLE IF NOT EXISTS content_images (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    content_id INTEGER NOT NULL,
+    filename VARCHAR(100) NOT NULL,
+    caption VARCHAR(200),
+    alt VARCHAR(200),
+    display_order INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (content_id, filename),
+    FOREIGN KEY (content_id) REFERENCES contents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_content_images_content ON content_images(content_id);
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS relations_page_delete;
+DROP TRIGGER IF EXISTS relations_content_delete;
+DROP INDEX IF EXISTS idx_relations_target;
+DROP TABLE IF EXISTS relations;
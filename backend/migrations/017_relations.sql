@@ -0,0 +1,32 @@
+-- Typed, directional links from a content or page to another content or page
+CREATE TABLE IF NOT EXISTS relations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_kind VARCHAR(10) NOT NULL CHECK (source_kind IN ('content', 'page')),
+    source_id INTEGER NOT NULL,
+    relation_type VARCHAR(50) NOT NULL,
+    target_kind VARCHAR(10) NOT NULL CHECK (target_kind IN ('content', 'page')),
+    target_id INTEGER NOT NULL,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (source_kind, source_id, relation_type, target_kind, target_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_relations_target ON relations(target_kind, target_id);
+
+-- Either end may be a content or a page, so foreign keys cannot remove
+-- relations of deleted rows; these triggers do, cascades included
+CREATE TRIGGER IF NOT EXISTS relations_content_delete
+AFTER DELETE ON contents
+BEGIN
+    DELETE FROM relations
+    WHERE (source_kind = 'content' AND source_id = OLD.id)
+       OR (target_kind = 'content' AND target_id = OLD.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS relations_page_delete
+AFTER DELETE ON pages
+BEGIN
+    DELETE FROM relations
+    WHERE (source_kind = 'page' AND source_id = OLD.id)
+       OR (target_kind = 'page' AND target_id = OLD.id);
+END;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_previews_page;
+DROP TABLE IF EXISTS previews;
@@ -0,0 +1,16 @@
+-- Preview links to a page's draft state. The token itself is not stored:
+-- it is signed from the row, so revoking or deleting the row disables it.
+CREATE TABLE IF NOT EXISTS previews (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    page_id INTEGER NOT NULL,
+    lang VARCHAR(35) NOT NULL,
+    note VARCHAR(200),
+    created_by INTEGER,
+    expires_at DATETIME NOT NULL,
+    revoked_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES agents(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_previews_page ON previews(page_id);
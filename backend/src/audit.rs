@@ -0,0 +1,74 @@
+use actix_web::{HttpMessage, HttpRequest};
+use serde::Serialize;
+use sqlx::{Executor, Sqlite};
+
+use crate::auth::Claims;
+
+/// Who made a request and from where, captured for the audit log.
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub agent_id: Option<i64>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AuditContext {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        AuditContext {
+            agent_id: req.extensions().get::<Claims>().map(|claims| claims.agent_id),
+            ip_address: req.connection_info().realip_remote_addr().map(str::to_string),
+            user_agent: req
+                .headers()
+                .get("User-Agent")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+
+    /// Context for changes made outside an HTTP request, e.g. by `ccs-admin`.
+    pub fn system(tool: &str) -> Self {
+        AuditContext {
+            agent_id: None,
+            ip_address: None,
+            user_agent: Some(tool.to_string()),
+        }
+    }
+}
+
+/// Appends an entry to `audit_log`. Snapshots are stored as JSON; pass `None`
+/// for the side that does not exist (before a create, after a delete).
+pub async fn record<'e, E, B, A>(
+    executor: E,
+    context: &AuditContext,
+    action: &str,
+    entity: &str,
+    entity_id: impl ToString,
+    before: Option<&B>,
+    after: Option<&A>,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+    B: Serialize,
+    A: Serialize,
+{
+    let before = before.and_then(|state| serde_json::to_string(state).ok());
+    let after = after.and_then(|state| serde_json::to_string(state).ok());
+
+    sqlx::query(
+        "INSERT INTO audit_log (agent_id, action, entity, entity_id, before_state, after_state,
+         ip_address, user_agent)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(context.agent_id)
+    .bind(action)
+    .bind(entity)
+    .bind(entity_id.to_string())
+    .bind(before)
+    .bind(after)
+    .bind(&context.ip_address)
+    .bind(&context.user_agent)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
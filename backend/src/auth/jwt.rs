@@ -44,3 +44,63 @@ pub fn decode_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::e
 
     Ok(token_data.claims)
 }
+
+/// Audience of the tokens that open the change stream. Browsers'
+/// `EventSource` cannot send an `Authorization` header, so these travel in
+/// the URL; the audience keeps them from working as agent tokens.
+const STREAM_AUDIENCE: &str = "changes-stream";
+
+/// Lifetime of a change stream token, which only needs to outlive the
+/// request that opens the stream.
+pub const STREAM_TOKEN_EXPIRATION: i64 = 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StreamClaims {
+    aud: String,
+    sub: String,
+    agent_id: i64,
+    exp: usize,
+    iat: usize,
+}
+
+pub fn generate_stream_token(
+    claims: &Claims,
+    secret: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = Utc::now();
+    let claims = StreamClaims {
+        aud: STREAM_AUDIENCE.to_string(),
+        sub: claims.sub.clone(),
+        agent_id: claims.agent_id,
+        exp: (now + Duration::seconds(STREAM_TOKEN_EXPIRATION)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+}
+
+pub fn decode_stream_token(
+    token: &str,
+    secret: &str,
+) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let mut validation = Validation::new(jsonwebtoken::Algorithm::HS256);
+    validation.set_audience(&[STREAM_AUDIENCE]);
+
+    let claims = decode::<StreamClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )?
+    .claims;
+
+    Ok(Claims {
+        sub: claims.sub,
+        agent_id: claims.agent_id,
+        exp: claims.exp,
+        iat: claims.iat,
+    })
+}
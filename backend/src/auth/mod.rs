@@ -2,3 +2,4 @@ pub mod jwt;
 pub mod middleware;
 
 pub use jwt::{generate_token, decode_token, Claims};
+pub use jwt::{generate_stream_token, decode_stream_token, STREAM_TOKEN_EXPIRATION};
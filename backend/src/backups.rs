@@ -0,0 +1,298 @@
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, SqlitePool};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::BackupConfig;
+use crate::db;
+use crate::models::backup::{BackupFile, BackupManifest, BackupSummary, BackupVerification};
+
+const DATABASE_FILE: &str = "database.db";
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug)]
+pub enum BackupError {
+    NotFound,
+    Invalid(String),
+    Database(sqlx::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::NotFound => write!(f, "Backup not found"),
+            BackupError::Invalid(message) => write!(f, "{}", message),
+            BackupError::Database(e) => write!(f, "Database error: {}", e),
+            BackupError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<sqlx::Error> for BackupError {
+    fn from(e: sqlx::Error) -> Self {
+        BackupError::Database(e)
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+/// Snapshot directory for `name`, rejecting anything that is not a plain
+/// snapshot name so it cannot point outside `backup_dir`.
+fn snapshot_dir(backup_dir: &Path, name: &str) -> Result<PathBuf, BackupError> {
+    let valid =
+        name.starts_with("backup-") && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        return Err(BackupError::NotFound);
+    }
+    Ok(backup_dir.join(name))
+}
+
+fn hash_file(path: &Path) -> Result<BackupFile, std::io::Error> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)?;
+    Ok(BackupFile {
+        path: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size,
+        sha256: hex::encode(hasher.finalize()),
+    })
+}
+
+/// Takes a consistent snapshot of the live database with `VACUUM INTO`,
+/// which is safe while the server keeps writing, and records the image files
+/// alongside it.
+pub async fn create(
+    pool: &SqlitePool,
+    backup_dir: &Path,
+    upload_dir: &Path,
+) -> Result<BackupManifest, BackupError> {
+    let now = chrono::Utc::now();
+    let name = format!("backup-{}", now.format("%Y%m%d-%H%M%S"));
+    let dir = snapshot_dir(backup_dir, &name)?;
+    if dir.exists() {
+        return Err(BackupError::Invalid(format!(
+            "Backup {} already exists",
+            name
+        )));
+    }
+    std::fs::create_dir_all(&dir)?;
+
+    let result = async {
+        let database_path = dir.join(DATABASE_FILE);
+        sqlx::query("VACUUM INTO ?")
+            .bind(database_path.to_string_lossy().into_owned())
+            .execute(pool)
+            .await?;
+
+        let mut images = Vec::new();
+        if upload_dir.exists() {
+            for entry in std::fs::read_dir(upload_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    images.push(hash_file(&entry.path())?);
+                }
+            }
+        }
+        images.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let manifest = BackupManifest {
+            name: name.clone(),
+            created_at: now.to_rfc3339(),
+            schema_version: db::applied_migrations(pool).await?.pop(),
+            database: hash_file(&database_path)?,
+            images,
+        };
+        let json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| BackupError::Invalid(e.to_string()))?;
+        std::fs::write(dir.join(MANIFEST_FILE), json)?;
+        Ok::<_, BackupError>(manifest)
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    result
+}
+
+pub fn manifest(backup_dir: &Path, name: &str) -> Result<BackupManifest, BackupError> {
+    let path = snapshot_dir(backup_dir, name)?.join(MANIFEST_FILE);
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(BackupError::NotFound),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_slice(&json)
+        .map_err(|e| BackupError::Invalid(format!("Unreadable manifest for {}: {}", name, e)))
+}
+
+/// Snapshots with a readable manifest, newest first.
+pub fn list(backup_dir: &Path) -> Result<Vec<BackupSummary>, BackupError> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut summaries = Vec::new();
+    for entry in std::fs::read_dir(backup_dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if let Ok(manifest) = manifest(backup_dir, &name) {
+            summaries.push(BackupSummary {
+                name: manifest.name,
+                created_at: manifest.created_at,
+                schema_version: manifest.schema_version,
+                database_size: manifest.database.size,
+                image_count: manifest.images.len(),
+            });
+        }
+    }
+    summaries.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(summaries)
+}
+
+pub fn delete(backup_dir: &Path, name: &str) -> Result<(), BackupError> {
+    let dir = snapshot_dir(backup_dir, name)?;
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Err(BackupError::NotFound);
+    }
+    Ok(std::fs::remove_dir_all(dir)?)
+}
+
+/// Checks the snapshot database against its manifest checksum and with
+/// `PRAGMA integrity_check`, and compares the manifest's images with the
+/// upload directory.
+pub async fn verify(
+    backup_dir: &Path,
+    upload_dir: &Path,
+    name: &str,
+) -> Result<BackupVerification, BackupError> {
+    let manifest = manifest(backup_dir, name)?;
+    let database_path = snapshot_dir(backup_dir, name)?.join(DATABASE_FILE);
+
+    let integrity = match hash_file(&database_path) {
+        Ok(file) if file.sha256 == manifest.database.sha256 => {
+            let mut conn = SqliteConnectOptions::new()
+                .filename(&database_path)
+                .read_only(true)
+                .connect()
+                .await?;
+            let rows: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+                .fetch_all(&mut conn)
+                .await?;
+            rows.join("; ")
+        }
+        Ok(_) => "checksum mismatch".to_string(),
+        Err(e) => format!("unreadable: {}", e),
+    };
+
+    let mut missing_images = Vec::new();
+    let mut changed_images = Vec::new();
+    for image in &manifest.images {
+        match hash_file(&upload_dir.join(&image.path)) {
+            Ok(file) if file.sha256 == image.sha256 => {}
+            Ok(_) => changed_images.push(image.path.clone()),
+            Err(_) => missing_images.push(image.path.clone()),
+        }
+    }
+
+    Ok(BackupVerification {
+        name: manifest.name,
+        valid: integrity == "ok",
+        integrity,
+        missing_images,
+        changed_images,
+    })
+}
+
+/// Deletes snapshots beyond the newest `keep_last` that are older than
+/// `max_age_days`, returning their names.
+pub fn prune(config: &BackupConfig) -> Result<Vec<String>, BackupError> {
+    let backup_dir = Path::new(&config.dir);
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(config.max_age_days);
+
+    let mut pruned = Vec::new();
+    for summary in list(backup_dir)?.into_iter().skip(config.keep_last) {
+        let expired = chrono::DateTime::parse_from_rfc3339(&summary.created_at)
+            .map(|created_at| created_at < cutoff)
+            .unwrap_or(false);
+        if expired {
+            delete(backup_dir, &summary.name)?;
+            pruned.push(summary.name);
+        }
+    }
+    Ok(pruned)
+}
+
+/// Replaces the database file at `database_path` with a verified snapshot.
+/// The server must not be running. The replaced database and its WAL files
+/// are kept next to it with a `.pre-restore-<timestamp>` suffix.
+pub async fn restore(
+    backup_dir: &Path,
+    upload_dir: &Path,
+    name: &str,
+    database_path: &Path,
+) -> Result<BackupVerification, BackupError> {
+    let verification = verify(backup_dir, upload_dir, name).await?;
+    if !verification.valid {
+        return Err(BackupError::Invalid(format!(
+            "Backup {} failed verification: {}",
+            name, verification.integrity
+        )));
+    }
+
+    let staged = database_path.with_extension("restore");
+    std::fs::copy(snapshot_dir(backup_dir, name)?.join(DATABASE_FILE), &staged)?;
+
+    let suffix = format!("pre-restore-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    for extension in ["", "-wal", "-shm"] {
+        let current = PathBuf::from(format!("{}{}", database_path.display(), extension));
+        if current.exists() {
+            let kept = PathBuf::from(format!(
+                "{}{}.{}",
+                database_path.display(),
+                extension,
+                suffix
+            ));
+            std::fs::rename(&current, kept)?;
+        }
+    }
+    std::fs::rename(&staged, database_path)?;
+
+    Ok(verification)
+}
+
+/// Takes a snapshot every `interval_hours` and prunes old ones until the
+/// process exits.
+pub async fn run_scheduler(pool: SqlitePool, config: BackupConfig, upload_dir: String) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_hours * 60 * 60));
+    // The first tick completes immediately; wait a full interval instead.
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        match create(&pool, Path::new(&config.dir), Path::new(&upload_dir)).await {
+            Ok(manifest) => log::info!("Created backup {}", manifest.name),
+            Err(e) => log::error!("Scheduled backup failed: {}", e),
+        }
+        match prune(&config) {
+            Ok(pruned) => {
+                for name in pruned {
+                    log::info!("Pruned backup {}", name);
+                }
+            }
+            Err(e) => log::error!("Backup pruning failed: {}", e),
+        }
+    }
+}
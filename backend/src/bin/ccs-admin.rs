@@ -0,0 +1,532 @@
+//! Operational tasks against the CMS database, for use inside the backend
+//! container: `ccs-admin help` lists the commands.
+
+use bcrypt::{hash, DEFAULT_COST};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::env;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use backend::audit::AuditContext;
+use backend::config::{BackupConfig, SiteConfig};
+use backend::models::bundle::{ConflictPolicy, ImportMode};
+use backend::models::Agent;
+use backend::render::{self, Renderer};
+use backend::{backups, bundle, changes, db, site};
+
+type CliResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "Usage: ccs-admin <command>
+
+Agents:
+  agent list
+  agent create <agent_number> [--inactive]   password is read from stdin
+  agent reset-password <agent_number>        password is read from stdin
+  agent activate <agent_number>
+  agent deactivate <agent_number>
+
+Database:
+  migrate status
+  migrate run
+  migrate rollback                           reverts the latest migration
+  db vacuum
+  db reindex                                 rebuilds indexes and planner statistics
+
+Backups:
+  backup list
+  backup create                              snapshot the database and record the images
+  backup verify <name>                       check a snapshot's checksum and integrity
+  backup restore <name>                      replace the database with a snapshot (server stopped)
+  backup prune                               apply BACKUP_KEEP_LAST / BACKUP_MAX_AGE_DAYS
+
+Images:
+  images gc [--dry-run]                      deletes files no content or block refers to
+
+Bundles:
+  bundle export <file>                       pages, contents and images as a gzipped bundle
+  bundle import <file> [options]             imports a bundle; pages get new ids
+      --replace                              delete every existing page first
+      --overwrite                            replace pages with the same page_name/section_name/lang
+      --fail-on-conflict                     abort if any such page exists
+      --dry-run                              report what would change without changing anything
+
+Static site:
+  site build [--full]                        render changed pages into SITE_DIR; --full renders all
+
+Reads DATABASE_URL, UPLOAD_DIR, TEMPLATE_DIR and the BACKUP_* and SITE_* settings
+like the server.";
+
+/// Files younger than this are kept by `images gc`: they may have been
+/// uploaded for content that has not been saved yet.
+const GC_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    if let Err(e) = run(&args).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: &[&str]) -> CliResult {
+    match args {
+        ["migrate", "status"] => migrate_status(&db::connect().await?).await,
+        ["migrate", "run"] => Ok(db::run_migrations(&db::connect().await?).await?),
+        ["migrate", "rollback"] => migrate_rollback(&db::connect().await?).await,
+        ["backup", "restore", name] => restore_backup(name).await,
+        [] | ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => {
+            let pool = db::init_db().await?;
+            let context = AuditContext::system("ccs-admin");
+            match args {
+                ["agent", "list"] => list_agents(&pool).await,
+                ["agent", "create", number] => create_agent(&pool, &context, number, true).await,
+                ["agent", "create", number, "--inactive"] => {
+                    create_agent(&pool, &context, number, false).await
+                }
+                ["agent", "reset-password", number] => {
+                    reset_password(&pool, &context, number).await
+                }
+                ["agent", "activate", number] => set_active(&pool, &context, number, true).await,
+                ["agent", "deactivate", number] => set_active(&pool, &context, number, false).await,
+                ["db", "vacuum"] => {
+                    sqlx::query("VACUUM").execute(&pool).await?;
+                    println!("Database vacuumed");
+                    Ok(())
+                }
+                ["db", "reindex"] => {
+                    sqlx::query("REINDEX").execute(&pool).await?;
+                    sqlx::query("ANALYZE").execute(&pool).await?;
+                    println!("Indexes rebuilt");
+                    Ok(())
+                }
+                ["backup", "list"] => list_backups(),
+                ["backup", "create"] => create_backup(&pool).await,
+                ["backup", "verify", name] => verify_backup(name).await,
+                ["backup", "prune"] => {
+                    for name in backups::prune(&BackupConfig::from_env())? {
+                        println!("Pruned {}", name);
+                    }
+                    Ok(())
+                }
+                ["images", "gc"] => collect_images(&pool, false).await,
+                ["images", "gc", "--dry-run"] => collect_images(&pool, true).await,
+                ["bundle", "export", file] => export_bundle(&pool, file).await,
+                ["bundle", "import", file, options @ ..] => {
+                    import_bundle(&pool, &context, file, options).await
+                }
+                ["site", "build"] => build_site(&pool, false).await,
+                ["site", "build", "--full"] => build_site(&pool, true).await,
+                _ => Err(format!("Unknown command '{}'\n\n{}", args.join(" "), USAGE).into()),
+            }
+        }
+    }
+}
+
+async fn migrate_status(pool: &SqlitePool) -> CliResult {
+    let applied = db::applied_migrations(pool).await?;
+    for migration in db::MIGRATIONS {
+        let state = if applied.contains(&migration.version) {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!("{:03}_{:<20} {}", migration.version, migration.name, state);
+    }
+    Ok(())
+}
+
+async fn migrate_rollback(pool: &SqlitePool) -> CliResult {
+    let Some(migration) = db::latest_migration(pool).await? else {
+        println!("No migrations applied");
+        return Ok(());
+    };
+    let Some(down) = migration.down else {
+        return Err(format!(
+            "Migration {:03}_{} cannot be rolled back",
+            migration.version, migration.name
+        )
+        .into());
+    };
+
+    db::rollback_migration(pool, migration, down).await?;
+    println!(
+        "Rolled back migration {:03}_{}",
+        migration.version, migration.name
+    );
+    Ok(())
+}
+
+fn upload_dir() -> String {
+    env::var("UPLOAD_DIR").unwrap_or_else(|_| "../data/images".to_string())
+}
+
+/// Reads a password from the first line of stdin, prompting on a terminal.
+fn read_password() -> CliResult<String> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        std::io::stderr().flush()?;
+    }
+
+    let mut password = String::new();
+    stdin.lock().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        return Err("Password must not be empty".into());
+    }
+    Ok(password)
+}
+
+async fn fetch_agent(pool: &SqlitePool, agent_number: &str) -> CliResult<Agent> {
+    sqlx::query_as::<_, Agent>("SELECT * FROM agents WHERE agent_number = ?")
+        .bind(agent_number)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| format!("Agent {} not found", agent_number).into())
+}
+
+async fn list_agents(pool: &SqlitePool) -> CliResult {
+    let agents = sqlx::query_as::<_, Agent>("SELECT * FROM agents ORDER BY agent_number")
+        .fetch_all(pool)
+        .await?;
+
+    for agent in agents {
+        let state = if agent.is_active {
+            "active"
+        } else {
+            "inactive"
+        };
+        println!(
+            "{:<8} {:<8} created {}",
+            agent.agent_number, state, agent.created_at
+        );
+    }
+    Ok(())
+}
+
+async fn create_agent(
+    pool: &SqlitePool,
+    context: &AuditContext,
+    agent_number: &str,
+    is_active: bool,
+) -> CliResult {
+    let password_hash = hash(read_password()?, DEFAULT_COST)?;
+
+    let mut tx = pool.begin().await?;
+    let agent = sqlx::query_as::<_, Agent>(
+        "INSERT INTO agents (agent_number, password_hash, is_active) VALUES (?, ?, ?)
+         RETURNING *",
+    )
+    .bind(agent_number)
+    .bind(&password_hash)
+    .bind(is_active)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE constraint failed") {
+            format!("Agent {} already exists", agent_number).into()
+        } else {
+            Box::<dyn std::error::Error>::from(e)
+        }
+    })?;
+
+    changes::record(
+        &mut tx,
+        context,
+        "create",
+        "agent",
+        agent.id,
+        None::<&Agent>,
+        Some(&agent),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("Created agent {} (id {})", agent.agent_number, agent.id);
+    Ok(())
+}
+
+async fn reset_password(
+    pool: &SqlitePool,
+    context: &AuditContext,
+    agent_number: &str,
+) -> CliResult {
+    let existing = fetch_agent(pool, agent_number).await?;
+    let password_hash = hash(read_password()?, DEFAULT_COST)?;
+
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query_as::<_, Agent>(
+        "UPDATE agents SET password_hash = ?, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? RETURNING *",
+    )
+    .bind(&password_hash)
+    .bind(existing.id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    changes::record(
+        &mut tx,
+        context,
+        "reset_password",
+        "agent",
+        existing.id,
+        Some(&existing),
+        Some(&updated),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("Password reset for agent {}", agent_number);
+    Ok(())
+}
+
+async fn set_active(
+    pool: &SqlitePool,
+    context: &AuditContext,
+    agent_number: &str,
+    is_active: bool,
+) -> CliResult {
+    let existing = fetch_agent(pool, agent_number).await?;
+
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query_as::<_, Agent>(
+        "UPDATE agents SET is_active = ?, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? RETURNING *",
+    )
+    .bind(is_active)
+    .bind(existing.id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    changes::record(
+        &mut tx,
+        context,
+        "update",
+        "agent",
+        existing.id,
+        Some(&existing),
+        Some(&updated),
+    )
+    .await?;
+    tx.commit().await?;
+
+    let state = if is_active {
+        "activated"
+    } else {
+        "deactivated"
+    };
+    println!("Agent {} {}", agent_number, state);
+    Ok(())
+}
+
+/// Deletes files in `UPLOAD_DIR` that no content's or block's `image_path`,
+/// nor any content's image attachment, refers to.
+async fn collect_images(pool: &SqlitePool, dry_run: bool) -> CliResult {
+    let upload_dir = upload_dir();
+
+    let image_paths: Vec<String> = sqlx::query_scalar(
+        "SELECT image_path FROM contents WHERE image_path IS NOT NULL
+         UNION SELECT image_path FROM blocks WHERE image_path IS NOT NULL
+         UNION SELECT filename FROM content_images",
+    )
+    .fetch_all(pool)
+    .await?;
+    let referenced: HashSet<&str> = image_paths
+        .iter()
+        .flat_map(|image_path| render::stored_files(image_path))
+        .collect();
+
+    let mut removed = 0;
+    let mut freed = 0;
+    for entry in std::fs::read_dir(&upload_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let filename = entry.file_name().to_string_lossy().into_owned();
+        if !metadata.is_file() || referenced.contains(filename.as_str()) {
+            continue;
+        }
+
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < GC_MIN_AGE {
+            continue;
+        }
+
+        if dry_run {
+            println!("Would delete {}", filename);
+        } else {
+            std::fs::remove_file(entry.path())?;
+            println!("Deleted {}", filename);
+        }
+        removed += 1;
+        freed += metadata.len();
+    }
+
+    let verb = if dry_run { "Would free" } else { "Freed" };
+    println!("{} {} bytes in {} unreferenced files", verb, freed, removed);
+    Ok(())
+}
+
+fn list_backups() -> CliResult {
+    let config = BackupConfig::from_env();
+    for summary in backups::list(Path::new(&config.dir))? {
+        println!(
+            "{}  {:>12} bytes  {:>5} images  schema {:03}",
+            summary.name,
+            summary.database_size,
+            summary.image_count,
+            summary.schema_version.unwrap_or(0)
+        );
+    }
+    Ok(())
+}
+
+async fn create_backup(pool: &SqlitePool) -> CliResult {
+    let config = BackupConfig::from_env();
+    let manifest = backups::create(pool, Path::new(&config.dir), Path::new(&upload_dir())).await?;
+    println!(
+        "Created {} ({} bytes, {} images)",
+        manifest.name,
+        manifest.database.size,
+        manifest.images.len()
+    );
+    Ok(())
+}
+
+async fn verify_backup(name: &str) -> CliResult {
+    let config = BackupConfig::from_env();
+    let verification =
+        backups::verify(Path::new(&config.dir), Path::new(&upload_dir()), name).await?;
+    println!("{}", serde_json::to_string_pretty(&verification)?);
+    if !verification.valid {
+        return Err(format!("Backup {} is not valid", name).into());
+    }
+    Ok(())
+}
+
+/// Restores a snapshot over the database named by `DATABASE_URL`. Stop the
+/// server first: it keeps the old file open and would go on writing to it.
+async fn restore_backup(name: &str) -> CliResult {
+    let config = BackupConfig::from_env();
+
+    let pool = db::connect().await?;
+    let database_path: String =
+        sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main'")
+            .fetch_one(&pool)
+            .await?;
+    pool.close().await;
+
+    let verification = backups::restore(
+        Path::new(&config.dir),
+        Path::new(&upload_dir()),
+        name,
+        Path::new(&database_path),
+    )
+    .await?;
+
+    println!("Restored {} to {}", name, database_path);
+    for image in &verification.missing_images {
+        println!(
+            "Warning: image {} is missing from the upload directory",
+            image
+        );
+    }
+    for image in &verification.changed_images {
+        println!("Warning: image {} has changed since the backup", image);
+    }
+    Ok(())
+}
+
+async fn export_bundle(pool: &SqlitePool, file: &str) -> CliResult {
+    let bundle = bundle::export(pool, Path::new(&upload_dir())).await?;
+    std::fs::write(file, bundle::encode(&bundle)?)?;
+    println!(
+        "Exported {} pages and {} images to {}",
+        bundle.pages.len(),
+        bundle.images.len(),
+        file
+    );
+    Ok(())
+}
+
+async fn import_bundle(
+    pool: &SqlitePool,
+    context: &AuditContext,
+    file: &str,
+    options: &[&str],
+) -> CliResult {
+    let mut mode = ImportMode::Merge;
+    let mut policy = ConflictPolicy::Skip;
+    let mut dry_run = false;
+    for option in options {
+        match *option {
+            "--replace" => mode = ImportMode::Replace,
+            "--overwrite" => policy = ConflictPolicy::Overwrite,
+            "--fail-on-conflict" => policy = ConflictPolicy::Fail,
+            "--dry-run" => dry_run = true,
+            _ => return Err(format!("Unknown option '{}'\n\n{}", option, USAGE).into()),
+        }
+    }
+
+    let bundle = bundle::decode(&std::fs::read(file)?)?;
+    let report = bundle::import(
+        pool,
+        Path::new(&upload_dir()),
+        context,
+        &bundle,
+        mode,
+        policy,
+        dry_run,
+    )
+    .await?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+async fn build_site(pool: &SqlitePool, full: bool) -> CliResult {
+    let config = SiteConfig::from_env();
+    let renderer = Renderer::new(env::var("TEMPLATE_DIR").ok().map(Into::into));
+    let report = site::build(pool, &renderer, &config, Path::new(&upload_dir()), full).await?;
+
+    for file in &report.rendered {
+        println!("Rendered {}", file);
+    }
+    for file in &report.removed {
+        println!("Removed {}", file);
+    }
+    for image in &report.missing_images {
+        println!(
+            "Warning: image {} is missing from the upload directory",
+            image
+        );
+    }
+    for page_name in &report.skipped {
+        println!(
+            "Warning: page_name '{}' is not a valid file name, skipped",
+            page_name
+        );
+    }
+    println!(
+        "Built {}: {} files rendered, {} pages unchanged, {} images copied",
+        config.dir,
+        report.rendered.len(),
+        report.unchanged,
+        report.images_copied
+    );
+    Ok(())
+}
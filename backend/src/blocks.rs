@@ -0,0 +1,87 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::galleries;
+use crate::models::block::{BlockPlacement, BlockUsage};
+use crate::models::Content;
+
+/// The blocks placed on `page_id`, shaped as contents of the page.
+pub async fn placed(pool: &SqlitePool, page_id: i64) -> Result<Vec<Content>, sqlx::Error> {
+    sqlx::query_as::<_, Content>(
+        "SELECT b.id, p.page_id AS ref_id, b.short_desc, b.long_desc, b.format,
+         b.long_desc_html, b.image_path, b.title, p.display_order, b.version, b.created_at,
+         b.updated_at, NULL AS type_id, NULL AS fields, b.id AS block_id
+         FROM block_placements p JOIN blocks b ON b.id = p.block_id
+         WHERE p.page_id = ? ORDER BY p.display_order, b.id",
+    )
+    .bind(page_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Everything shown on `page_id`: its own contents and the blocks placed on
+/// it, by `display_order`. Of equal positions, the page's own contents come
+/// first.
+pub async fn page_contents(pool: &SqlitePool, page_id: i64) -> Result<Vec<Content>, sqlx::Error> {
+    let mut contents = sqlx::query_as::<_, Content>(
+        "SELECT * FROM contents WHERE ref_id = ? ORDER BY display_order, id",
+    )
+    .bind(page_id)
+    .fetch_all(pool)
+    .await?;
+    galleries::attach(pool, &mut contents).await?;
+    contents.extend(placed(pool, page_id).await?);
+    contents.sort_by_key(|content| {
+        (
+            content.display_order,
+            content.block_id.is_some(),
+            content.id,
+        )
+    });
+    Ok(contents)
+}
+
+/// Every page `block_id` is placed on.
+pub async fn usage(pool: &SqlitePool, block_id: i64) -> Result<Vec<BlockUsage>, sqlx::Error> {
+    sqlx::query_as::<_, BlockUsage>(
+        "SELECT bp.id AS placement_id, p.id AS page_id, p.page_name, p.section_name, p.lang,
+         p.path, p.visible, bp.display_order
+         FROM block_placements bp JOIN pages p ON p.id = bp.page_id
+         WHERE bp.block_id = ? ORDER BY p.page_name, p.lang, p.id",
+    )
+    .bind(block_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Places every block of page `from` on page `to` at the same position.
+pub async fn copy_placements(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    from: i64,
+    to: i64,
+) -> Result<(), sqlx::Error> {
+    let copies = sqlx::query_as::<_, BlockPlacement>(
+        "INSERT INTO block_placements (block_id, page_id, display_order)
+         SELECT block_id, ?, display_order FROM block_placements WHERE page_id = ?
+         RETURNING *",
+    )
+    .bind(to)
+    .bind(from)
+    .fetch_all(&mut *conn)
+    .await?;
+    for copy in &copies {
+        changes::record(
+            &mut *conn,
+            context,
+            "create",
+            "block_placement",
+            copy.id,
+            None::<&BlockPlacement>,
+            Some(copy),
+        )
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,1281 @@
+use base64ct::{Base64, Encoding};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::handlers::image::unique_filename;
+use crate::galleries;
+use crate::models::bundle::{
+    Bundle, BundleConflict, BundleContent, BundleContentType, BundleGalleryImage, BundleImage,
+    BundleLanguage, BundleMenu, BundleMenuItem, BundlePage, BundleSettings, BundleTaxonomy,
+    BundleTerm, BundleTermRef, ConflictPolicy, ImportMode, ImportReport,
+};
+use crate::models::content_type::{ContentType, ContentTypeDetail, FieldSpec};
+use crate::models::gallery::ContentImage;
+use crate::models::language::{Language, DIRECTIONS};
+use crate::models::menu::{Menu, MenuItem, LINK_TYPES, LOCATIONS};
+use crate::models::taxonomy::{Taxonomy, Term};
+use crate::models::{Content, Page};
+use crate::page_tree::{self, TreeError};
+use crate::taxonomies::{self, Tagged, TaxonomyError};
+use crate::{content_types, languages, menus, redirects, render, rich_text};
+
+pub const FORMAT: &str = "ccs-bundle";
+/// Bumped whenever the bundle layout changes; imports accept this version and older.
+/// Version 2 added `settings` and term tags.
+pub const VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum BundleError {
+    Invalid(String),
+    Conflicts(Vec<BundleConflict>),
+    Database(sqlx::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Invalid(message) => write!(f, "Invalid bundle: {}", message),
+            BundleError::Conflicts(conflicts) => {
+                write!(f, "{} conflicting pages:", conflicts.len())?;
+                for conflict in conflicts {
+                    write!(
+                        f,
+                        " {}/{}/{}",
+                        conflict.page_name, conflict.section_name, conflict.lang
+                    )?;
+                }
+                Ok(())
+            }
+            BundleError::Database(e) => write!(f, "Database error: {}", e),
+            BundleError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<sqlx::Error> for BundleError {
+    fn from(e: sqlx::Error) -> Self {
+        BundleError::Database(e)
+    }
+}
+
+impl From<std::io::Error> for BundleError {
+    fn from(e: std::io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+/// Collects the site's settings and every page with its contents and the
+/// image files they use. Images referenced by content but missing on disk
+/// are left out.
+pub async fn export(pool: &SqlitePool, upload_dir: &Path) -> Result<Bundle, BundleError> {
+    let settings = export_settings(pool).await?;
+
+    let pages = sqlx::query_as::<_, Page>("SELECT * FROM pages ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    let type_names: BTreeMap<i64, String> =
+        sqlx::query_as::<_, (i64, String)>("SELECT id, name FROM content_types")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+
+    let mut bundle_pages = Vec::with_capacity(pages.len());
+    let mut image_paths = BTreeSet::new();
+    for page in pages {
+        let mut contents = sqlx::query_as::<_, Content>(
+            "SELECT * FROM contents WHERE ref_id = ? ORDER BY display_order, id",
+        )
+        .bind(page.id)
+        .fetch_all(pool)
+        .await?;
+        galleries::attach(pool, &mut contents).await?;
+
+        image_paths.extend(
+            contents
+                .iter()
+                .filter_map(|c| c.image_path.as_deref())
+                .flat_map(render::stored_files)
+                .map(str::to_string),
+        );
+        image_paths.extend(
+            contents
+                .iter()
+                .flat_map(|c| c.gallery.iter().map(|image| image.filename.clone())),
+        );
+        let mut bundle_contents = Vec::with_capacity(contents.len());
+        for content in contents {
+            bundle_contents.push(BundleContent {
+                terms: term_refs(pool, Tagged::Content, content.id).await?,
+                short_desc: content.short_desc,
+                long_desc: content.long_desc,
+                format: content.format,
+                image_path: content.image_path,
+                title: content.title,
+                display_order: content.display_order,
+                content_type: content
+                    .type_id
+                    .and_then(|type_id| type_names.get(&type_id).cloned()),
+                fields: content
+                    .fields
+                    .as_deref()
+                    .and_then(|fields| serde_json::from_str(fields).ok()),
+                gallery: content
+                    .gallery
+                    .into_iter()
+                    .map(|image| BundleGalleryImage {
+                        filename: image.filename,
+                        caption: image.caption,
+                        alt: image.alt,
+                    })
+                    .collect(),
+            });
+        }
+        bundle_pages.push(BundlePage {
+            terms: term_refs(pool, Tagged::Page, page.id).await?,
+            id: page.id,
+            page_name: page.page_name,
+            section_name: page.section_name,
+            lang: page.lang,
+            content_type: page.content_type,
+            visible: page.visible,
+            display_order: page.display_order,
+            attributes: page.attributes,
+            translation_group: page.translation_group,
+            translation_of: page.translation_of,
+            parent_id: page.parent_id,
+            slug: page.slug,
+            contents: bundle_contents,
+        });
+    }
+
+    let mut images = Vec::with_capacity(image_paths.len());
+    for filename in image_paths {
+        match std::fs::read(upload_dir.join(&filename)) {
+            Ok(data) => images.push(BundleImage {
+                filename,
+                data: Base64::encode_string(&data),
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(Bundle {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        settings,
+        pages: bundle_pages,
+        images,
+    })
+}
+
+/// Serializes a bundle as gzipped JSON.
+pub fn encode(bundle: &Bundle) -> Result<Vec<u8>, BundleError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, bundle).map_err(|e| BundleError::Invalid(e.to_string()))?;
+    Ok(encoder.finish()?)
+}
+
+/// Parses a bundle, gzipped or plain JSON, and checks its format and version.
+pub fn decode(bytes: &[u8]) -> Result<Bundle, BundleError> {
+    let mut json = Vec::new();
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(bytes)
+            .read_to_end(&mut json)
+            .map_err(|e| BundleError::Invalid(e.to_string()))?;
+        &json[..]
+    } else {
+        bytes
+    };
+
+    let bundle: Bundle =
+        serde_json::from_slice(bytes).map_err(|e| BundleError::Invalid(e.to_string()))?;
+    if bundle.format != FORMAT {
+        return Err(BundleError::Invalid(format!(
+            "expected format '{}', found '{}'",
+            FORMAT, bundle.format
+        )));
+    }
+    if bundle.version > VERSION {
+        return Err(BundleError::Invalid(format!(
+            "version {} is newer than the supported version {}",
+            bundle.version, VERSION
+        )));
+    }
+    Ok(bundle)
+}
+
+type Scope = (String, String, String);
+
+/// Imports a bundle in one transaction. Settings missing here come first:
+/// languages, content types and taxonomies, matched by code or name, while
+/// existing ones keep theirs. Pages then get new ids and their contents
+/// follow them, and menus are created last so their items can link to the
+/// imported pages. Bundle images whose names are taken by a different file
+/// are renamed. In merge mode a bundle page conflicts with existing pages of
+/// the same `page_name`, `section_name` and `lang`, resolved by `policy`.
+/// Pages left behind by a deleted parent go under the page replacing it
+/// where they fit, and image files only deleted contents used are removed
+/// after the commit. A dry run reports what would happen and rolls
+/// everything back.
+pub async fn import(
+    pool: &SqlitePool,
+    upload_dir: &Path,
+    context: &AuditContext,
+    bundle: &Bundle,
+    mode: ImportMode,
+    policy: ConflictPolicy,
+    dry_run: bool,
+) -> Result<ImportReport, BundleError> {
+    let mut report = ImportReport {
+        dry_run,
+        mode,
+        pages_created: 0,
+        pages_deleted: 0,
+        contents_created: 0,
+        languages_created: 0,
+        languages_disabled: Vec::new(),
+        content_types_created: 0,
+        taxonomies_created: 0,
+        terms_created: 0,
+        menus_created: 0,
+        pages_reparented: BTreeMap::new(),
+        images_written: 0,
+        images_removed: Vec::new(),
+        images_renamed: BTreeMap::new(),
+        conflicts: Vec::new(),
+        page_ids: BTreeMap::new(),
+    };
+
+    let images = plan_images(upload_dir, &bundle.images, &mut report)?;
+
+    let mut tx = pool.begin().await?;
+
+    let mut skipped = BTreeSet::new();
+    let mut deleted = Vec::new();
+    if mode == ImportMode::Replace {
+        deleted = sqlx::query_as::<_, Page>("SELECT * FROM pages ORDER BY id")
+            .fetch_all(&mut *tx)
+            .await?;
+    } else {
+        let mut scopes: BTreeMap<Scope, Vec<i64>> = BTreeMap::new();
+        for page in &bundle.pages {
+            scopes.entry(scope_of(page)).or_default().push(page.id);
+        }
+
+        for ((page_name, section_name, lang), bundle_ids) in scopes {
+            let existing = sqlx::query_as::<_, Page>(
+                "SELECT * FROM pages WHERE page_name = ? AND section_name = ? AND lang = ?",
+            )
+            .bind(&page_name)
+            .bind(&section_name)
+            .bind(&lang)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            if existing.is_empty() {
+                continue;
+            }
+
+            match policy {
+                ConflictPolicy::Skip | ConflictPolicy::Fail => {
+                    skipped.insert((page_name.clone(), section_name.clone(), lang.clone()));
+                }
+                ConflictPolicy::Overwrite => {}
+            }
+
+            report.conflicts.push(BundleConflict {
+                page_name,
+                section_name,
+                lang,
+                existing_ids: existing.iter().map(|page| page.id).collect(),
+                bundle_ids,
+            });
+            if policy == ConflictPolicy::Overwrite {
+                deleted.extend(existing);
+            }
+        }
+
+        if policy == ConflictPolicy::Fail && !report.conflicts.is_empty() {
+            return Err(BundleError::Conflicts(report.conflicts));
+        }
+    }
+
+    let (orphaned_images, orphaned_children) = delete_pages(&mut tx, context, &deleted).await?;
+    report.pages_deleted = deleted.len();
+
+    import_languages(&mut tx, context, &bundle.settings.languages, &mut report).await?;
+    import_content_types(
+        &mut tx,
+        context,
+        &bundle.settings.content_types,
+        &mut report,
+    )
+    .await?;
+    import_taxonomies(&mut tx, context, &bundle.settings.taxonomies, &mut report).await?;
+
+    // The first page imported for each scope, which takes the place of the
+    // pages deleted from it.
+    let mut replacements: BTreeMap<Scope, i64> = BTreeMap::new();
+
+    for bundle_page in &bundle.pages {
+        if skipped.contains(&scope_of(bundle_page)) {
+            continue;
+        }
+
+        // Languages the bundle does not define are registered, as existing
+        // ones were when the registry was introduced, so imported pages stay
+        // editable.
+        let registered = sqlx::query("INSERT OR IGNORE INTO languages (code, name) VALUES (?, ?)")
+            .bind(&bundle_page.lang)
+            .bind(&bundle_page.lang)
+            .execute(&mut *tx)
+            .await?;
+        report.languages_created += registered.rows_affected() as usize;
+
+        let page = sqlx::query_as::<_, Page>(
+            "INSERT INTO pages (page_name, section_name, lang, content_type, visible, display_order,
+             attributes, translation_group, translation_of)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&bundle_page.page_name)
+        .bind(&bundle_page.section_name)
+        .bind(&bundle_page.lang)
+        .bind(&bundle_page.content_type)
+        .bind(bundle_page.visible)
+        .bind(bundle_page.display_order)
+        .bind(&bundle_page.attributes)
+        .bind(&bundle_page.translation_group)
+        // Only linked when the source page came in earlier in the same bundle.
+        .bind(
+            bundle_page
+                .translation_of
+                .and_then(|id| report.page_ids.get(&id).copied()),
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        changes::record(
+            &mut tx,
+            context,
+            "create",
+            "page",
+            page.id,
+            None::<&Page>,
+            Some(&page),
+        )
+        .await?;
+        tag(&mut tx, context, Tagged::Page, page.id, &bundle_page.terms).await?;
+        report.page_ids.insert(bundle_page.id, page.id);
+        replacements.entry(scope_of(bundle_page)).or_insert(page.id);
+        report.pages_created += 1;
+
+        for bundle_content in &bundle_page.contents {
+            let image_path = bundle_content.image_path.as_deref().map(|path| {
+                render::rename_stored_files(path, |file| {
+                    report
+                        .images_renamed
+                        .get(file)
+                        .cloned()
+                        .unwrap_or_else(|| file.to_string())
+                })
+            });
+            let (long_desc, long_desc_html) =
+                rich_text::prepare(&bundle_content.format, bundle_content.long_desc.as_deref())
+                    .map_err(BundleError::Invalid)?;
+            // Field values are taken as exported; a page they refer to is
+            // looked up by id, which only holds within the same site.
+            let type_id = match &bundle_content.content_type {
+                Some(name) => Some(
+                    sqlx::query_scalar::<_, i64>("SELECT id FROM content_types WHERE name = ?")
+                        .bind(name)
+                        .fetch_optional(&mut *tx)
+                        .await?
+                        .ok_or_else(|| {
+                            BundleError::Invalid(format!("Content type '{}' does not exist", name))
+                        })?,
+                ),
+                None => None,
+            };
+            let fields = bundle_content
+                .fields
+                .as_ref()
+                .filter(|_| type_id.is_some())
+                .map(|fields| fields.to_string());
+            let content = sqlx::query_as::<_, Content>(
+                "INSERT INTO contents (ref_id, short_desc, long_desc, format, long_desc_html,
+                 image_path, title, display_order, type_id, fields)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+            )
+            .bind(page.id)
+            .bind(&bundle_content.short_desc)
+            .bind(&long_desc)
+            .bind(&bundle_content.format)
+            .bind(&long_desc_html)
+            .bind(&image_path)
+            .bind(&bundle_content.title)
+            .bind(bundle_content.display_order)
+            .bind(type_id)
+            .bind(&fields)
+            .fetch_one(&mut *tx)
+            .await?;
+            changes::record(
+                &mut tx,
+                context,
+                "create",
+                "content",
+                content.id,
+                None::<&Content>,
+                Some(&content),
+            )
+            .await?;
+            tag(
+                &mut tx,
+                context,
+                Tagged::Content,
+                content.id,
+                &bundle_content.terms,
+            )
+            .await?;
+            for (position, bundle_image) in bundle_content.gallery.iter().enumerate() {
+                let filename = report
+                    .images_renamed
+                    .get(&bundle_image.filename)
+                    .unwrap_or(&bundle_image.filename);
+                let image = sqlx::query_as::<_, ContentImage>(
+                    "INSERT INTO content_images (content_id, filename, caption, alt, display_order)
+                     VALUES (?, ?, ?, ?, ?) RETURNING *",
+                )
+                .bind(content.id)
+                .bind(filename)
+                .bind(&bundle_image.caption)
+                .bind(&bundle_image.alt)
+                .bind(position as i32)
+                .fetch_one(&mut *tx)
+                .await?;
+                changes::record(
+                    &mut tx,
+                    context,
+                    "create",
+                    "content_image",
+                    image.id,
+                    None::<&ContentImage>,
+                    Some(&image),
+                )
+                .await?;
+            }
+            report.contents_created += 1;
+        }
+    }
+
+    // Parents and slugs are linked once every page exists, since a parent
+    // may come later in the bundle than its children.
+    for bundle_page in &bundle.pages {
+        let Some(&page_id) = report.page_ids.get(&bundle_page.id) else {
+            continue;
+        };
+        // Pages whose parent was not imported end up at the top level.
+        let parent_id = bundle_page
+            .parent_id
+            .and_then(|id| report.page_ids.get(&id).copied());
+        if parent_id.is_none() && bundle_page.slug.is_none() {
+            continue;
+        }
+
+        let slug = bundle_page
+            .slug
+            .as_deref()
+            .map(page_tree::normalize_slug)
+            .transpose()
+            .map_err(tree_error)?;
+        page_tree::check_parent(&mut tx, parent_id, &bundle_page.lang, Some(page_id))
+            .await
+            .map_err(tree_error)?;
+        page_tree::check_slug(
+            &mut tx,
+            parent_id,
+            &bundle_page.lang,
+            slug.as_deref(),
+            Some(page_id),
+        )
+        .await
+        .map_err(tree_error)?;
+
+        let before = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+            .bind(page_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let after = sqlx::query_as::<_, Page>(
+            "UPDATE pages SET parent_id = ?, slug = ? WHERE id = ? RETURNING *",
+        )
+        .bind(parent_id)
+        .bind(&slug)
+        .bind(page_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        changes::record(
+            &mut tx,
+            context,
+            "update",
+            "page",
+            page_id,
+            Some(&before),
+            Some(&after),
+        )
+        .await?;
+    }
+
+    // Pages that stay lose a deleted parent to the top level. They go under
+    // the page imported in its place instead when their slug is free there.
+    let deleted_scopes: BTreeMap<i64, Scope> = deleted
+        .iter()
+        .map(|page| (page.id, page_scope(page)))
+        .collect();
+    for child in &orphaned_children {
+        let replacement = child
+            .parent_id
+            .and_then(|id| deleted_scopes.get(&id))
+            .and_then(|scope| replacements.get(scope))
+            .copied();
+        let mut parent_id = None;
+        if let Some(replacement) = replacement {
+            let fits = match page_tree::check_parent(
+                &mut tx,
+                Some(replacement),
+                &child.lang,
+                Some(child.id),
+            )
+            .await
+            {
+                Ok(()) => {
+                    page_tree::check_slug(
+                        &mut tx,
+                        Some(replacement),
+                        &child.lang,
+                        child.slug.as_deref(),
+                        Some(child.id),
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match fits {
+                Ok(()) => parent_id = Some(replacement),
+                Err(TreeError::Database(e)) => return Err(e.into()),
+                Err(_) => {}
+            }
+        }
+
+        let after =
+            sqlx::query_as::<_, Page>("UPDATE pages SET parent_id = ? WHERE id = ? RETURNING *")
+                .bind(parent_id)
+                .bind(child.id)
+                .fetch_one(&mut *tx)
+                .await?;
+        changes::record(
+            &mut tx,
+            context,
+            "move",
+            "page",
+            child.id,
+            Some(child),
+            Some(&after),
+        )
+        .await?;
+        report.pages_reparented.insert(child.id, parent_id);
+    }
+
+    // Also clears paths below pages deleted by a replace or overwrite.
+    page_tree::rebuild_paths(&mut tx).await?;
+    for &page_id in report.page_ids.values() {
+        redirects::clear_page_sources(&mut tx, context, page_id).await?;
+    }
+    import_menus(&mut tx, context, &bundle.settings.menus, mode, &mut report).await?;
+
+    report.languages_disabled = sqlx::query_scalar(
+        "SELECT DISTINCT p.lang FROM pages p JOIN languages l ON l.code = p.lang
+         WHERE l.enabled = 0 AND p.id IN (SELECT value FROM json_each(?)) ORDER BY p.lang",
+    )
+    .bind(serde_json::json!(report.page_ids.values().collect::<Vec<_>>()).to_string())
+    .fetch_all(&mut *tx)
+    .await?;
+    report.images_removed = galleries::unreferenced(&mut tx, &orphaned_images).await?;
+
+    report.images_written = images.len();
+    if dry_run {
+        tx.rollback().await?;
+        return Ok(report);
+    }
+
+    // Files go in before the commit so committed content never points at a
+    // missing image; they are removed again if anything fails.
+    let mut written: Vec<PathBuf> = Vec::with_capacity(images.len());
+    let result: Result<(), BundleError> = async {
+        if !images.is_empty() {
+            std::fs::create_dir_all(upload_dir)?;
+        }
+        for (path, data) in &images {
+            std::fs::File::create(path)?.write_all(data)?;
+            written.push(path.clone());
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        for path in written {
+            let _ = std::fs::remove_file(path);
+        }
+        return Err(e);
+    }
+
+    for filename in &report.images_removed {
+        let _ = std::fs::remove_file(upload_dir.join(filename));
+    }
+
+    Ok(report)
+}
+
+fn tree_error(e: TreeError) -> BundleError {
+    match e {
+        TreeError::Database(e) => BundleError::Database(e),
+        e => BundleError::Invalid(e.to_string()),
+    }
+}
+
+fn taxonomy_error(e: TaxonomyError) -> BundleError {
+    match e {
+        TaxonomyError::Database(e) => BundleError::Database(e),
+        e => BundleError::Invalid(e.to_string()),
+    }
+}
+
+fn scope_of(page: &BundlePage) -> Scope {
+    (
+        page.page_name.clone(),
+        page.section_name.clone(),
+        page.lang.clone(),
+    )
+}
+
+fn page_scope(page: &Page) -> Scope {
+    (
+        page.page_name.clone(),
+        page.section_name.clone(),
+        page.lang.clone(),
+    )
+}
+
+/// Deletes `pages`, and with them their contents. Returns the uploaded files
+/// those contents used, and the pages left behind whose parent was deleted
+/// as they were before.
+async fn delete_pages(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    pages: &[Page],
+) -> Result<(Vec<String>, Vec<Page>), sqlx::Error> {
+    if pages.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let ids = serde_json::json!(pages.iter().map(|page| page.id).collect::<Vec<_>>()).to_string();
+
+    let image_paths: Vec<String> = sqlx::query_scalar(
+        "SELECT image_path FROM contents
+         WHERE ref_id IN (SELECT value FROM json_each(?1)) AND image_path IS NOT NULL
+         UNION
+         SELECT i.filename FROM content_images i JOIN contents c ON c.id = i.content_id
+         WHERE c.ref_id IN (SELECT value FROM json_each(?1))",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    let images: Vec<String> = image_paths
+        .iter()
+        .flat_map(|image_path| render::stored_files(image_path))
+        .map(str::to_string)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let children = sqlx::query_as::<_, Page>(
+        "SELECT * FROM pages WHERE parent_id IN (SELECT value FROM json_each(?1))
+         AND id NOT IN (SELECT value FROM json_each(?1)) ORDER BY id",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for page in pages {
+        sqlx::query("DELETE FROM pages WHERE id = ?")
+            .bind(page.id)
+            .execute(&mut *conn)
+            .await?;
+        changes::record(
+            conn,
+            context,
+            "delete",
+            "page",
+            page.id,
+            Some(page),
+            None::<&Page>,
+        )
+        .await?;
+    }
+    Ok((images, children))
+}
+
+/// `items` ordered so that each comes after its parent when the parent is
+/// among them. Items caught in a cycle come last.
+fn parents_first<T>(
+    items: &[T],
+    id: impl Fn(&T) -> i64,
+    parent_id: impl Fn(&T) -> Option<i64>,
+) -> Vec<&T> {
+    let ids: BTreeSet<i64> = items.iter().map(&id).collect();
+    let mut placed = BTreeSet::new();
+    let mut ordered = Vec::with_capacity(items.len());
+    let mut remaining: Vec<&T> = items.iter().collect();
+    while !remaining.is_empty() {
+        let (ready, rest): (Vec<&T>, Vec<&T>) = remaining.into_iter().partition(|item| {
+            parent_id(item).is_none_or(|parent| !ids.contains(&parent) || placed.contains(&parent))
+        });
+        if ready.is_empty() {
+            ordered.extend(rest);
+            break;
+        }
+        placed.extend(ready.iter().map(|item| id(item)));
+        ordered.extend(ready);
+        remaining = rest;
+    }
+    ordered
+}
+
+async fn export_settings(pool: &SqlitePool) -> Result<BundleSettings, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+
+    let languages = sqlx::query_as::<_, Language>("SELECT * FROM languages ORDER BY code")
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|language| BundleLanguage {
+            code: language.code,
+            name: language.name,
+            direction: language.direction,
+            is_default: language.is_default,
+            enabled: language.enabled,
+            fallback: language.fallback,
+        })
+        .collect();
+
+    let mut bundle_types = Vec::new();
+    let types = sqlx::query_as::<_, ContentType>("SELECT * FROM content_types ORDER BY id")
+        .fetch_all(&mut *conn)
+        .await?;
+    for content_type in types {
+        let fields = content_types::fields_of(&mut conn, content_type.id).await?;
+        bundle_types.push(BundleContentType {
+            name: content_type.name,
+            label: content_type.label,
+            fields: fields
+                .into_iter()
+                .map(|field| FieldSpec {
+                    name: field.name,
+                    label: Some(field.label),
+                    field_type: field.field_type,
+                    item_type: field.item_type,
+                    required: Some(field.required),
+                })
+                .collect(),
+        });
+    }
+
+    let mut bundle_taxonomies = Vec::new();
+    let all_taxonomies = sqlx::query_as::<_, Taxonomy>("SELECT * FROM taxonomies ORDER BY id")
+        .fetch_all(&mut *conn)
+        .await?;
+    for taxonomy in all_taxonomies {
+        let terms = sqlx::query_as::<_, Term>(
+            "SELECT * FROM terms WHERE taxonomy_id = ? ORDER BY display_order, id",
+        )
+        .bind(taxonomy.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut names = taxonomies::names(&mut conn, Some(taxonomy.id)).await?;
+        bundle_taxonomies.push(BundleTaxonomy {
+            name: taxonomy.name,
+            hierarchical: taxonomy.hierarchical,
+            terms: terms
+                .into_iter()
+                .map(|term| BundleTerm {
+                    names: names.remove(&term.id).unwrap_or_default(),
+                    id: term.id,
+                    parent_id: term.parent_id,
+                    slug: term.slug,
+                    name: term.name,
+                    display_order: term.display_order,
+                })
+                .collect(),
+        });
+    }
+
+    let mut bundle_menus = Vec::new();
+    let all_menus = sqlx::query_as::<_, Menu>("SELECT * FROM menus ORDER BY id")
+        .fetch_all(&mut *conn)
+        .await?;
+    for menu in all_menus {
+        let items = sqlx::query_as::<_, MenuItem>(
+            "SELECT * FROM menu_items WHERE menu_id = ? ORDER BY display_order, id",
+        )
+        .bind(menu.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut labels = menus::labels(&mut conn, menu.id).await?;
+        bundle_menus.push(BundleMenu {
+            name: menu.name,
+            location: menu.location,
+            items: items
+                .into_iter()
+                .map(|item| BundleMenuItem {
+                    labels: labels.remove(&item.id).unwrap_or_default(),
+                    id: item.id,
+                    parent_id: item.parent_id,
+                    display_order: item.display_order,
+                    link_type: item.link_type,
+                    page_id: item.page_id,
+                    url: item.url,
+                    anchor: item.anchor,
+                    label: item.label,
+                    visible: item.visible,
+                    langs: item.langs,
+                })
+                .collect(),
+        });
+    }
+
+    Ok(BundleSettings {
+        languages,
+        content_types: bundle_types,
+        taxonomies: bundle_taxonomies,
+        menus: bundle_menus,
+    })
+}
+
+/// The terms a page or content is tagged with, by taxonomy name and slug.
+async fn term_refs(
+    pool: &SqlitePool,
+    tagged: Tagged,
+    id: i64,
+) -> Result<Vec<BundleTermRef>, sqlx::Error> {
+    let query = match tagged {
+        Tagged::Content => {
+            "SELECT x.name, t.slug FROM content_terms a JOIN terms t ON t.id = a.term_id
+             JOIN taxonomies x ON x.id = t.taxonomy_id WHERE a.content_id = ?
+             ORDER BY x.name, t.slug"
+        }
+        Tagged::Page => {
+            "SELECT x.name, t.slug FROM page_terms a JOIN terms t ON t.id = a.term_id
+             JOIN taxonomies x ON x.id = t.taxonomy_id WHERE a.page_id = ?
+             ORDER BY x.name, t.slug"
+        }
+    };
+    let rows: Vec<(String, String)> = sqlx::query_as(query).bind(id).fetch_all(pool).await?;
+    Ok(rows
+        .into_iter()
+        .map(|(taxonomy, slug)| BundleTermRef { taxonomy, slug })
+        .collect())
+}
+
+/// Registers the bundle's languages that are missing here. Existing ones
+/// keep their settings, and the default language only changes if there is
+/// none.
+async fn import_languages(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    bundle_languages: &[BundleLanguage],
+    report: &mut ImportReport,
+) -> Result<(), BundleError> {
+    let mut has_default = languages::default_code(&mut *conn).await?.is_some();
+    for language in bundle_languages {
+        let valid = languages::normalize_tag(&language.code).as_deref() == Some(&language.code)
+            && DIRECTIONS.contains(&language.direction.as_str());
+        if !valid {
+            return Err(BundleError::Invalid(format!(
+                "language '{}' is not valid",
+                language.code
+            )));
+        }
+        if languages::fetch(&mut *conn, &language.code)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+
+        let is_default = language.is_default && !has_default;
+        let created = sqlx::query_as::<_, Language>(
+            "INSERT INTO languages (code, name, direction, is_default, enabled, fallback)
+             VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&language.code)
+        .bind(&language.name)
+        .bind(&language.direction)
+        .bind(is_default)
+        .bind(language.enabled)
+        .bind(&language.fallback)
+        .fetch_one(&mut *conn)
+        .await?;
+        has_default |= is_default;
+        changes::record(
+            conn,
+            context,
+            "create",
+            "language",
+            &created.code,
+            None::<&Language>,
+            Some(&created),
+        )
+        .await?;
+        report.languages_created += 1;
+    }
+    Ok(())
+}
+
+/// Creates the bundle's content types that are missing here. A type that
+/// exists keeps its fields.
+async fn import_content_types(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    bundle_types: &[BundleContentType],
+    report: &mut ImportReport,
+) -> Result<(), BundleError> {
+    for bundle_type in bundle_types {
+        content_types::check_name(&bundle_type.name)
+            .and_then(|()| content_types::check_specs(&bundle_type.fields))
+            .map_err(BundleError::Invalid)?;
+        let existing: Option<i64> =
+            sqlx::query_scalar("SELECT id FROM content_types WHERE name = ?")
+                .bind(&bundle_type.name)
+                .fetch_optional(&mut *conn)
+                .await?;
+        if existing.is_some() {
+            continue;
+        }
+
+        let content_type = sqlx::query_as::<_, ContentType>(
+            "INSERT INTO content_types (name, label) VALUES (?, ?) RETURNING *",
+        )
+        .bind(&bundle_type.name)
+        .bind(&bundle_type.label)
+        .fetch_one(&mut *conn)
+        .await?;
+        content_types::set_fields(conn, content_type.id, &bundle_type.fields).await?;
+        let fields = content_types::fields_of(conn, content_type.id).await?;
+        let created = ContentTypeDetail {
+            content_type,
+            fields,
+        };
+        changes::record(
+            conn,
+            context,
+            "create",
+            "content_type",
+            created.content_type.id,
+            None::<&ContentTypeDetail>,
+            Some(&created),
+        )
+        .await?;
+        report.content_types_created += 1;
+    }
+    Ok(())
+}
+
+/// Creates the bundle's taxonomies and terms that are missing here, matched
+/// by name and by slug. Existing terms keep their names and place.
+async fn import_taxonomies(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    bundle_taxonomies: &[BundleTaxonomy],
+    report: &mut ImportReport,
+) -> Result<(), BundleError> {
+    for bundle_taxonomy in bundle_taxonomies {
+        let existing = sqlx::query_as::<_, Taxonomy>("SELECT * FROM taxonomies WHERE name = ?")
+            .bind(&bundle_taxonomy.name)
+            .fetch_optional(&mut *conn)
+            .await?;
+        let taxonomy = match existing {
+            Some(taxonomy) => taxonomy,
+            None => {
+                let taxonomy = sqlx::query_as::<_, Taxonomy>(
+                    "INSERT INTO taxonomies (name, hierarchical) VALUES (?, ?) RETURNING *",
+                )
+                .bind(&bundle_taxonomy.name)
+                .bind(bundle_taxonomy.hierarchical)
+                .fetch_one(&mut *conn)
+                .await?;
+                changes::record(
+                    conn,
+                    context,
+                    "create",
+                    "taxonomy",
+                    taxonomy.id,
+                    None::<&Taxonomy>,
+                    Some(&taxonomy),
+                )
+                .await?;
+                report.taxonomies_created += 1;
+                taxonomy
+            }
+        };
+
+        let mut term_ids: BTreeMap<i64, i64> = BTreeMap::new();
+        for term in parents_first(&bundle_taxonomy.terms, |t| t.id, |t| t.parent_id) {
+            let slug = page_tree::normalize_slug(&term.slug).map_err(tree_error)?;
+            let existing: Option<i64> =
+                sqlx::query_scalar("SELECT id FROM terms WHERE taxonomy_id = ? AND slug = ?")
+                    .bind(taxonomy.id)
+                    .bind(&slug)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+            if let Some(existing) = existing {
+                term_ids.insert(term.id, existing);
+                continue;
+            }
+
+            let parent_id = term
+                .parent_id
+                .filter(|_| taxonomy.hierarchical)
+                .and_then(|id| term_ids.get(&id).copied());
+            let created = sqlx::query_as::<_, Term>(
+                "INSERT INTO terms (taxonomy_id, parent_id, slug, name, display_order)
+                 VALUES (?, ?, ?, ?, ?) RETURNING *",
+            )
+            .bind(taxonomy.id)
+            .bind(parent_id)
+            .bind(&slug)
+            .bind(&term.name)
+            .bind(term.display_order)
+            .fetch_one(&mut *conn)
+            .await?;
+            taxonomies::set_names(conn, created.id, &term.names).await?;
+            changes::record(
+                conn,
+                context,
+                "create",
+                "term",
+                created.id,
+                None::<&Term>,
+                Some(&created),
+            )
+            .await?;
+            term_ids.insert(term.id, created.id);
+            report.terms_created += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Tags an imported page or content with `terms`, which must exist by now.
+async fn tag(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    tagged: Tagged,
+    id: i64,
+    terms: &[BundleTermRef],
+) -> Result<(), BundleError> {
+    if terms.is_empty() {
+        return Ok(());
+    }
+    let mut term_ids = Vec::with_capacity(terms.len());
+    for term in terms {
+        let term_id: Option<i64> = sqlx::query_scalar(
+            "SELECT t.id FROM terms t JOIN taxonomies x ON x.id = t.taxonomy_id
+             WHERE x.name = ? AND t.slug = ?",
+        )
+        .bind(&term.taxonomy)
+        .bind(&term.slug)
+        .fetch_optional(&mut *conn)
+        .await?;
+        term_ids.push(term_id.ok_or_else(|| {
+            BundleError::Invalid(format!(
+                "Term '{}' of taxonomy '{}' does not exist",
+                term.slug, term.taxonomy
+            ))
+        })?);
+    }
+
+    taxonomies::assign(conn, tagged, id, &term_ids)
+        .await
+        .map_err(taxonomy_error)?;
+    let after = taxonomies::assigned(conn, tagged, id).await?;
+    let entity = match tagged {
+        Tagged::Content => "content_terms",
+        Tagged::Page => "page_terms",
+    };
+    changes::record(
+        conn,
+        context,
+        "update",
+        entity,
+        id,
+        Some(&Vec::<Term>::new()),
+        Some(&after),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Creates the bundle's menus, with page items linking to the imported
+/// pages. A menu that exists here is kept in a merge; a replace swaps it for
+/// the bundle's, since the pages its items led to are gone.
+async fn import_menus(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    bundle_menus: &[BundleMenu],
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> Result<(), BundleError> {
+    for bundle_menu in bundle_menus {
+        if !LOCATIONS.contains(&bundle_menu.location.as_str()) {
+            return Err(BundleError::Invalid(format!(
+                "menu '{}' has unknown location '{}'",
+                bundle_menu.name, bundle_menu.location
+            )));
+        }
+        let existing = sqlx::query_as::<_, Menu>("SELECT * FROM menus WHERE name = ?")
+            .bind(&bundle_menu.name)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if let Some(existing) = existing {
+            if mode == ImportMode::Merge {
+                continue;
+            }
+            sqlx::query("DELETE FROM menus WHERE id = ?")
+                .bind(existing.id)
+                .execute(&mut *conn)
+                .await?;
+            changes::record(
+                conn,
+                context,
+                "delete",
+                "menu",
+                existing.id,
+                Some(&existing),
+                None::<&Menu>,
+            )
+            .await?;
+        }
+
+        let menu = sqlx::query_as::<_, Menu>(
+            "INSERT INTO menus (name, location) VALUES (?, ?) RETURNING *",
+        )
+        .bind(&bundle_menu.name)
+        .bind(&bundle_menu.location)
+        .fetch_one(&mut *conn)
+        .await?;
+        changes::record(
+            conn,
+            context,
+            "create",
+            "menu",
+            menu.id,
+            None::<&Menu>,
+            Some(&menu),
+        )
+        .await?;
+        report.menus_created += 1;
+
+        let mut item_ids: BTreeMap<i64, i64> = BTreeMap::new();
+        for item in parents_first(&bundle_menu.items, |i| i.id, |i| i.parent_id) {
+            if !LINK_TYPES.contains(&item.link_type.as_str()) {
+                return Err(BundleError::Invalid(format!(
+                    "menu '{}' has an item with unknown link_type '{}'",
+                    bundle_menu.name, item.link_type
+                )));
+            }
+            let created = sqlx::query_as::<_, MenuItem>(
+                "INSERT INTO menu_items (menu_id, parent_id, display_order, link_type, page_id,
+                 url, anchor, label, visible, langs)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+            )
+            .bind(menu.id)
+            .bind(item.parent_id.and_then(|id| item_ids.get(&id).copied()))
+            .bind(item.display_order)
+            .bind(&item.link_type)
+            // Items whose page was not imported keep no link.
+            .bind(
+                item.page_id
+                    .and_then(|id| report.page_ids.get(&id).copied()),
+            )
+            .bind(&item.url)
+            .bind(&item.anchor)
+            .bind(&item.label)
+            .bind(item.visible)
+            .bind(&item.langs)
+            .fetch_one(&mut *conn)
+            .await?;
+            menus::set_labels(conn, created.id, &item.labels).await?;
+            changes::record(
+                conn,
+                context,
+                "create",
+                "menu_item",
+                created.id,
+                None::<&MenuItem>,
+                Some(&created),
+            )
+            .await?;
+            item_ids.insert(item.id, created.id);
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the bundle's images and decides where each one goes. Identical
+/// files already on disk are not written again.
+fn plan_images(
+    upload_dir: &Path,
+    images: &[BundleImage],
+    report: &mut ImportReport,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, BundleError> {
+    let mut planned = Vec::new();
+    for image in images {
+        if image.filename.is_empty()
+            || sanitize_filename::sanitize(&image.filename) != image.filename
+        {
+            return Err(BundleError::Invalid(format!(
+                "invalid image filename '{}'",
+                image.filename
+            )));
+        }
+
+        let data = Base64::decode_vec(&image.data).map_err(|_| {
+            BundleError::Invalid(format!("image '{}' is not valid base64", image.filename))
+        })?;
+
+        let path = upload_dir.join(&image.filename);
+        match std::fs::read(&path) {
+            Ok(existing) if existing == data => continue,
+            Ok(_) => {
+                let filename = unique_filename(&image.filename);
+                report
+                    .images_renamed
+                    .insert(image.filename.clone(), filename.clone());
+                planned.push((upload_dir.join(filename), data));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => planned.push((path, data)),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(planned)
+}
@@ -0,0 +1,132 @@
+use serde_json::Value;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::models::{ChangeEvent, ChangeQuery};
+
+/// Number of events kept for clients resuming with `Last-Event-ID`.
+const RETAINED_EVENTS: i64 = 1000;
+
+/// Appends a page, content or agent change to the live feed on the caller's
+/// connection, so streams only ever see committed changes. Other entities are
+/// ignored.
+pub async fn append(
+    conn: &mut SqliteConnection,
+    entity: &str,
+    action: &str,
+    entity_id: &str,
+    agent_id: Option<i64>,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) -> Result<(), sqlx::Error> {
+    if !matches!(entity, "page" | "content" | "agent") {
+        return Ok(());
+    }
+
+    let event_type = match action {
+        "create" => format!("{}.created", entity),
+        "delete" => format!("{}.deleted", entity),
+        _ => format!("{}.updated", entity),
+    };
+
+    let Some(state) = after.or(before) else {
+        return Ok(());
+    };
+
+    // Contents are filtered by the page they belong to.
+    let scope: Option<(String, String, String)> = match entity {
+        "page" => Some((
+            state["page_name"].as_str().unwrap_or_default().to_string(),
+            state["section_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            state["lang"].as_str().unwrap_or_default().to_string(),
+        )),
+        "content" => {
+            sqlx::query_as("SELECT page_name, section_name, lang FROM pages WHERE id = ?")
+                .bind(state["ref_id"].as_i64())
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+        _ => None,
+    };
+    let (page_name, section_name, lang) = match scope {
+        Some((page_name, section_name, lang)) => (Some(page_name), Some(section_name), Some(lang)),
+        None => (None, None, None),
+    };
+
+    let payload = serde_json::json!({
+        "event": event_type,
+        "action": action,
+        "entity": entity,
+        "entity_id": entity_id,
+        "agent_id": agent_id,
+        "data": after,
+        "previous": before,
+    });
+
+    let id = sqlx::query(
+        "INSERT INTO change_events
+         (event_type, entity, entity_id, page_name, section_name, lang, payload)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&event_type)
+    .bind(entity)
+    .bind(entity_id)
+    .bind(page_name)
+    .bind(section_name)
+    .bind(lang)
+    .bind(payload.to_string())
+    .execute(&mut *conn)
+    .await?
+    .last_insert_rowid();
+
+    sqlx::query("DELETE FROM change_events WHERE id <= ?")
+        .bind(id - RETAINED_EVENTS)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Events after `after` that match the query's filters, oldest first.
+pub async fn fetch_since(
+    pool: &SqlitePool,
+    query: &ChangeQuery,
+    after: i64,
+    limit: i64,
+) -> Result<Vec<ChangeEvent>, sqlx::Error> {
+    sqlx::query_as::<_, ChangeEvent>(
+        "SELECT * FROM change_events
+         WHERE id > ?
+         AND (? IS NULL OR page_name = ?)
+         AND (? IS NULL OR section_name = ?)
+         AND (? IS NULL OR lang = ?)
+         ORDER BY id
+         LIMIT ?",
+    )
+    .bind(after)
+    .bind(&query.page_name)
+    .bind(&query.page_name)
+    .bind(&query.section_name)
+    .bind(&query.section_name)
+    .bind(&query.lang)
+    .bind(&query.lang)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Id of the newest event, or 0 when the feed is empty.
+pub async fn latest_id(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM change_events")
+        .fetch_one(pool)
+        .await
+}
+
+/// Id of the oldest retained event, or `None` when the feed is empty.
+pub async fn oldest_id(pool: &SqlitePool) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT MIN(id) FROM change_events")
+        .fetch_one(pool)
+        .await
+}
@@ -0,0 +1,97 @@
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqliteConnection;
+
+use crate::audit::{self, AuditContext};
+use crate::change_feed;
+use crate::webhooks;
+
+/// Records a mutation on the caller's transaction: appends it to the audit log
+/// and the live change feed, and queues the matching webhook events in the
+/// outbox. Pass `None` for the side that does not exist (before a create,
+/// after a delete).
+pub async fn record<B, A>(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    action: &str,
+    entity: &str,
+    entity_id: impl ToString,
+    before: Option<&B>,
+    after: Option<&A>,
+) -> Result<(), sqlx::Error>
+where
+    B: Serialize,
+    A: Serialize,
+{
+    let entity_id = entity_id.to_string();
+    let before = before.and_then(|state| serde_json::to_value(state).ok());
+    let after = after.and_then(|state| serde_json::to_value(state).ok());
+
+    audit::record(
+        &mut *conn,
+        context,
+        action,
+        entity,
+        &entity_id,
+        before.as_ref(),
+        after.as_ref(),
+    )
+    .await?;
+
+    change_feed::append(
+        &mut *conn,
+        entity,
+        action,
+        &entity_id,
+        context.agent_id,
+        before.as_ref(),
+        after.as_ref(),
+    )
+    .await?;
+
+    for event_type in webhook_events(entity, action, before.as_ref(), after.as_ref()) {
+        let payload = serde_json::json!({
+            "event": event_type,
+            "entity": entity,
+            "entity_id": entity_id,
+            "agent_id": context.agent_id,
+            "data": after.as_ref().or(before.as_ref()),
+            "previous": before,
+            "occurred_at": chrono::Utc::now().to_rfc3339(),
+        });
+        webhooks::enqueue(&mut *conn, &event_type, &payload).await?;
+    }
+
+    Ok(())
+}
+
+/// Webhook event names for a change; agents are not part of the content
+/// lifecycle and emit none.
+fn webhook_events(
+    entity: &str,
+    action: &str,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) -> Vec<String> {
+    if !matches!(entity, "page" | "content" | "image") {
+        return Vec::new();
+    }
+
+    let mut events = match (entity, action) {
+        ("image", "create") => vec!["image.uploaded".to_string()],
+        (_, "create") => vec![format!("{}.created", entity)],
+        (_, "delete") => vec![format!("{}.deleted", entity)],
+        _ => vec![format!("{}.updated", entity)],
+    };
+
+    let visible = |state: Option<&Value>| state.and_then(|s| s["visible"].as_bool());
+    if entity == "page"
+        && action != "delete"
+        && visible(after) == Some(true)
+        && visible(before) != Some(true)
+    {
+        events.push("page.published".to_string());
+    }
+
+    events
+}
@@ -1,6 +1,72 @@
+use std::env;
+
 #[derive(Clone)]
 pub struct AppConfig {
     pub jwt_secret: String,
     pub jwt_expiration: i64,
     pub upload_dir: String,
+    pub webhook_poll_interval: u64,
+    pub webhook_max_attempts: i64,
+    /// Days finished webhook events are kept; `0` keeps them forever.
+    pub webhook_retention_days: i64,
+    pub backup: BackupConfig,
+    /// Origin prepended to links in rendered HTML; empty keeps them relative.
+    pub public_base_url: String,
+    /// Author named in feeds; empty uses the feed's title.
+    pub feed_author: String,
+    /// Directory whose templates replace the built-in ones of the same name.
+    pub template_dir: Option<String>,
+    pub site: SiteConfig,
+}
+
+/// Where snapshots go and how long they are kept. Shared by the server's
+/// scheduled backups and `ccs-admin`.
+#[derive(Clone)]
+pub struct BackupConfig {
+    pub dir: String,
+    /// Hours between scheduled snapshots; 0 disables them.
+    pub interval_hours: u64,
+    /// Newest snapshots that are never pruned.
+    pub keep_last: usize,
+    /// Older snapshots beyond `keep_last` are pruned after this many days.
+    pub max_age_days: i64,
+}
+
+impl BackupConfig {
+    pub fn from_env() -> Self {
+        BackupConfig {
+            dir: env::var("BACKUP_DIR").unwrap_or_else(|_| "../data/backups".to_string()),
+            interval_hours: env::var("BACKUP_INTERVAL_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .expect("BACKUP_INTERVAL_HOURS must be a number"),
+            keep_last: env::var("BACKUP_KEEP_LAST")
+                .unwrap_or_else(|_| "7".to_string())
+                .parse()
+                .expect("BACKUP_KEEP_LAST must be a number"),
+            max_age_days: env::var("BACKUP_MAX_AGE_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("BACKUP_MAX_AGE_DAYS must be a number"),
+        }
+    }
+}
+
+/// Where static site builds are written and the origin they are served
+/// from. Shared by the server's build endpoint and `ccs-admin`.
+#[derive(Clone)]
+pub struct SiteConfig {
+    pub dir: String,
+    /// Origin prepended to links in the built site; empty keeps them
+    /// root-relative.
+    pub base_url: String,
+}
+
+impl SiteConfig {
+    pub fn from_env() -> Self {
+        SiteConfig {
+            dir: env::var("SITE_DIR").unwrap_or_else(|_| "../data/site".to_string()),
+            base_url: env::var("SITE_BASE_URL").unwrap_or_default(),
+        }
+    }
 }
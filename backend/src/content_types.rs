@@ -0,0 +1,371 @@
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+use sqlx::SqliteConnection;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::models::content_type::{ContentTypeField, FieldSpec, FIELD_TYPES};
+use crate::models::Content;
+use crate::{render, rich_text};
+
+#[derive(Debug)]
+pub enum ContentTypeError {
+    /// Malformed definition, or field values that do not match it.
+    Invalid(String),
+    /// A new definition does not fit contents that already use the type.
+    Conflict(String, Vec<i64>),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for ContentTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentTypeError::Invalid(message) | ContentTypeError::Conflict(message, _) => {
+                write!(f, "{}", message)
+            }
+            ContentTypeError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ContentTypeError {}
+
+impl From<sqlx::Error> for ContentTypeError {
+    fn from(e: sqlx::Error) -> Self {
+        ContentTypeError::Database(e)
+    }
+}
+
+/// Type and field names end up as keys in templates and the public API, so
+/// they are limited to lowercase letters, digits and `_`, starting with a
+/// letter.
+pub fn check_name(name: &str) -> Result<(), String> {
+    let valid = name.len() <= 50
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err(format!(
+            "'{}' is not a valid name: use up to 50 lowercase letters, digits and '_', starting with a letter",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Checks a field list: valid, distinct names, known types, and an
+/// `item_type` exactly on `list` fields.
+pub fn check_specs(specs: &[FieldSpec]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for spec in specs {
+        check_name(&spec.name)?;
+        if !names.insert(spec.name.as_str()) {
+            return Err(format!("Field '{}' is defined more than once", spec.name));
+        }
+        if !FIELD_TYPES.contains(&spec.field_type.as_str()) {
+            return Err(format!(
+                "Field '{}' has unknown field_type '{}'",
+                spec.name, spec.field_type
+            ));
+        }
+        match (spec.field_type.as_str(), spec.item_type.as_deref()) {
+            ("list", Some(item_type))
+                if item_type != "list" && FIELD_TYPES.contains(&item_type) => {}
+            ("list", _) => {
+                return Err(format!(
+                    "List field '{}' needs an item_type other than 'list'",
+                    spec.name
+                ))
+            }
+            (_, Some(_)) => {
+                return Err(format!(
+                    "Field '{}' is not a list and cannot have an item_type",
+                    spec.name
+                ))
+            }
+            (_, None) => {}
+        }
+        if let Some(label) = &spec.label {
+            if label.trim().is_empty() || label.chars().count() > 100 {
+                return Err(format!(
+                    "Field '{}' needs a label of 1 to 100 characters",
+                    spec.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fields of `type_id` in `display_order`.
+pub async fn fields_of(
+    conn: &mut SqliteConnection,
+    type_id: i64,
+) -> Result<Vec<ContentTypeField>, sqlx::Error> {
+    sqlx::query_as::<_, ContentTypeField>(
+        "SELECT * FROM content_type_fields WHERE type_id = ? ORDER BY display_order, id",
+    )
+    .bind(type_id)
+    .fetch_all(conn)
+    .await
+}
+
+/// Replaces the fields of `type_id` with `specs`, in their order.
+pub async fn set_fields(
+    conn: &mut SqliteConnection,
+    type_id: i64,
+    specs: &[FieldSpec],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM content_type_fields WHERE type_id = ?")
+        .bind(type_id)
+        .execute(&mut *conn)
+        .await?;
+    for (position, spec) in specs.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO content_type_fields (type_id, name, label, field_type, item_type,
+             required, display_order)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(type_id)
+        .bind(&spec.name)
+        .bind(spec.label.as_deref().map(str::trim).unwrap_or(&spec.name))
+        .bind(&spec.field_type)
+        .bind(&spec.item_type)
+        .bind(spec.required.unwrap_or(false))
+        .bind(position as i32)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// `value` as stored for a single value of `field_type`: rich text is
+/// sanitized and dates are written as `YYYY-MM-DD`.
+async fn check_value(
+    conn: &mut SqliteConnection,
+    field: &str,
+    field_type: &str,
+    value: &Value,
+) -> Result<Value, ContentTypeError> {
+    let mismatch = |expected: &str| {
+        ContentTypeError::Invalid(format!("Field '{}' must be {}", field, expected))
+    };
+    match field_type {
+        "text" => value
+            .as_str()
+            .map(|_| value.clone())
+            .ok_or_else(|| mismatch("a string")),
+        "rich_text" => value
+            .as_str()
+            .map(|html| Value::String(rich_text::sanitize(html)))
+            .ok_or_else(|| mismatch("an HTML string")),
+        "number" => value
+            .is_number()
+            .then(|| value.clone())
+            .ok_or_else(|| mismatch("a number")),
+        "boolean" => value
+            .is_boolean()
+            .then(|| value.clone())
+            .ok_or_else(|| mismatch("true or false")),
+        "date" => value
+            .as_str()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+            .ok_or_else(|| mismatch("a date as YYYY-MM-DD")),
+        "image" => value
+            .as_str()
+            .filter(|image| render::is_external(image) || render::stored_file(image).is_some())
+            .map(|_| value.clone())
+            .ok_or_else(|| mismatch("an uploaded image file name or an http(s) URL")),
+        "page" => {
+            let page_id = value.as_i64().ok_or_else(|| mismatch("a page id"))?;
+            let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM pages WHERE id = ?")
+                .bind(page_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+            match exists {
+                Some(_) => Ok(value.clone()),
+                None => Err(ContentTypeError::Invalid(format!(
+                    "Field '{}' refers to page {}, which does not exist",
+                    field, page_id
+                ))),
+            }
+        }
+        _ => Err(mismatch(field_type)),
+    }
+}
+
+/// `value` checked against `field`, with list elements checked one by one.
+async fn check_field(
+    conn: &mut SqliteConnection,
+    field: &ContentTypeField,
+    value: &Value,
+) -> Result<Value, ContentTypeError> {
+    match (field.field_type.as_str(), field.item_type.as_deref()) {
+        ("list", Some(item_type)) => {
+            let items = value.as_array().ok_or_else(|| {
+                ContentTypeError::Invalid(format!("Field '{}' must be a list", field.name))
+            })?;
+            let mut checked = Vec::with_capacity(items.len());
+            for item in items {
+                checked.push(check_value(conn, &field.name, item_type, item).await?);
+            }
+            Ok(Value::Array(checked))
+        }
+        (field_type, _) => check_value(conn, &field.name, field_type, value).await,
+    }
+}
+
+/// Checks `fields` against content type `type_id` and returns them as
+/// stored. Unknown fields and missing required ones are rejected; `null`
+/// values are dropped. A content without a type cannot have fields.
+pub async fn prepare(
+    conn: &mut SqliteConnection,
+    type_id: Option<i64>,
+    fields: Option<&Value>,
+) -> Result<Option<String>, ContentTypeError> {
+    let empty = Map::new();
+    let values = match fields {
+        None | Some(Value::Null) => &empty,
+        Some(Value::Object(values)) => values,
+        Some(_) => {
+            return Err(ContentTypeError::Invalid(
+                "fields must be an object".to_string(),
+            ))
+        }
+    };
+
+    let Some(type_id) = type_id else {
+        if values.is_empty() {
+            return Ok(None);
+        }
+        return Err(ContentTypeError::Invalid(
+            "fields need a type_id".to_string(),
+        ));
+    };
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM content_types WHERE id = ?")
+        .bind(type_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if exists.is_none() {
+        return Err(ContentTypeError::Invalid(format!(
+            "Content type {} not found",
+            type_id
+        )));
+    }
+
+    let definitions = fields_of(&mut *conn, type_id).await?;
+    if let Some(unknown) = values
+        .keys()
+        .find(|name| !definitions.iter().any(|field| field.name == **name))
+    {
+        return Err(ContentTypeError::Invalid(format!(
+            "Unknown field '{}'",
+            unknown
+        )));
+    }
+
+    let mut prepared = Map::new();
+    for field in &definitions {
+        match values.get(&field.name) {
+            None | Some(Value::Null) if field.required => {
+                return Err(ContentTypeError::Invalid(format!(
+                    "Field '{}' is required",
+                    field.name
+                )))
+            }
+            None | Some(Value::Null) => {}
+            Some(value) => {
+                let value = check_field(&mut *conn, field, value).await?;
+                prepared.insert(field.name.clone(), value);
+            }
+        }
+    }
+    Ok(Some(Value::Object(prepared).to_string()))
+}
+
+/// Brings contents of `type_id` in line with its current fields after they
+/// were redefined: values of removed fields are dropped, which gives the
+/// content a new version. Fails with the ids of contents holding values the
+/// new field types do not accept. Required fields are only enforced on the
+/// next write of each content.
+pub async fn conform(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    type_id: i64,
+) -> Result<(), ContentTypeError> {
+    let definitions = fields_of(&mut *conn, type_id).await?;
+    let contents = sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE type_id = ?")
+        .bind(type_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let mut mismatched = Vec::new();
+    let mut message = None;
+    for content in contents {
+        let values: Map<String, Value> = content
+            .fields
+            .as_deref()
+            .and_then(|fields| serde_json::from_str(fields).ok())
+            .unwrap_or_default();
+
+        let mut conformed = Map::new();
+        let mut failed = false;
+        for (name, value) in &values {
+            let Some(field) = definitions.iter().find(|field| field.name == *name) else {
+                continue;
+            };
+            match check_field(&mut *conn, field, value).await {
+                Ok(value) => {
+                    conformed.insert(name.clone(), value);
+                }
+                Err(ContentTypeError::Invalid(e)) => {
+                    message.get_or_insert(e);
+                    failed = true;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if failed {
+            mismatched.push(content.id);
+            continue;
+        }
+        if conformed == values {
+            continue;
+        }
+
+        let updated = sqlx::query_as::<_, Content>(
+            "UPDATE contents SET fields = ?, version = version + 1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(Value::Object(conformed).to_string())
+        .bind(content.id)
+        .fetch_one(&mut *conn)
+        .await?;
+        changes::record(
+            &mut *conn,
+            context,
+            "update",
+            "content",
+            content.id,
+            Some(&content),
+            Some(&updated),
+        )
+        .await?;
+    }
+
+    if !mismatched.is_empty() {
+        return Err(ContentTypeError::Conflict(
+            format!(
+                "{} contents have values the new fields do not accept: {}",
+                mismatched.len(),
+                message.unwrap_or_default()
+            ),
+            mismatched,
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use sqlx::SqliteConnection;
+use std::fmt;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::content_types::{self, ContentTypeError};
+use crate::galleries;
+use crate::models::{Content, ContentFields};
+use crate::rich_text;
+
+#[derive(Debug)]
+pub enum ContentError {
+    /// Unknown page or format, or custom fields that do not fit the content
+    /// type.
+    Invalid(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::Invalid(message) => write!(f, "{}", message),
+            ContentError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+impl From<sqlx::Error> for ContentError {
+    fn from(e: sqlx::Error) -> Self {
+        ContentError::Database(e)
+    }
+}
+
+impl From<ContentTypeError> for ContentError {
+    fn from(e: ContentTypeError) -> Self {
+        match e {
+            ContentTypeError::Database(e) => ContentError::Database(e),
+            e => ContentError::Invalid(e.to_string()),
+        }
+    }
+}
+
+/// `long_desc` as stored with its rendered HTML, and custom `fields` checked
+/// against the content type.
+type Prepared = (Option<String>, Option<String>, Option<String>);
+
+async fn prepare(
+    conn: &mut SqliteConnection,
+    fields: &ContentFields,
+) -> Result<Prepared, ContentError> {
+    let page: Option<i64> = sqlx::query_scalar("SELECT id FROM pages WHERE id = ?")
+        .bind(fields.ref_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if page.is_none() {
+        return Err(ContentError::Invalid(format!(
+            "Page {} does not exist",
+            fields.ref_id
+        )));
+    }
+    let (long_desc, long_desc_html) =
+        rich_text::prepare(&fields.format, fields.long_desc.as_deref())
+            .map_err(ContentError::Invalid)?;
+    let custom_fields =
+        content_types::prepare(conn, fields.type_id, fields.fields.as_ref()).await?;
+    Ok((long_desc, long_desc_html, custom_fields))
+}
+
+/// Creates a content from `fields` and records it.
+pub async fn create(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    fields: &ContentFields,
+) -> Result<Content, ContentError> {
+    let (long_desc, long_desc_html, custom_fields) = prepare(conn, fields).await?;
+
+    let content = sqlx::query_as::<_, Content>(
+        "INSERT INTO contents (ref_id, short_desc, long_desc, format, long_desc_html, image_path,
+         title, display_order, type_id, fields)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(fields.ref_id)
+    .bind(&fields.short_desc)
+    .bind(&long_desc)
+    .bind(&fields.format)
+    .bind(&long_desc_html)
+    .bind(&fields.image_path)
+    .bind(&fields.title)
+    .bind(fields.display_order)
+    .bind(fields.type_id)
+    .bind(&custom_fields)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    changes::record(
+        conn,
+        context,
+        "create",
+        "content",
+        content.id,
+        None::<&Content>,
+        Some(&content),
+    )
+    .await?;
+    Ok(content)
+}
+
+/// Writes `fields` over `existing` if its version is still current, and
+/// records the change. `None` when the content changed or went away since
+/// it was read.
+pub async fn update(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    existing: &Content,
+    fields: &ContentFields,
+) -> Result<Option<Content>, ContentError> {
+    let (long_desc, long_desc_html, custom_fields) = prepare(conn, fields).await?;
+
+    let updated = sqlx::query_as::<_, Content>(
+        "UPDATE contents SET ref_id = ?, short_desc = ?, long_desc = ?, format = ?,
+         long_desc_html = ?, image_path = ?, title = ?, display_order = ?, type_id = ?,
+         fields = ?, version = version + 1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND version = ? RETURNING *",
+    )
+    .bind(fields.ref_id)
+    .bind(&fields.short_desc)
+    .bind(&long_desc)
+    .bind(&fields.format)
+    .bind(&long_desc_html)
+    .bind(&fields.image_path)
+    .bind(&fields.title)
+    .bind(fields.display_order)
+    .bind(fields.type_id)
+    .bind(&custom_fields)
+    .bind(existing.id)
+    .bind(existing.version)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(updated) = updated else {
+        return Ok(None);
+    };
+    changes::record(
+        conn,
+        context,
+        "update",
+        "content",
+        existing.id,
+        Some(existing),
+        Some(&updated),
+    )
+    .await?;
+    Ok(Some(updated))
+}
+
+/// Deletes `existing` if its version is still current, and records it.
+/// Returns the image files it used, or `None` when the content changed or
+/// went away since it was read.
+pub async fn delete(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    existing: &Content,
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let gallery = galleries::of_content(conn, existing.id).await?;
+    let deleted = sqlx::query("DELETE FROM contents WHERE id = ? AND version = ?")
+        .bind(existing.id)
+        .bind(existing.version)
+        .execute(&mut *conn)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    changes::record(
+        conn,
+        context,
+        "delete",
+        "content",
+        existing.id,
+        Some(existing),
+        None::<&Content>,
+    )
+    .await?;
+    let mut files: Vec<String> = gallery.into_iter().map(|image| image.filename).collect();
+    files.extend(existing.image_path.clone());
+    Ok(Some(files))
+}
+
+/// Image files `before` used that `after` no longer does, to be removed once
+/// the change has committed unless something else still refers to them.
+pub fn dropped_images(before: &Content, after: &Content) -> Vec<String> {
+    match &before.image_path {
+        Some(old_path) if after.image_path.as_ref() != Some(old_path) => vec![old_path.clone()],
+        _ => Vec::new(),
+    }
+}
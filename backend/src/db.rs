@@ -1,7 +1,139 @@
 use sqlx::{sqlite::SqlitePool, migrate::MigrateDatabase, Sqlite};
 use std::env;
 
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    /// Reverts `up`; `None` when the migration cannot be rolled back.
+    pub down: Option<&'static str>,
+}
+
+/// Schema migrations in the order they are applied. Each entry runs once and
+/// is recorded in `schema_migrations`; `001_init.sql` is idempotent so
+/// databases created before the table existed are picked up transparently.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "init",
+        up: include_str!("../migrations/001_init.sql"),
+        down: None,
+    },
+    Migration {
+        version: 2,
+        name: "row_versions",
+        up: include_str!("../migrations/002_row_versions.sql"),
+        down: Some(include_str!("../migrations/002_row_versions.down.sql")),
+    },
+    Migration {
+        version: 3,
+        name: "content_order",
+        up: include_str!("../migrations/003_content_order.sql"),
+        down: Some(include_str!("../migrations/003_content_order.down.sql")),
+    },
+    Migration {
+        version: 4,
+        name: "audit_log",
+        up: include_str!("../migrations/004_audit_log.sql"),
+        down: Some(include_str!("../migrations/004_audit_log.down.sql")),
+    },
+    Migration {
+        version: 5,
+        name: "webhooks",
+        up: include_str!("../migrations/005_webhooks.sql"),
+        down: Some(include_str!("../migrations/005_webhooks.down.sql")),
+    },
+    Migration {
+        version: 6,
+        name: "change_feed",
+        up: include_str!("../migrations/006_change_feed.sql"),
+        down: Some(include_str!("../migrations/006_change_feed.down.sql")),
+    },
+    Migration {
+        version: 7,
+        name: "translations",
+        up: include_str!("../migrations/007_translations.sql"),
+        down: Some(include_str!("../migrations/007_translations.down.sql")),
+    },
+    Migration {
+        version: 8,
+        name: "languages",
+        up: include_str!("../migrations/008_languages.sql"),
+        down: Some(include_str!("../migrations/008_languages.down.sql")),
+    },
+    Migration {
+        version: 9,
+        name: "content_format",
+        up: include_str!("../migrations/009_content_format.sql"),
+        down: Some(include_str!("../migrations/009_content_format.down.sql")),
+    },
+    Migration {
+        version: 10,
+        name: "page_tree",
+        up: include_str!("../migrations/010_page_tree.sql"),
+        down: Some(include_str!("../migrations/010_page_tree.down.sql")),
+    },
+    Migration {
+        version: 11,
+        name: "menus",
+        up: include_str!("../migrations/011_menus.sql"),
+        down: Some(include_str!("../migrations/011_menus.down.sql")),
+    },
+    Migration {
+        version: 12,
+        name: "redirects",
+        up: include_str!("../migrations/012_redirects.sql"),
+        down: Some(include_str!("../migrations/012_redirects.down.sql")),
+    },
+    Migration {
+        version: 13,
+        name: "taxonomies",
+        up: include_str!("../migrations/013_taxonomies.sql"),
+        down: Some(include_str!("../migrations/013_taxonomies.down.sql")),
+    },
+    Migration {
+        version: 14,
+        name: "blocks",
+        up: include_str!("../migrations/014_blocks.sql"),
+        down: Some(include_str!("../migrations/014_blocks.down.sql")),
+    },
+    Migration {
+        version: 15,
+        name: "content_types",
+        up: include_str!("../migrations/015_content_types.sql"),
+        down: Some(include_str!("../migrations/015_content_types.down.sql")),
+    },
+    Migration {
+        version: 16,
+        name: "content_images",
+        up: include_str!("../migrations/016_content_images.sql"),
+        down: Some(include_str!("../migrations/016_content_images.down.sql")),
+    },
+    Migration {
+        version: 17,
+        name: "relations",
+        up: include_str!("../migrations/017_relations.sql"),
+        down: Some(include_str!("../migrations/017_relations.down.sql")),
+    },
+    Migration {
+        version: 18,
+        name: "previews",
+        up: include_str!("../migrations/018_previews.sql"),
+        down: Some(include_str!("../migrations/018_previews.down.sql")),
+    },
+];
+
 pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
+    let pool = connect().await?;
+
+    run_migrations(&pool).await?;
+
+    println!("Database initialized successfully");
+    Ok(pool)
+}
+
+/// Opens `DATABASE_URL`, creating the file if needed, without migrating it.
+pub async fn connect() -> Result<SqlitePool, sqlx::Error> {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     if !Sqlite::database_exists(&database_url).await.unwrap_or(false) {
@@ -9,11 +141,66 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
         Sqlite::create_database(&database_url).await?;
     }
 
-    let pool = SqlitePool::connect(&database_url).await?;
+    SqlitePool::connect(&database_url).await
+}
 
-    let migration_sql = include_str!("../migrations/001_init.sql");
-    sqlx::query(migration_sql).execute(&pool).await?;
+/// Versions recorded in `schema_migrations`, ascending.
+pub async fn applied_migrations(pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(pool)
+    .await?;
 
-    println!("Database initialized successfully");
-    Ok(pool)
+    sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let applied = applied_migrations(pool).await?;
+
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration.up).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        println!("Applied migration {:03}_{}", migration.version, migration.name);
+    }
+
+    Ok(())
+}
+
+/// The most recently applied migration, if any.
+pub async fn latest_migration(pool: &SqlitePool) -> Result<Option<&'static Migration>, sqlx::Error> {
+    let latest = applied_migrations(pool).await?.pop();
+    Ok(latest.and_then(|version| MIGRATIONS.iter().find(|migration| migration.version == version)))
+}
+
+/// Runs a migration's `down` script and forgets that it was applied.
+pub async fn rollback_migration(
+    pool: &SqlitePool,
+    migration: &Migration,
+    down: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(down).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+        .bind(migration.version)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
 }
@@ -0,0 +1,141 @@
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// How long shared caches may serve public documents without revalidating.
+const PUBLIC_MAX_AGE: u32 = 300;
+
+/// Entity tag for a row version, e.g. `"3"`.
+pub fn etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+pub enum IfMatch {
+    Missing,
+    Any,
+    Versions(Vec<i64>),
+}
+
+impl IfMatch {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let value = match req
+            .headers()
+            .get(header::IF_MATCH)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(value) => value.trim(),
+            None => return IfMatch::Missing,
+        };
+
+        if value == "*" {
+            return IfMatch::Any;
+        }
+
+        IfMatch::Versions(
+            value
+                .split(',')
+                .filter_map(|tag| {
+                    tag.trim()
+                        .trim_start_matches("W/")
+                        .trim_matches('"')
+                        .parse()
+                        .ok()
+                })
+                .collect(),
+        )
+    }
+
+    pub fn matches(&self, version: i64) -> bool {
+        match self {
+            IfMatch::Missing => false,
+            IfMatch::Any => true,
+            IfMatch::Versions(versions) => versions.contains(&version),
+        }
+    }
+}
+
+/// Checks `If-Match` against the row's current version. Returns the response
+/// to send when the precondition is missing (428) or fails (412); the 412 body
+/// carries the current row so the client can offer a merge.
+pub fn check<T: Serialize>(req: &HttpRequest, version: i64, current: &T) -> Option<HttpResponse> {
+    let if_match = IfMatch::from_request(req);
+
+    if let IfMatch::Missing = if_match {
+        return Some(HttpResponse::PreconditionRequired().json(serde_json::json!({
+            "error": "If-Match header is required"
+        })));
+    }
+
+    if if_match.matches(version) {
+        None
+    } else {
+        Some(precondition_failed(version, current))
+    }
+}
+
+pub fn precondition_failed<T: Serialize>(version: i64, current: &T) -> HttpResponse {
+    HttpResponse::PreconditionFailed()
+        .insert_header((header::ETAG, etag(version)))
+        .json(serde_json::json!({
+            "error": "Resource has been modified",
+            "current": current
+        }))
+}
+
+/// A public, cacheable `200` for a generated document, tagged with a hash of
+/// its body and `Last-Modified`. Answers `304 Not Modified` instead when
+/// `If-None-Match` or, failing that, `If-Modified-Since` shows the client's
+/// copy is current.
+pub fn cacheable(
+    req: &HttpRequest,
+    content_type: &str,
+    body: String,
+    last_modified: Option<DateTime<Utc>>,
+) -> HttpResponse {
+    let tag = format!(
+        "\"{}\"",
+        &hex::encode(Sha256::digest(body.as_bytes()))[..32]
+    );
+    let cache_control = format!("public, max-age={}", PUBLIC_MAX_AGE);
+
+    let headers = req.headers();
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok());
+    let if_modified_since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok());
+    let not_modified = match if_none_match {
+        Some(value) => value
+            .split(',')
+            .map(|candidate| candidate.trim().trim_start_matches("W/"))
+            .any(|candidate| candidate == "*" || candidate == tag),
+        None => match (if_modified_since, last_modified) {
+            (Some(since), Some(modified)) => DateTime::parse_from_rfc2822(since)
+                .is_ok_and(|since| modified.timestamp() <= since.timestamp()),
+            _ => false,
+        },
+    };
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header((header::ETAG, tag))
+        .insert_header((header::CACHE_CONTROL, cache_control));
+    if let Some(modified) = last_modified {
+        response.insert_header((
+            header::LAST_MODIFIED,
+            modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        ));
+    }
+
+    if not_modified {
+        response.finish()
+    } else {
+        response.content_type(content_type).body(body)
+    }
+}
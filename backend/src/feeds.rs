@@ -0,0 +1,257 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::render::Alternate;
+
+/// Feed formats served per `page_name`, by the name used in their URL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rss" => Some(FeedFormat::Rss),
+            "atom" => Some(FeedFormat::Atom),
+            "json" => Some(FeedFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Json => "application/feed+json; charset=utf-8",
+        }
+    }
+}
+
+pub struct Feed {
+    pub title: String,
+    /// Name given as the author of every entry, which Atom requires.
+    pub author: String,
+    pub lang: String,
+    /// The page the feed is about.
+    pub link: String,
+    /// The feed's own URL.
+    pub self_url: String,
+    pub updated: DateTime<Utc>,
+    pub items: Vec<FeedItem>,
+}
+
+/// One content of the page.
+pub struct FeedItem {
+    /// Stable across edits: the page URL with the content id as fragment.
+    pub id: String,
+    pub title: String,
+    pub summary: Option<String>,
+    pub link: String,
+    pub image: Option<String>,
+    pub updated: DateTime<Utc>,
+}
+
+/// A `<url>` of a sitemap, with the other language versions of the page.
+pub struct SitemapUrl {
+    pub loc: String,
+    /// `YYYY-MM-DD`.
+    pub lastmod: Option<String>,
+    pub alternates: Vec<Alternate>,
+}
+
+/// Parses the `CURRENT_TIMESTAMP` format SQLite stores, which is UTC.
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|timestamp| timestamp.and_utc())
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// A sitemap.xml document, with `xhtml:link` hreflang alternates for pages
+/// that exist in more than one language.
+pub fn sitemap_xml(urls: &[SitemapUrl]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\" \
+         xmlns:xhtml=\"http://www.w3.org/1999/xhtml\">\n",
+    );
+    for url in urls {
+        xml.push_str("  <url>\n");
+        xml.push_str(&format!("    <loc>{}</loc>\n", xml_escape(&url.loc)));
+        if let Some(lastmod) = &url.lastmod {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", xml_escape(lastmod)));
+        }
+        if url.alternates.len() > 1 {
+            for alternate in &url.alternates {
+                xml.push_str(&format!(
+                    "    <xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>\n",
+                    xml_escape(&alternate.lang),
+                    xml_escape(&alternate.href)
+                ));
+            }
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// RSS 2.0, with images as Media RSS `media:content`.
+pub fn rss(feed: &Feed) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" \
+         xmlns:media=\"http://search.yahoo.com/mrss/\">\n<channel>\n",
+    );
+    xml.push_str(&format!("  <title>{}</title>\n", xml_escape(&feed.title)));
+    xml.push_str(&format!("  <link>{}</link>\n", xml_escape(&feed.link)));
+    xml.push_str(&format!(
+        "  <description>{}</description>\n",
+        xml_escape(&feed.title)
+    ));
+    xml.push_str(&format!(
+        "  <language>{}</language>\n",
+        xml_escape(&feed.lang)
+    ));
+    xml.push_str(&format!(
+        "  <lastBuildDate>{}</lastBuildDate>\n",
+        feed.updated.to_rfc2822()
+    ));
+    xml.push_str(&format!(
+        "  <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        xml_escape(&feed.self_url)
+    ));
+    for item in &feed.items {
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", xml_escape(&item.title)));
+        xml.push_str(&format!("    <link>{}</link>\n", xml_escape(&item.link)));
+        xml.push_str(&format!(
+            "    <guid isPermaLink=\"false\">{}</guid>\n",
+            xml_escape(&item.id)
+        ));
+        xml.push_str(&format!(
+            "    <pubDate>{}</pubDate>\n",
+            item.updated.to_rfc2822()
+        ));
+        if let Some(summary) = &item.summary {
+            xml.push_str(&format!(
+                "    <description>{}</description>\n",
+                xml_escape(summary)
+            ));
+        }
+        if let Some(image) = &item.image {
+            xml.push_str(&format!(
+                "    <media:content url=\"{}\" medium=\"image\"/>\n",
+                xml_escape(image)
+            ));
+        }
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Atom 1.0, with images as `enclosure` links.
+pub fn atom(feed: &Feed) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"{}\">\n",
+        xml_escape(&feed.lang)
+    );
+    xml.push_str(&format!("  <id>{}</id>\n", xml_escape(&feed.self_url)));
+    xml.push_str(&format!("  <title>{}</title>\n", xml_escape(&feed.title)));
+    xml.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        feed.updated.to_rfc3339()
+    ));
+    xml.push_str(&format!(
+        "  <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+        xml_escape(&feed.link)
+    ));
+    xml.push_str(&format!(
+        "  <link rel=\"self\" href=\"{}\"/>\n",
+        xml_escape(&feed.self_url)
+    ));
+    xml.push_str(&format!(
+        "  <author><name>{}</name></author>\n",
+        xml_escape(&feed.author)
+    ));
+    for item in &feed.items {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>{}</id>\n", xml_escape(&item.id)));
+        xml.push_str(&format!("    <title>{}</title>\n", xml_escape(&item.title)));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            item.updated.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+            xml_escape(&item.link)
+        ));
+        if let Some(summary) = &item.summary {
+            xml.push_str(&format!("    <summary>{}</summary>\n", xml_escape(summary)));
+        }
+        if let Some(image) = &item.image {
+            xml.push_str(&format!(
+                "    <link rel=\"enclosure\" href=\"{}\"/>\n",
+                xml_escape(image)
+            ));
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// JSON Feed 1.1.
+pub fn json_feed(feed: &Feed) -> String {
+    let items: Vec<serde_json::Value> = feed
+        .items
+        .iter()
+        .map(|item| {
+            let mut value = serde_json::json!({
+                "id": item.id,
+                "url": item.link,
+                "title": item.title,
+                "content_text": item.summary.as_deref().unwrap_or(&item.title),
+                "date_modified": item.updated.to_rfc3339(),
+            });
+            if let Some(summary) = &item.summary {
+                value["summary"] = serde_json::json!(summary);
+            }
+            if let Some(image) = &item.image {
+                value["image"] = serde_json::json!(image);
+            }
+            value
+        })
+        .collect();
+
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed.title,
+        "home_page_url": feed.link,
+        "feed_url": feed.self_url,
+        "language": feed.lang,
+        "authors": [{"name": feed.author}],
+        "items": items,
+    })
+    .to_string()
+}
+
+pub fn render(format: FeedFormat, feed: &Feed) -> String {
+    match format {
+        FeedFormat::Rss => rss(feed),
+        FeedFormat::Atom => atom(feed),
+        FeedFormat::Json => json_feed(feed),
+    }
+}
@@ -0,0 +1,99 @@
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::models::gallery::ContentImage;
+use crate::models::Content;
+use crate::render;
+
+/// Image attachments of `content_id` in `display_order`.
+pub async fn of_content(
+    conn: &mut SqliteConnection,
+    content_id: i64,
+) -> Result<Vec<ContentImage>, sqlx::Error> {
+    sqlx::query_as::<_, ContentImage>(
+        "SELECT * FROM content_images WHERE content_id = ? ORDER BY display_order, id",
+    )
+    .bind(content_id)
+    .fetch_all(conn)
+    .await
+}
+
+/// Loads the `gallery` of each of `contents`. Placed blocks have none.
+pub async fn attach(pool: &SqlitePool, contents: &mut [Content]) -> Result<(), sqlx::Error> {
+    let ids: Vec<i64> = contents
+        .iter()
+        .filter(|content| content.block_id.is_none())
+        .map(|content| content.id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let images = sqlx::query_as::<_, ContentImage>(
+        "SELECT * FROM content_images
+         WHERE content_id IN (SELECT value FROM json_each(?))
+         ORDER BY display_order, id",
+    )
+    .bind(serde_json::json!(ids).to_string())
+    .fetch_all(pool)
+    .await?;
+    for content in contents
+        .iter_mut()
+        .filter(|content| content.block_id.is_none())
+    {
+        content.gallery = images
+            .iter()
+            .filter(|image| image.content_id == content.id)
+            .cloned()
+            .collect();
+    }
+    Ok(())
+}
+
+/// The uploaded files named in `image_paths`, which are `image_path` values or
+/// attachment filenames, that no content, block or attachment refers to.
+pub async fn unreferenced(
+    conn: &mut SqliteConnection,
+    image_paths: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let filenames: BTreeSet<&str> = image_paths
+        .iter()
+        .flat_map(|image_path| render::stored_files(image_path))
+        .collect();
+    let mut unreferenced = Vec::new();
+    for filename in filenames {
+        // `LIKE` narrows the rows down; which files they name is decided on
+        // the parsed entries.
+        let candidates: Vec<String> = sqlx::query_scalar(
+            "SELECT image_path FROM contents WHERE image_path LIKE '%' || ?1 || '%'
+             UNION SELECT image_path FROM blocks WHERE image_path LIKE '%' || ?1 || '%'
+             UNION SELECT filename FROM content_images WHERE filename LIKE '%' || ?1 || '%'",
+        )
+        .bind(filename)
+        .fetch_all(&mut *conn)
+        .await?;
+        let referenced = candidates
+            .iter()
+            .any(|image_path| render::stored_files(image_path).any(|file| file == filename));
+        if !referenced {
+            unreferenced.push(filename.to_string());
+        }
+    }
+    Ok(unreferenced)
+}
+
+/// Removes the uploaded files named in `image_paths` from `upload_dir` that
+/// no content, block or attachment refers to any more. Meant for after the
+/// change that dropped them has committed.
+pub async fn remove_files(
+    pool: &SqlitePool,
+    upload_dir: &str,
+    image_paths: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    for filename in unreferenced(&mut conn, image_paths).await? {
+        let _ = std::fs::remove_file(Path::new(upload_dir).join(filename));
+    }
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use actix_web::{web, HttpResponse, HttpRequest, Responder};
 use sqlx::SqlitePool;
 
+use crate::audit::AuditContext;
+use crate::changes;
 use crate::auth::Claims;
-use crate::models::Agent;
+use crate::merge_patch;
+use crate::models::{Agent, AgentFields};
 
 use actix_web::HttpMessage;
 
@@ -40,56 +43,93 @@ pub async fn get_agent(
 }
 
 pub async fn update_agent_status(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     agent_id: web::Path<i64>,
     body: web::Json<serde_json::Value>,
 ) -> impl Responder {
     let is_active = body.get("is_active").and_then(|v| v.as_bool());
 
-    if is_active.is_none() {
+    let Some(is_active) = is_active else {
         return HttpResponse::BadRequest().json(serde_json::json!({
             "error": "is_active field is required"
         }));
+    };
+
+    let existing = match fetch_agent(&pool, *agent_id).await {
+        Ok(agent) => agent,
+        Err(response) => return response,
+    };
+
+    let fields = AgentFields {
+        agent_number: existing.agent_number.clone(),
+        is_active,
+    };
+
+    match save_agent(&req, &pool, &existing, &fields).await {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Agent status updated"
+        })),
+        Err(response) => response,
     }
+}
 
-    let result = sqlx::query(
-        "UPDATE agents SET is_active = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
-    )
-    .bind(is_active.unwrap())
-    .bind(*agent_id)
-    .execute(pool.get_ref())
-    .await;
+pub async fn patch_agent(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    agent_id: web::Path<i64>,
+    patch: web::Json<serde_json::Value>,
+) -> impl Responder {
+    let existing = match fetch_agent(&pool, *agent_id).await {
+        Ok(agent) => agent,
+        Err(response) => return response,
+    };
 
-    match result {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Agent status updated"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Agent not found"
-                }))
-            }
+    let fields = match merge_patch::apply(&AgentFields::from(&existing), &patch) {
+        Ok(fields) => fields,
+        Err(error) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": error
+            }))
         }
-        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Database error"
+    };
+
+    match save_agent(&req, &pool, &existing, &fields).await {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Agent updated"
         })),
+        Err(response) => response,
     }
 }
 
 pub async fn delete_agent(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     agent_id: web::Path<i64>,
 ) -> impl Responder {
-    let result = sqlx::query("DELETE FROM agents WHERE id = ?")
-        .bind(*agent_id)
-        .execute(pool.get_ref())
-        .await;
+    let existing = match fetch_agent(&pool, *agent_id).await {
+        Ok(agent) => agent,
+        Err(response) => return response,
+    };
+
+    let result: Result<u64, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let deleted = sqlx::query("DELETE FROM agents WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(&mut tx, &context, "delete", "agent", existing.id, Some(&existing), None::<&Agent>)
+            .await?;
+        tx.commit().await?;
+        Ok(deleted.rows_affected())
+    }
+    .await;
 
     match result {
         Ok(result) => {
-            if result.rows_affected() > 0 {
+            if result > 0 {
                 HttpResponse::Ok().json(serde_json::json!({
                     "message": "Agent deleted"
                 }))
@@ -105,6 +145,65 @@ pub async fn delete_agent(
     }
 }
 
+async fn fetch_agent(pool: &SqlitePool, agent_id: i64) -> Result<Agent, HttpResponse> {
+    match sqlx::query_as::<_, Agent>("SELECT * FROM agents WHERE id = ?")
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(agent)) => Ok(agent),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Agent not found"
+        }))),
+        Err(_) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+/// Writes `fields` over `existing` and records the change in the audit log
+/// within the same transaction.
+async fn save_agent(
+    req: &HttpRequest,
+    pool: &SqlitePool,
+    existing: &Agent,
+    fields: &AgentFields,
+) -> Result<Agent, HttpResponse> {
+    let result: Result<Agent, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let updated = sqlx::query_as::<_, Agent>(
+            "UPDATE agents SET agent_number = ?, is_active = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *"
+        )
+        .bind(&fields.agent_number)
+        .bind(fields.is_active)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(req);
+        changes::record(&mut tx, &context, "update", "agent", existing.id, Some(existing), Some(&updated))
+            .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    result.map_err(|e| match e {
+        sqlx::Error::RowNotFound => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Agent not found"
+        })),
+        e if e.to_string().contains("UNIQUE constraint failed") => {
+            HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Agent number already exists"
+            }))
+        }
+        _ => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Database error"
+        })),
+    })
+}
+
 pub async fn get_current_agent(req: HttpRequest, pool: web::Data<SqlitePool>) -> impl Responder {
     let claims = req.extensions().get::<Claims>().cloned();
 
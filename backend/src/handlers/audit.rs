@@ -0,0 +1,115 @@
+use actix_web::{http::header, web, HttpResponse, Responder};
+use sqlx::SqlitePool;
+
+use crate::models::{AuditEntry, AuditQuery};
+
+const FILTERED_AUDIT_LOG: &str = "SELECT * FROM audit_log
+     WHERE (? IS NULL OR agent_id = ?)
+     AND (? IS NULL OR action = ?)
+     AND (? IS NULL OR entity = ?)
+     AND (? IS NULL OR entity_id = ?)
+     AND (? IS NULL OR created_at >= ?)
+     AND (? IS NULL OR created_at < ?)
+     ORDER BY id DESC
+     LIMIT ? OFFSET ?";
+
+async fn fetch_entries(
+    pool: &SqlitePool,
+    query: &AuditQuery,
+    limit: i64,
+) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    sqlx::query_as::<_, AuditEntry>(FILTERED_AUDIT_LOG)
+        .bind(query.agent_id)
+        .bind(query.agent_id)
+        .bind(&query.action)
+        .bind(&query.action)
+        .bind(&query.entity)
+        .bind(&query.entity)
+        .bind(&query.entity_id)
+        .bind(&query.entity_id)
+        .bind(&query.from)
+        .bind(&query.from)
+        .bind(&query.to)
+        .bind(&query.to)
+        .bind(limit)
+        .bind(query.offset.unwrap_or(0))
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_audit_log(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<AuditQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+
+    match fetch_entries(pool.get_ref(), &query, limit).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch audit log"
+        })),
+    }
+}
+
+/// Same filters as `get_audit_log`, returned as CSV. Without a `limit` the
+/// whole matching log is exported.
+pub async fn export_audit_log(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<AuditQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(-1);
+
+    let entries = match fetch_entries(pool.get_ref(), &query, limit).await {
+        Ok(entries) => entries,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to export audit log"
+            }))
+        }
+    };
+
+    let mut csv = String::from(
+        "id,created_at,agent_id,action,entity,entity_id,ip_address,user_agent,before_state,after_state\n",
+    );
+    for entry in entries {
+        let fields = [
+            entry.id.to_string(),
+            entry.created_at,
+            entry.agent_id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.action,
+            entry.entity,
+            entry.entity_id.unwrap_or_default(),
+            entry.ip_address.unwrap_or_default(),
+            entry.user_agent.unwrap_or_default(),
+            entry.before_state.unwrap_or_default(),
+            entry.after_state.unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"audit_log.csv\"",
+        ))
+        .body(csv)
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+/// Values a spreadsheet would read as a formula get a leading `'`: user
+/// agents, ids and snapshots come from clients.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
@@ -1,7 +1,9 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use sqlx::SqlitePool;
 
+use crate::audit::AuditContext;
+use crate::changes;
 use crate::auth::generate_token;
 use crate::config::AppConfig;
 use crate::models::{Agent, AgentCreate, AgentLogin};
@@ -58,6 +60,7 @@ pub async fn login(
 }
 
 pub async fn register(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     agent_data: web::Json<AgentCreate>,
 ) -> impl Responder {
@@ -72,18 +75,29 @@ pub async fn register(
 
     let is_active = agent_data.is_active.unwrap_or(true);
 
-    let result = sqlx::query(
-        "INSERT INTO agents (agent_number, password_hash, is_active) VALUES (?, ?, ?)"
-    )
-    .bind(&agent_data.agent_number)
-    .bind(&password_hash)
-    .bind(is_active)
-    .execute(pool.get_ref())
+    let result: Result<Agent, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let agent = sqlx::query_as::<_, Agent>(
+            "INSERT INTO agents (agent_number, password_hash, is_active) VALUES (?, ?, ?)
+             RETURNING *"
+        )
+        .bind(&agent_data.agent_number)
+        .bind(&password_hash)
+        .bind(is_active)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(&mut tx, &context, "create", "agent", agent.id, None::<&Agent>, Some(&agent))
+            .await?;
+        tx.commit().await?;
+        Ok(agent)
+    }
     .await;
 
     match result {
-        Ok(res) => HttpResponse::Created().json(serde_json::json!({
-            "id": res.last_insert_rowid(),
+        Ok(agent) => HttpResponse::Created().json(serde_json::json!({
+            "id": agent.id,
             "agent_number": agent_data.agent_number,
             "is_active": is_active
         })),
@@ -0,0 +1,109 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::audit::{self, AuditContext};
+use crate::backups::{self, BackupError};
+use crate::config::AppConfig;
+
+fn error_response(e: BackupError, action: &str) -> HttpResponse {
+    match e {
+        BackupError::NotFound => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Backup not found"
+        })),
+        BackupError::Invalid(message) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": message
+        })),
+        _ => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to {} backup", action)
+        })),
+    }
+}
+
+pub async fn create_backup(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let manifest = match backups::create(
+        pool.get_ref(),
+        Path::new(&config.backup.dir),
+        Path::new(&config.upload_dir),
+    )
+    .await
+    {
+        Ok(manifest) => manifest,
+        Err(e) => return error_response(e, "create"),
+    };
+
+    let context = AuditContext::from_request(&req);
+    let _ = audit::record(
+        pool.get_ref(),
+        &context,
+        "create",
+        "backup",
+        &manifest.name,
+        None::<&serde_json::Value>,
+        Some(&manifest.database),
+    )
+    .await;
+
+    HttpResponse::Created().json(manifest)
+}
+
+pub async fn get_backups(config: web::Data<AppConfig>) -> impl Responder {
+    match backups::list(Path::new(&config.backup.dir)) {
+        Ok(summaries) => HttpResponse::Ok().json(summaries),
+        Err(e) => error_response(e, "list"),
+    }
+}
+
+pub async fn get_backup(config: web::Data<AppConfig>, name: web::Path<String>) -> impl Responder {
+    match backups::manifest(Path::new(&config.backup.dir), &name) {
+        Ok(manifest) => HttpResponse::Ok().json(manifest),
+        Err(e) => error_response(e, "read"),
+    }
+}
+
+pub async fn verify_backup(
+    config: web::Data<AppConfig>,
+    name: web::Path<String>,
+) -> impl Responder {
+    match backups::verify(
+        Path::new(&config.backup.dir),
+        Path::new(&config.upload_dir),
+        &name,
+    )
+    .await
+    {
+        Ok(verification) => HttpResponse::Ok().json(verification),
+        Err(e) => error_response(e, "verify"),
+    }
+}
+
+pub async fn delete_backup(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    name: web::Path<String>,
+) -> impl Responder {
+    if let Err(e) = backups::delete(Path::new(&config.backup.dir), &name) {
+        return error_response(e, "delete");
+    }
+
+    let context = AuditContext::from_request(&req);
+    let _ = audit::record(
+        pool.get_ref(),
+        &context,
+        "delete",
+        "backup",
+        &*name,
+        None::<&serde_json::Value>,
+        None::<&serde_json::Value>,
+    )
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Backup deleted successfully"
+    }))
+}
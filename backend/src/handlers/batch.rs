@@ -0,0 +1,280 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Responder};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+use crate::audit::AuditContext;
+use crate::config::AppConfig;
+use crate::contents::{self, ContentError};
+use crate::galleries;
+use crate::merge_patch;
+use crate::pages::{self, PageError};
+use crate::rich_text;
+use crate::models::batch::{BatchContentCreate, BatchItemResult, BatchOperation, BatchRequest};
+use crate::models::{Content, ContentFields, Page, PageFields};
+
+struct OpError {
+    status: StatusCode,
+    message: String,
+}
+
+impl OpError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        OpError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn database() -> Self {
+        OpError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+    }
+}
+
+impl From<PageError> for OpError {
+    fn from(e: PageError) -> Self {
+        match e {
+            PageError::Invalid(message) => OpError::new(StatusCode::BAD_REQUEST, message),
+            PageError::Conflict(message) => OpError::new(StatusCode::CONFLICT, message),
+            PageError::Database(_) => OpError::database(),
+        }
+    }
+}
+
+impl From<ContentError> for OpError {
+    fn from(e: ContentError) -> Self {
+        match e {
+            ContentError::Invalid(message) => OpError::new(StatusCode::BAD_REQUEST, message),
+            ContentError::Database(_) => OpError::database(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for OpError {
+    fn from(_: sqlx::Error) -> Self {
+        OpError::database()
+    }
+}
+
+/// State shared by the operations of one batch.
+struct BatchState {
+    context: AuditContext,
+    /// Ids of pages created in this batch, keyed by their client `ref`.
+    page_refs: HashMap<String, i64>,
+    /// Image files to remove once the transaction has committed, unless
+    /// something still refers to them.
+    orphaned_images: Vec<String>,
+}
+
+/// Runs every operation in a single transaction. Either all of them are
+/// applied and per-item results are returned, or the batch is rolled back and
+/// the response carries the status and error of the operation that failed.
+pub async fn run_batch(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    batch: web::Json<BatchRequest>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let batch = batch.into_inner();
+
+    if batch.operations.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Batch contains no operations"
+        }));
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to start transaction"
+            }))
+        }
+    };
+
+    let mut state = BatchState {
+        context: AuditContext::from_request(&req),
+        page_refs: HashMap::new(),
+        orphaned_images: Vec::new(),
+    };
+    let mut results = Vec::with_capacity(batch.operations.len());
+
+    for (index, operation) in batch.operations.into_iter().enumerate() {
+        match apply_operation(&mut tx, &mut state, operation).await {
+            Ok((status, id, version)) => results.push(BatchItemResult {
+                index,
+                status: status.as_u16(),
+                id: Some(id),
+                version,
+                error: None,
+            }),
+            Err(error) => {
+                let _ = tx.rollback().await;
+                results.push(BatchItemResult {
+                    index,
+                    status: error.status.as_u16(),
+                    id: None,
+                    version: None,
+                    error: Some(error.message),
+                });
+                return HttpResponse::build(error.status).json(serde_json::json!({
+                    "error": "Batch rolled back",
+                    "failed_index": index,
+                    "results": results
+                }));
+            }
+        }
+    }
+
+    if tx.commit().await.is_err() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to commit batch"
+        }));
+    }
+
+    let _ = galleries::remove_files(&pool, &config.upload_dir, &state.orphaned_images).await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Batch applied successfully",
+        "results": results
+    }))
+}
+
+async fn apply_operation(
+    conn: &mut SqliteConnection,
+    state: &mut BatchState,
+    operation: BatchOperation,
+) -> Result<(StatusCode, i64, Option<i64>), OpError> {
+    match operation {
+        BatchOperation::CreatePage { client_ref, data } => {
+            let page = pages::create(conn, &state.context, &data).await?;
+            if let Some(client_ref) = client_ref {
+                if state.page_refs.insert(client_ref.clone(), page.id).is_some() {
+                    return Err(OpError::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("Duplicate page ref '{}'", client_ref),
+                    ));
+                }
+            }
+            Ok((StatusCode::CREATED, page.id, Some(page.version)))
+        }
+        BatchOperation::UpdatePage { id, version, data } => {
+            let existing = fetch_page(conn, id, version).await?;
+            let fields = merge_patch::apply(&PageFields::from(&existing), &data)
+                .map_err(|e| OpError::new(StatusCode::BAD_REQUEST, e))?;
+            let page = pages::update(conn, &state.context, &existing, &fields)
+                .await?
+                .ok_or_else(|| modified("Page"))?;
+            Ok((StatusCode::OK, id, Some(page.version)))
+        }
+        BatchOperation::DeletePage { id, version } => {
+            let existing = fetch_page(conn, id, version).await?;
+            if !pages::delete(conn, &state.context, &existing).await? {
+                return Err(modified("Page"));
+            }
+            Ok((StatusCode::OK, id, None))
+        }
+        BatchOperation::CreateContent { data } => {
+            let ref_id = resolve_page(state, &data)?;
+            let fields = ContentFields {
+                ref_id,
+                short_desc: data.short_desc,
+                long_desc: data.long_desc,
+                format: data
+                    .format
+                    .unwrap_or_else(|| rich_text::DEFAULT_FORMAT.to_string()),
+                image_path: data.image_path,
+                title: data.title,
+                display_order: data.display_order.unwrap_or(0),
+                type_id: data.type_id,
+                fields: data.fields,
+            };
+            let content = contents::create(conn, &state.context, &fields).await?;
+            Ok((StatusCode::CREATED, content.id, Some(content.version)))
+        }
+        BatchOperation::UpdateContent { id, version, data } => {
+            let existing = fetch_content(conn, id, version).await?;
+            let fields = merge_patch::apply(&ContentFields::from(&existing), &data)
+                .map_err(|e| OpError::new(StatusCode::BAD_REQUEST, e))?;
+            let content = contents::update(conn, &state.context, &existing, &fields)
+                .await?
+                .ok_or_else(|| modified("Content"))?;
+            state
+                .orphaned_images
+                .extend(contents::dropped_images(&existing, &content));
+            Ok((StatusCode::OK, id, Some(content.version)))
+        }
+        BatchOperation::DeleteContent { id, version } => {
+            let existing = fetch_content(conn, id, version).await?;
+            let files = contents::delete(conn, &state.context, &existing)
+                .await?
+                .ok_or_else(|| modified("Content"))?;
+            state.orphaned_images.extend(files);
+            Ok((StatusCode::OK, id, None))
+        }
+    }
+}
+
+/// Error for a guarded write that matched no row, which within the batch's
+/// transaction only happens if the row changed after it was fetched.
+fn modified(entity: &str) -> OpError {
+    OpError::new(
+        StatusCode::PRECONDITION_FAILED,
+        format!("{} has been modified", entity),
+    )
+}
+
+fn resolve_page(state: &BatchState, data: &BatchContentCreate) -> Result<i64, OpError> {
+    match (&data.page_ref, data.ref_id) {
+        (Some(page_ref), None) => state.page_refs.get(page_ref).copied().ok_or_else(|| {
+            OpError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown page ref '{}'", page_ref),
+            )
+        }),
+        (None, Some(ref_id)) => Ok(ref_id),
+        _ => Err(OpError::new(
+            StatusCode::BAD_REQUEST,
+            "Exactly one of ref_id or page_ref is required",
+        )),
+    }
+}
+
+async fn fetch_page(conn: &mut SqliteConnection, id: i64, version: i64) -> Result<Page, OpError> {
+    let page = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|_| OpError::database())?
+        .ok_or_else(|| OpError::new(StatusCode::NOT_FOUND, "Page not found"))?;
+
+    if page.version != version {
+        return Err(OpError::new(
+            StatusCode::PRECONDITION_FAILED,
+            format!("Page has been modified (current version {})", page.version),
+        ));
+    }
+
+    Ok(page)
+}
+
+async fn fetch_content(
+    conn: &mut SqliteConnection,
+    id: i64,
+    version: i64,
+) -> Result<Content, OpError> {
+    let content = sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|_| OpError::database())?
+        .ok_or_else(|| OpError::new(StatusCode::NOT_FOUND, "Content not found"))?;
+
+    if content.version != version {
+        return Err(OpError::new(
+            StatusCode::PRECONDITION_FAILED,
+            format!("Content has been modified (current version {})", content.version),
+        ));
+    }
+
+    Ok(content)
+}
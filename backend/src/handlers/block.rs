@@ -0,0 +1,541 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::audit::AuditContext;
+use crate::blocks;
+use crate::changes;
+use crate::config::AppConfig;
+use crate::etag;
+use crate::models::block::{Block, BlockCreate, BlockPlacement, BlockUpdate, PlacementSave};
+use crate::rich_text;
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+/// Block names are handles for editors and templates, so they are limited
+/// to lowercase letters, digits, `-` and `_`.
+fn check_block_name(name: &str) -> Result<(), HttpResponse> {
+    let valid = !name.is_empty()
+        && name.len() <= 50
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(bad_request(
+            "name must be 1 to 50 lowercase letters, digits, '-' or '_'",
+        ));
+    }
+    Ok(())
+}
+
+async fn name_taken(pool: &SqlitePool, name: &str, block_id: i64) -> Result<(), HttpResponse> {
+    let taken: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM blocks WHERE name = ? AND id != ?")
+            .bind(name)
+            .bind(block_id)
+            .fetch_optional(pool)
+            .await;
+    match taken {
+        Ok(None) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Block '{}' already exists", name)
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn fetch_block(pool: &SqlitePool, block_id: i64) -> Result<Block, HttpResponse> {
+    let block = sqlx::query_as::<_, Block>("SELECT * FROM blocks WHERE id = ?")
+        .bind(block_id)
+        .fetch_optional(pool)
+        .await;
+
+    match block {
+        Ok(Some(block)) => Ok(block),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Block not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn check_page(pool: &SqlitePool, page_id: i64) -> Result<(), HttpResponse> {
+    let exists: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM pages WHERE id = ?")
+            .bind(page_id)
+            .fetch_optional(pool)
+            .await;
+    match exists {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Page not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+/// Response for a guarded write that matched no row: the block either
+/// changed since it was read (412 with the current state) or is gone (404).
+async fn conflict(pool: &SqlitePool, block_id: i64) -> HttpResponse {
+    match fetch_block(pool, block_id).await {
+        Ok(current) => etag::precondition_failed(current.version, &current),
+        Err(response) => response,
+    }
+}
+
+pub async fn create_block(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    block_data: web::Json<BlockCreate>,
+) -> impl Responder {
+    if let Err(response) = check_block_name(&block_data.name) {
+        return response;
+    }
+    let format = block_data
+        .format
+        .as_deref()
+        .unwrap_or(rich_text::DEFAULT_FORMAT);
+    let (long_desc, long_desc_html) =
+        match rich_text::prepare(format, block_data.long_desc.as_deref()) {
+            Ok(prepared) => prepared,
+            Err(error) => return bad_request(error),
+        };
+    if let Err(response) = name_taken(&pool, &block_data.name, 0).await {
+        return response;
+    }
+
+    let result: Result<Block, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let block = sqlx::query_as::<_, Block>(
+            "INSERT INTO blocks (name, short_desc, long_desc, format, long_desc_html, image_path,
+             title)
+             VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&block_data.name)
+        .bind(&block_data.short_desc)
+        .bind(&long_desc)
+        .bind(format)
+        .bind(&long_desc_html)
+        .bind(&block_data.image_path)
+        .bind(&block_data.title)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "block",
+            block.id,
+            None::<&Block>,
+            Some(&block),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(block)
+    }
+    .await;
+
+    match result {
+        Ok(block) => HttpResponse::Created()
+            .insert_header((header::ETAG, etag::etag(block.version)))
+            .json(block),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create block"
+        })),
+    }
+}
+
+pub async fn get_blocks(pool: web::Data<SqlitePool>) -> impl Responder {
+    let blocks = sqlx::query_as::<_, Block>("SELECT * FROM blocks ORDER BY name")
+        .fetch_all(pool.get_ref())
+        .await;
+
+    match blocks {
+        Ok(blocks) => HttpResponse::Ok().json(blocks),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch blocks"
+        })),
+    }
+}
+
+pub async fn get_block(pool: web::Data<SqlitePool>, block_id: web::Path<i64>) -> impl Responder {
+    match fetch_block(&pool, *block_id).await {
+        Ok(block) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag::etag(block.version)))
+            .json(block),
+        Err(response) => response,
+    }
+}
+
+/// Updates a block everywhere it is placed. The old image file is removed
+/// when `image_path` changes.
+pub async fn update_block(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    block_id: web::Path<i64>,
+    block_data: web::Json<BlockUpdate>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let existing = match fetch_block(&pool, *block_id).await {
+        Ok(block) => block,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    let name = block_data.name.as_deref().unwrap_or(&existing.name);
+    if let Err(response) = check_block_name(name) {
+        return response;
+    }
+    let format = block_data.format.as_deref().unwrap_or(&existing.format);
+    let long_desc = block_data
+        .long_desc
+        .as_deref()
+        .or(existing.long_desc.as_deref());
+    let (long_desc, long_desc_html) = match rich_text::prepare(format, long_desc) {
+        Ok(prepared) => prepared,
+        Err(error) => return bad_request(error),
+    };
+    if let Err(response) = name_taken(&pool, name, existing.id).await {
+        return response;
+    }
+
+    let result: Result<Option<Block>, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let updated = sqlx::query_as::<_, Block>(
+            "UPDATE blocks SET name = ?, short_desc = ?, long_desc = ?, format = ?,
+             long_desc_html = ?, image_path = ?, title = ?,
+             version = version + 1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND version = ? RETURNING *",
+        )
+        .bind(name)
+        .bind(
+            block_data
+                .short_desc
+                .as_ref()
+                .or(existing.short_desc.as_ref()),
+        )
+        .bind(&long_desc)
+        .bind(format)
+        .bind(&long_desc_html)
+        .bind(
+            block_data
+                .image_path
+                .as_ref()
+                .or(existing.image_path.as_ref()),
+        )
+        .bind(block_data.title.as_ref().or(existing.title.as_ref()))
+        .bind(existing.id)
+        .bind(existing.version)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(updated) = updated else {
+            return Ok(None);
+        };
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "block",
+            existing.id,
+            Some(&existing),
+            Some(&updated),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(Some(updated))
+    }
+    .await;
+
+    match result {
+        Ok(Some(block)) => {
+            if let Some(old_path) = &existing.image_path {
+                if block.image_path.as_ref() != Some(old_path) {
+                    let _ = std::fs::remove_file(Path::new(&config.upload_dir).join(old_path));
+                }
+            }
+            HttpResponse::Ok()
+                .insert_header((header::ETAG, etag::etag(block.version)))
+                .json(block)
+        }
+        Ok(None) => conflict(&pool, existing.id).await,
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to update block"
+        })),
+    }
+}
+
+/// Deletes a block and its image. A block still placed on a page gets
+/// `409` with the pages it is on.
+pub async fn delete_block(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    block_id: web::Path<i64>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let existing = match fetch_block(&pool, *block_id).await {
+        Ok(block) => block,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    match blocks::usage(&pool, existing.id).await {
+        Ok(pages) if !pages.is_empty() => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("Block '{}' is placed on {} pages", existing.name, pages.len()),
+                "pages": pages
+            }))
+        }
+        Ok(_) => {}
+        Err(_) => return database_error(),
+    }
+
+    let result: Result<bool, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let deleted = sqlx::query("DELETE FROM blocks WHERE id = ? AND version = ?")
+            .bind(existing.id)
+            .bind(existing.version)
+            .execute(&mut *tx)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "block",
+            existing.id,
+            Some(&existing),
+            None::<&Block>,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
+
+    match result {
+        Ok(true) => {
+            if let Some(path) = &existing.image_path {
+                let _ = std::fs::remove_file(Path::new(&config.upload_dir).join(path));
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Block deleted successfully"
+            }))
+        }
+        Ok(false) => conflict(&pool, existing.id).await,
+        // A placement added since the check above trips the foreign key.
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => HttpResponse::Conflict()
+            .json(serde_json::json!({
+                "error": format!("Block '{}' is placed on a page", existing.name)
+            })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete block"
+        })),
+    }
+}
+
+/// Every page a block is placed on.
+pub async fn get_block_usage(
+    pool: web::Data<SqlitePool>,
+    block_id: web::Path<i64>,
+) -> impl Responder {
+    let block = match fetch_block(&pool, *block_id).await {
+        Ok(block) => block,
+        Err(response) => return response,
+    };
+
+    match blocks::usage(&pool, block.id).await {
+        Ok(pages) => HttpResponse::Ok().json(pages),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch block usage"
+        })),
+    }
+}
+
+pub async fn get_page_blocks(
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+) -> impl Responder {
+    if let Err(response) = check_page(&pool, *page_id).await {
+        return response;
+    }
+
+    let placements = sqlx::query_as::<_, BlockPlacement>(
+        "SELECT * FROM block_placements WHERE page_id = ? ORDER BY display_order, block_id",
+    )
+    .bind(*page_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match placements {
+        Ok(placements) => HttpResponse::Ok().json(placements),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch placements"
+        })),
+    }
+}
+
+/// Places a block on a page, or moves it to `display_order` if it is
+/// already there.
+pub async fn place_block(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    placement_data: web::Json<PlacementSave>,
+) -> impl Responder {
+    let (page_id, block_id) = path.into_inner();
+    if let Err(response) = check_page(&pool, page_id).await {
+        return response;
+    }
+    if let Err(response) = fetch_block(&pool, block_id).await {
+        return response;
+    }
+
+    let result: Result<(BlockPlacement, bool), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let existing = sqlx::query_as::<_, BlockPlacement>(
+            "SELECT * FROM block_placements WHERE page_id = ? AND block_id = ?",
+        )
+        .bind(page_id)
+        .bind(block_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        let (placement, created) = match &existing {
+            Some(existing) => {
+                let moved = sqlx::query_as::<_, BlockPlacement>(
+                    "UPDATE block_placements SET display_order = ?,
+                     updated_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING *",
+                )
+                .bind(
+                    placement_data
+                        .display_order
+                        .unwrap_or(existing.display_order),
+                )
+                .bind(existing.id)
+                .fetch_one(&mut *tx)
+                .await?;
+                changes::record(
+                    &mut tx,
+                    &context,
+                    "move",
+                    "block_placement",
+                    existing.id,
+                    Some(existing),
+                    Some(&moved),
+                )
+                .await?;
+                (moved, false)
+            }
+            None => {
+                let placed = sqlx::query_as::<_, BlockPlacement>(
+                    "INSERT INTO block_placements (block_id, page_id, display_order)
+                     VALUES (?, ?, ?) RETURNING *",
+                )
+                .bind(block_id)
+                .bind(page_id)
+                .bind(placement_data.display_order.unwrap_or(0))
+                .fetch_one(&mut *tx)
+                .await?;
+                changes::record(
+                    &mut tx,
+                    &context,
+                    "create",
+                    "block_placement",
+                    placed.id,
+                    None::<&BlockPlacement>,
+                    Some(&placed),
+                )
+                .await?;
+                (placed, true)
+            }
+        };
+        tx.commit().await?;
+        Ok((placement, created))
+    }
+    .await;
+
+    match result {
+        Ok((placement, true)) => HttpResponse::Created().json(placement),
+        Ok((placement, false)) => HttpResponse::Ok().json(placement),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to place block"
+        })),
+    }
+}
+
+/// Takes a block off a page. The block itself is kept.
+pub async fn remove_block(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let (page_id, block_id) = path.into_inner();
+
+    let result: Result<bool, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let removed = sqlx::query_as::<_, BlockPlacement>(
+            "DELETE FROM block_placements WHERE page_id = ? AND block_id = ? RETURNING *",
+        )
+        .bind(page_id)
+        .bind(block_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(removed) = removed else {
+            return Ok(false);
+        };
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "block_placement",
+            removed.id,
+            Some(&removed),
+            None::<&BlockPlacement>,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
+
+    match result {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Block removed from page"
+        })),
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Block is not placed on this page"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to remove block"
+        })),
+    }
+}
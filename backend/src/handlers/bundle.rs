@@ -0,0 +1,80 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::audit::AuditContext;
+use crate::bundle::{self, BundleError};
+use crate::config::AppConfig;
+use crate::models::bundle::{BundleImportQuery, ConflictPolicy, ImportMode};
+
+pub async fn export_bundle(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let encoded = match bundle::export(pool.get_ref(), Path::new(&config.upload_dir)).await {
+        Ok(bundle) => bundle::encode(&bundle),
+        Err(e) => Err(e),
+    };
+
+    match encoded {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/gzip")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"ccs-bundle-{}.json.gz\"",
+                    chrono::Utc::now().format("%Y%m%d-%H%M%S")
+                ),
+            ))
+            .body(bytes),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to export bundle"
+        })),
+    }
+}
+
+/// Takes the raw bundle file as the request body.
+pub async fn import_bundle(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    query: web::Query<BundleImportQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    let bundle = match bundle::decode(&body) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e.to_string()
+            }))
+        }
+    };
+
+    let context = AuditContext::from_request(&req);
+    let result = bundle::import(
+        pool.get_ref(),
+        Path::new(&config.upload_dir),
+        &context,
+        &bundle,
+        query.mode.unwrap_or(ImportMode::Merge),
+        query.on_conflict.unwrap_or(ConflictPolicy::Skip),
+        query.dry_run.unwrap_or(false),
+    )
+    .await;
+
+    match result {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(BundleError::Invalid(message)) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid bundle: {}", message)
+        })),
+        Err(BundleError::Conflicts(conflicts)) => {
+            HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Bundle pages conflict with existing pages",
+                "conflicts": conflicts
+            }))
+        }
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to import bundle"
+        })),
+    }
+}
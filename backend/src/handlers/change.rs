@@ -0,0 +1,173 @@
+use actix_web::{http::header, web, web::Bytes, HttpMessage, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::auth::{self, Claims};
+use crate::change_feed;
+use crate::config::AppConfig;
+use crate::models::ChangeQuery;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Idle polls between keep-alive comments, which also detect closed clients.
+const KEEP_ALIVE_POLLS: u32 = 15;
+const STREAM_BATCH_SIZE: i64 = 100;
+
+/// Changes after `since` (default: the start of the retained log), oldest
+/// first. For clients that cannot hold a stream open.
+pub async fn get_changes(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<ChangeQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+
+    match change_feed::fetch_since(pool.get_ref(), &query, query.since.unwrap_or(0), limit).await {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch changes"
+        })),
+    }
+}
+
+/// Short-lived token that opens the change stream through `?token=`, for
+/// browsers whose `EventSource` cannot send an `Authorization` header.
+pub async fn create_stream_token(req: HttpRequest, config: web::Data<AppConfig>) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Unauthorized"
+        }));
+    };
+
+    match auth::generate_stream_token(&claims, &config.jwt_secret) {
+        Ok(token) => HttpResponse::Ok().json(serde_json::json!({
+            "token": token,
+            "expires_in": auth::STREAM_TOKEN_EXPIRATION
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create stream token"
+        })),
+    }
+}
+
+/// Whether the request carries a valid change stream token or agent token.
+/// The stream checks this itself rather than sitting behind
+/// `AuthMiddleware`, which only reads the `Authorization` header.
+fn stream_authorized(req: &HttpRequest, query: &ChangeQuery, secret: &str) -> bool {
+    if let Some(token) = &query.token {
+        return auth::decode_stream_token(token, secret).is_ok();
+    }
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| auth::decode_token(token, secret).is_ok())
+}
+
+struct StreamState {
+    pool: SqlitePool,
+    query: ChangeQuery,
+    last_id: i64,
+    preamble: Option<String>,
+}
+
+/// Server-Sent Events stream of page, content and agent changes. Resumes
+/// after `Last-Event-ID` (or `?since=`); when that point has already been
+/// pruned from the log a `reset` event tells the client to refetch.
+pub async fn stream_changes(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    query: web::Query<ChangeQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    if !stream_authorized(&req, &query, &config.jwt_secret) {
+        return HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or missing token"
+        }));
+    }
+    let resume_from = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .or(query.since);
+
+    let bounds = async {
+        let latest = change_feed::latest_id(pool.get_ref()).await?;
+        let oldest = change_feed::oldest_id(pool.get_ref()).await?;
+        Ok::<_, sqlx::Error>((latest, oldest))
+    }
+    .await;
+    let (latest, oldest) = match bounds {
+        Ok(bounds) => bounds,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to open change stream"
+            }))
+        }
+    };
+
+    let mut preamble = String::from("retry: 3000\n\n");
+    let last_id = match resume_from {
+        Some(id) if id > latest || oldest.is_some_and(|oldest| id + 1 < oldest) => {
+            preamble.push_str(&format!("id: {}\nevent: reset\ndata: {{}}\n\n", latest));
+            latest
+        }
+        Some(id) => id,
+        None => latest,
+    };
+
+    let state = StreamState {
+        pool: pool.get_ref().clone(),
+        query,
+        last_id,
+        preamble: Some(preamble),
+    };
+
+    let stream = futures_util::stream::unfold(state, |mut state| async move {
+        if let Some(preamble) = state.preamble.take() {
+            return Some((Ok::<_, actix_web::Error>(Bytes::from(preamble)), state));
+        }
+
+        let mut idle_polls = 0;
+        loop {
+            match change_feed::fetch_since(
+                &state.pool,
+                &state.query,
+                state.last_id,
+                STREAM_BATCH_SIZE,
+            )
+            .await
+            {
+                Ok(events) if !events.is_empty() => {
+                    let mut chunk = String::new();
+                    for event in events {
+                        chunk.push_str(&format!(
+                            "id: {}\nevent: {}\ndata: {}\n\n",
+                            event.id, event.event_type, event.payload
+                        ));
+                        state.last_id = event.id;
+                    }
+                    return Some((Ok(Bytes::from(chunk)), state));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // Closing lets the client reconnect from its last event id.
+                    log::warn!("Change stream error: {}", e);
+                    return None;
+                }
+            }
+
+            idle_polls += 1;
+            if idle_polls >= KEEP_ALIVE_POLLS {
+                return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), state));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
@@ -1,46 +1,107 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
 use sqlx::SqlitePool;
-use std::path::Path;
+use std::collections::BTreeSet;
 
 use crate::{
+    audit::AuditContext,
+    changes,
     config::AppConfig,
-    models::{Content, ContentCreate, ContentUpdate},
+    contents::{self, ContentError},
+    etag, galleries, merge_patch,
+    models::{Content, ContentCreate, ContentFields, ContentQuery, ContentReorder, ContentUpdate},
+    rich_text,
+    taxonomies::{self, Tagged},
 };
 
+/// Response for a failed content write; `action` names the write for
+/// database errors.
+fn content_error(e: ContentError, action: &str) -> HttpResponse {
+    match e {
+        ContentError::Invalid(message) => {
+            HttpResponse::BadRequest().json(serde_json::json!({"error": message}))
+        }
+        ContentError::Database(_) => HttpResponse::InternalServerError()
+            .json(serde_json::json!({"error": format!("Failed to {} content", action)})),
+    }
+}
+
 pub async fn create_content(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     content_data: web::Json<ContentCreate>,
 ) -> impl Responder {
-    let result = sqlx::query(
-        "INSERT INTO contents (ref_id, short_desc, long_desc, image_path, title)
-         VALUES (?, ?, ?, ?, ?)",
-    )
-    .bind(content_data.ref_id)
-    .bind(&content_data.short_desc)
-    .bind(&content_data.long_desc)
-    .bind(&content_data.image_path)
-    .bind(&content_data.title)
-    .execute(pool.get_ref())
+    let fields = ContentFields {
+        ref_id: content_data.ref_id,
+        short_desc: content_data.short_desc.clone(),
+        long_desc: content_data.long_desc.clone(),
+        format: content_data
+            .format
+            .clone()
+            .unwrap_or_else(|| rich_text::DEFAULT_FORMAT.to_string()),
+        image_path: content_data.image_path.clone(),
+        title: content_data.title.clone(),
+        display_order: content_data.display_order.unwrap_or(0),
+        type_id: content_data.type_id,
+        fields: content_data.fields.clone(),
+    };
+
+    let result: Result<Content, ContentError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        let content = contents::create(&mut tx, &context, &fields).await?;
+        tx.commit().await?;
+        Ok(content)
+    }
     .await;
 
     match result {
-        Ok(result) => {
-            let content_id = result.last_insert_rowid();
-            HttpResponse::Created().json(serde_json::json!({
-                "id": content_id,
+        Ok(content) => HttpResponse::Created()
+            .insert_header((header::ETAG, etag::etag(content.version)))
+            .json(serde_json::json!({
+                "id": content.id,
+                "version": content.version,
                 "message": "Content created successfully"
-            }))
-        }
-        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create content"
-        })),
+            })),
+        Err(e) => content_error(e, "create"),
     }
 }
 
-pub async fn get_contents(pool: web::Data<SqlitePool>) -> impl Responder {
-    let contents = sqlx::query_as::<_, Content>("SELECT * FROM contents ORDER BY id DESC")
+pub async fn get_contents(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<ContentQuery>,
+) -> impl Responder {
+    let term_ids = match query.terms.as_deref().map(taxonomies::parse_terms) {
+        Some(Ok(term_ids)) if !term_ids.is_empty() => Some(term_ids),
+        Some(Err(message)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": message
+            }))
+        }
+        _ => None,
+    };
+
+    let contents: Result<Vec<Content>, sqlx::Error> = async {
+        let contents = sqlx::query_as::<_, Content>(
+            "SELECT * FROM contents WHERE ? IS NULL OR type_id = ? ORDER BY id DESC",
+        )
+        .bind(query.type_id)
+        .bind(query.type_id)
         .fetch_all(pool.get_ref())
-        .await;
+        .await?;
+        let mut contents: Vec<Content> = match term_ids {
+            Some(term_ids) => {
+                let tagged = taxonomies::matching(&pool, Tagged::Content, &term_ids).await?;
+                contents
+                    .into_iter()
+                    .filter(|content| tagged.contains(&content.id))
+                    .collect()
+            }
+            None => contents,
+        };
+        galleries::attach(&pool, &mut contents).await?;
+        Ok(contents)
+    }
+    .await;
 
     match contents {
         Ok(contents) => HttpResponse::Ok().json(contents),
@@ -54,13 +115,21 @@ pub async fn get_content(
     pool: web::Data<SqlitePool>,
     content_id: web::Path<i64>,
 ) -> impl Responder {
-    let content = sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
-        .bind(*content_id)
-        .fetch_optional(pool.get_ref())
-        .await;
+    let content: Result<Option<Content>, sqlx::Error> = async {
+        let content = sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
+            .bind(*content_id)
+            .fetch_optional(pool.get_ref())
+            .await?;
+        let mut contents = Vec::from_iter(content);
+        galleries::attach(&pool, &mut contents).await?;
+        Ok(contents.pop())
+    }
+    .await;
 
     match content {
-        Ok(Some(content)) => HttpResponse::Ok().json(content),
+        Ok(Some(content)) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag::etag(content.version)))
+            .json(content),
         Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
             "error": "Content not found"
         })),
@@ -74,11 +143,17 @@ pub async fn get_contents_by_ref(
     pool: web::Data<SqlitePool>,
     ref_id: web::Path<i64>,
 ) -> impl Responder {
-    let contents =
-        sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE ref_id = ? ORDER BY id DESC")
-            .bind(*ref_id)
-            .fetch_all(pool.get_ref())
-            .await;
+    let contents: Result<Vec<Content>, sqlx::Error> = async {
+        let mut contents = sqlx::query_as::<_, Content>(
+            "SELECT * FROM contents WHERE ref_id = ? ORDER BY display_order, id DESC",
+        )
+        .bind(*ref_id)
+        .fetch_all(pool.get_ref())
+        .await?;
+        galleries::attach(&pool, &mut contents).await?;
+        Ok(contents)
+    }
+    .await;
 
     match contents {
         Ok(contents) => HttpResponse::Ok().json(contents),
@@ -88,110 +163,234 @@ pub async fn get_contents_by_ref(
     }
 }
 
-pub async fn update_content(
+/// Rewrites `display_order` of every content under a page to match `ids`.
+/// Contents whose position changes get a new version.
+pub async fn reorder_contents(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
-    content_id: web::Path<i64>,
-    content_data: web::Json<ContentUpdate>,
-    config: web::Data<AppConfig>,
+    ref_id: web::Path<i64>,
+    reorder: web::Json<ContentReorder>,
 ) -> impl Responder {
-    let upload_dir = &config.upload_dir;
-    
-    let existing = match sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
-        .bind(*content_id)
-        .fetch_optional(pool.get_ref())
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Database error"}))
+        }
+    };
+
+    let scope = match sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE ref_id = ?")
+        .bind(*ref_id)
+        .fetch_all(&mut *tx)
         .await
     {
-        Ok(Some(content)) => content,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({"error": "Content not found"}))
-        }
+        Ok(contents) => contents,
         Err(_) => {
             return HttpResponse::InternalServerError()
                 .json(serde_json::json!({"error": "Database error"}))
         }
     };
 
-    let ref_id = content_data.ref_id.unwrap_or(existing.ref_id);
-    let short_desc = content_data
-        .short_desc
-        .as_ref()
-        .or(existing.short_desc.as_ref());
-    let long_desc = content_data
-        .long_desc
-        .as_ref()
-        .or(existing.long_desc.as_ref());
-    let image_path = content_data
-        .image_path
-        .as_ref()
-        .or(existing.image_path.as_ref());
-    let title = content_data.title.as_ref().or(existing.title.as_ref());
-
-    let result = sqlx::query(
-        "UPDATE contents SET ref_id = ?, short_desc = ?, long_desc = ?, image_path = ?, 
-         title = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-    )
-    .bind(ref_id)
-    .bind(short_desc)
-    .bind(long_desc)
-    .bind(image_path)
-    .bind(title)
-    .bind(*content_id)
-    .execute(pool.get_ref())
-    .await;
+    let expected: BTreeSet<i64> = scope.iter().map(|content| content.id).collect();
+    let given: BTreeSet<i64> = reorder.ids.iter().copied().collect();
+    if given.len() != reorder.ids.len() || given != expected {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "ids must list every content of the page exactly once",
+            "expected": expected
+        }));
+    }
 
-    match result {
-        Ok(_) => {
-            if let Some(new_path) = &content_data.image_path {
-                if let Some(old_path) = &existing.image_path {
-                    if old_path != new_path {
-                        let full_old_path = Path::new(&**upload_dir).join(old_path);
-                        let _ = std::fs::remove_file(full_old_path);
-                    }
-                }
+    let context = AuditContext::from_request(&req);
+    let result: Result<(), sqlx::Error> = async {
+        for (position, id) in reorder.ids.iter().enumerate() {
+            let moved = sqlx::query_as::<_, Content>(
+                "UPDATE contents SET display_order = ?, version = version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ? AND display_order != ? RETURNING *",
+            )
+            .bind(position as i32)
+            .bind(id)
+            .bind(position as i32)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some(moved) = moved {
+                let before = scope.iter().find(|content| content.id == moved.id);
+                changes::record(&mut tx, &context, "reorder", "content", moved.id, before, Some(&moved))
+                    .await?;
             }
-            HttpResponse::Ok().json(serde_json::json!({"message": "Content updated successfully"}))
         }
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({"message": "Contents reordered successfully"})),
         Err(_) => HttpResponse::InternalServerError()
-            .json(serde_json::json!({"error": "Failed to update database"})),
+            .json(serde_json::json!({"error": "Failed to reorder contents"})),
     }
 }
 
+pub async fn update_content(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    content_data: web::Json<ContentUpdate>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let existing = match fetch_content(&pool, *content_id).await {
+        Ok(content) => content,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    let fields = ContentFields {
+        ref_id: content_data.ref_id.unwrap_or(existing.ref_id),
+        short_desc: content_data.short_desc.clone().or_else(|| existing.short_desc.clone()),
+        long_desc: content_data.long_desc.clone().or_else(|| existing.long_desc.clone()),
+        format: content_data.format.clone().unwrap_or_else(|| existing.format.clone()),
+        image_path: content_data.image_path.clone().or_else(|| existing.image_path.clone()),
+        title: content_data.title.clone().or_else(|| existing.title.clone()),
+        display_order: content_data.display_order.unwrap_or(existing.display_order),
+        type_id: content_data.type_id.or(existing.type_id),
+        fields: content_data
+            .fields
+            .clone()
+            .or_else(|| ContentFields::from(&existing).fields),
+    };
+
+    save_content(&req, &pool, &config, &existing, &fields).await
+}
+
+pub async fn patch_content(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    patch: web::Json<serde_json::Value>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let existing = match fetch_content(&pool, *content_id).await {
+        Ok(content) => content,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    let fields = match merge_patch::apply(&ContentFields::from(&existing), &patch) {
+        Ok(fields) => fields,
+        Err(error) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": error
+            }))
+        }
+    };
+
+    save_content(&req, &pool, &config, &existing, &fields).await
+}
+
 pub async fn delete_content(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     content_id: web::Path<i64>,
     config: web::Data<AppConfig>,
 ) -> impl Responder {
     let upload_dir = &config.upload_dir;
 
-    let content = match sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
-        .bind(*content_id)
-        .fetch_optional(pool.get_ref())
-        .await
-    {
-        Ok(Some(c)) => c,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({"error": "Not found"}))
+    let content = match fetch_content(&pool, *content_id).await {
+        Ok(content) => content,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, content.version, &content) {
+        return response;
+    }
+
+    let result: Result<Option<Vec<String>>, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        let Some(files) = contents::delete(&mut tx, &context, &content).await? else {
+            return Ok(None);
+        };
+        tx.commit().await?;
+        Ok(Some(files))
+    }
+    .await;
+
+    match result {
+        Ok(Some(files)) => {
+            let _ = galleries::remove_files(&pool, upload_dir, &files).await;
+            HttpResponse::Ok().json(serde_json::json!({"message": "Deleted successfully"}))
         }
+        Ok(None) => conflict(&pool, content.id).await,
         Err(_) => {
-            return HttpResponse::InternalServerError()
-                .json(serde_json::json!({"error": "DB error"}))
+            HttpResponse::InternalServerError().json(serde_json::json!({"error": "Delete failed"}))
         }
-    };
+    }
+}
 
-    match sqlx::query("DELETE FROM contents WHERE id = ?")
-        .bind(*content_id)
-        .execute(pool.get_ref())
+async fn fetch_content(pool: &SqlitePool, content_id: i64) -> Result<Content, HttpResponse> {
+    match sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
+        .bind(content_id)
+        .fetch_optional(pool)
         .await
     {
-        Ok(_) => {
-            if let Some(path) = content.image_path {
-                let full_path = Path::new(&**upload_dir).join(path);
-                let _ = std::fs::remove_file(full_path);
-            }
-            HttpResponse::Ok().json(serde_json::json!({"message": "Deleted successfully"}))
+        Ok(Some(content)) => Ok(content),
+        Ok(None) => {
+            Err(HttpResponse::NotFound().json(serde_json::json!({"error": "Content not found"})))
         }
-        Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!({"error": "Delete failed"}))
+        Err(_) => Err(HttpResponse::InternalServerError()
+            .json(serde_json::json!({"error": "Database error"}))),
+    }
+}
+
+/// Writes `fields` over `existing` if its version is still current, recording
+/// the change in the audit log within the same transaction. The old image file
+/// is removed whenever the stored `image_path` changes, unless the content
+/// still has it attached.
+async fn save_content(
+    req: &HttpRequest,
+    pool: &SqlitePool,
+    config: &AppConfig,
+    existing: &Content,
+    fields: &ContentFields,
+) -> HttpResponse {
+    let result: Result<Option<Content>, ContentError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(req);
+        let Some(updated) = contents::update(&mut tx, &context, existing, fields).await? else {
+            return Ok(None);
+        };
+        tx.commit().await?;
+        Ok(Some(updated))
+    }
+    .await;
+
+    match result {
+        Ok(Some(content)) => {
+            let dropped = contents::dropped_images(existing, &content);
+            let _ = galleries::remove_files(pool, &config.upload_dir, &dropped).await;
+            HttpResponse::Ok()
+                .insert_header((header::ETAG, etag::etag(content.version)))
+                .json(serde_json::json!({
+                    "message": "Content updated successfully",
+                    "version": content.version
+                }))
         }
+        Ok(None) => conflict(pool, existing.id).await,
+        Err(e) => content_error(e, "update"),
+    }
+}
+
+/// Response for a guarded write that matched no row: the content either
+/// changed since it was read (412 with the current state) or is gone (404).
+async fn conflict(pool: &SqlitePool, content_id: i64) -> HttpResponse {
+    match fetch_content(pool, content_id).await {
+        Ok(current) => etag::precondition_failed(current.version, &current),
+        Err(response) => response,
     }
 }
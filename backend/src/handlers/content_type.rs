@@ -0,0 +1,331 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::content_types::{self, ContentTypeError};
+use crate::models::content_type::{
+    ContentType, ContentTypeCreate, ContentTypeDetail, ContentTypeUpdate, FieldSpec, FIELD_TYPES,
+};
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+fn content_type_error(e: ContentTypeError, action: &str) -> HttpResponse {
+    match e {
+        ContentTypeError::Invalid(message) => bad_request(message),
+        ContentTypeError::Conflict(message, contents) => {
+            HttpResponse::Conflict().json(serde_json::json!({
+                "error": message,
+                "contents": contents
+            }))
+        }
+        ContentTypeError::Database(_) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to {} content type", action)
+            }))
+        }
+    }
+}
+
+fn check_definition(
+    name: &str,
+    label: &str,
+    fields: Option<&[FieldSpec]>,
+) -> Result<(), HttpResponse> {
+    content_types::check_name(name).map_err(bad_request)?;
+    if label.trim().is_empty() || label.chars().count() > 100 {
+        return Err(bad_request("label must be 1 to 100 characters"));
+    }
+    if let Some(fields) = fields {
+        content_types::check_specs(fields).map_err(|error| {
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "error": error,
+                "field_types": FIELD_TYPES
+            }))
+        })?;
+    }
+    Ok(())
+}
+
+async fn fetch_content_type(pool: &SqlitePool, type_id: i64) -> Result<ContentType, HttpResponse> {
+    let content_type = sqlx::query_as::<_, ContentType>("SELECT * FROM content_types WHERE id = ?")
+        .bind(type_id)
+        .fetch_optional(pool)
+        .await;
+
+    match content_type {
+        Ok(Some(content_type)) => Ok(content_type),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Content type not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn detail(
+    pool: &SqlitePool,
+    content_type: ContentType,
+) -> Result<ContentTypeDetail, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let fields = content_types::fields_of(&mut conn, content_type.id).await?;
+    Ok(ContentTypeDetail {
+        content_type,
+        fields,
+    })
+}
+
+async fn name_taken(pool: &SqlitePool, name: &str, type_id: i64) -> Result<(), HttpResponse> {
+    let taken: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM content_types WHERE name = ? AND id != ?")
+            .bind(name)
+            .bind(type_id)
+            .fetch_optional(pool)
+            .await;
+    match taken {
+        Ok(None) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Content type '{}' already exists", name)
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+pub async fn create_content_type(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    type_data: web::Json<ContentTypeCreate>,
+) -> impl Responder {
+    let label = type_data.label.as_deref().unwrap_or(&type_data.name);
+    if let Err(response) = check_definition(&type_data.name, label, Some(&type_data.fields)) {
+        return response;
+    }
+    if let Err(response) = name_taken(&pool, &type_data.name, 0).await {
+        return response;
+    }
+
+    let result: Result<ContentTypeDetail, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let content_type = sqlx::query_as::<_, ContentType>(
+            "INSERT INTO content_types (name, label) VALUES (?, ?) RETURNING *",
+        )
+        .bind(&type_data.name)
+        .bind(label.trim())
+        .fetch_one(&mut *tx)
+        .await?;
+        content_types::set_fields(&mut tx, content_type.id, &type_data.fields).await?;
+        let fields = content_types::fields_of(&mut tx, content_type.id).await?;
+        let created = ContentTypeDetail {
+            content_type,
+            fields,
+        };
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "content_type",
+            created.content_type.id,
+            None::<&ContentTypeDetail>,
+            Some(&created),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(created)
+    }
+    .await;
+
+    match result {
+        Ok(created) => HttpResponse::Created().json(created),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create content type"
+        })),
+    }
+}
+
+pub async fn get_content_types(pool: web::Data<SqlitePool>) -> impl Responder {
+    let result: Result<Vec<ContentTypeDetail>, sqlx::Error> = async {
+        let content_types =
+            sqlx::query_as::<_, ContentType>("SELECT * FROM content_types ORDER BY name")
+                .fetch_all(pool.get_ref())
+                .await?;
+        let mut details = Vec::with_capacity(content_types.len());
+        for content_type in content_types {
+            details.push(detail(&pool, content_type).await?);
+        }
+        Ok(details)
+    }
+    .await;
+
+    match result {
+        Ok(details) => HttpResponse::Ok().json(details),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch content types"
+        })),
+    }
+}
+
+pub async fn get_content_type(
+    pool: web::Data<SqlitePool>,
+    type_id: web::Path<i64>,
+) -> impl Responder {
+    let content_type = match fetch_content_type(&pool, *type_id).await {
+        Ok(content_type) => content_type,
+        Err(response) => return response,
+    };
+
+    match detail(&pool, content_type).await {
+        Ok(detail) => HttpResponse::Ok().json(detail),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch content type"
+        })),
+    }
+}
+
+/// Updates a content type. A new field list replaces the old one; contents
+/// of the type lose values of removed fields, and a field type that existing
+/// values do not fit gets `409` with the ids of those `contents`.
+pub async fn update_content_type(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    type_id: web::Path<i64>,
+    type_data: web::Json<ContentTypeUpdate>,
+) -> impl Responder {
+    let existing = match fetch_content_type(&pool, *type_id).await {
+        Ok(content_type) => content_type,
+        Err(response) => return response,
+    };
+
+    let name = type_data
+        .name
+        .clone()
+        .unwrap_or_else(|| existing.name.clone());
+    let label = type_data
+        .label
+        .clone()
+        .unwrap_or_else(|| existing.label.clone());
+    if let Err(response) = check_definition(&name, &label, type_data.fields.as_deref()) {
+        return response;
+    }
+    if let Err(response) = name_taken(&pool, &name, existing.id).await {
+        return response;
+    }
+    let before = match detail(&pool, existing).await {
+        Ok(before) => before,
+        Err(_) => return database_error(),
+    };
+
+    let result: Result<ContentTypeDetail, ContentTypeError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        let content_type = sqlx::query_as::<_, ContentType>(
+            "UPDATE content_types SET name = ?, label = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(&name)
+        .bind(label.trim())
+        .bind(before.content_type.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(fields) = &type_data.fields {
+            content_types::set_fields(&mut tx, content_type.id, fields).await?;
+            content_types::conform(&mut tx, &context, content_type.id).await?;
+        }
+        let fields = content_types::fields_of(&mut tx, content_type.id).await?;
+        let updated = ContentTypeDetail {
+            content_type,
+            fields,
+        };
+
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "content_type",
+            updated.content_type.id,
+            Some(&before),
+            Some(&updated),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    match result {
+        Ok(updated) => HttpResponse::Ok().json(updated),
+        Err(e) => content_type_error(e, "update"),
+    }
+}
+
+/// Deletes a content type no content uses.
+pub async fn delete_content_type(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    type_id: web::Path<i64>,
+) -> impl Responder {
+    let existing = match fetch_content_type(&pool, *type_id).await {
+        Ok(content_type) => content_type,
+        Err(response) => return response,
+    };
+    let before = match detail(&pool, existing).await {
+        Ok(before) => before,
+        Err(_) => return database_error(),
+    };
+
+    let result: Result<(), ContentTypeError> = async {
+        let mut tx = pool.begin().await?;
+        let contents: Vec<i64> =
+            sqlx::query_scalar("SELECT id FROM contents WHERE type_id = ? ORDER BY id")
+                .bind(before.content_type.id)
+                .fetch_all(&mut *tx)
+                .await?;
+        if !contents.is_empty() {
+            return Err(ContentTypeError::Conflict(
+                format!(
+                    "Content type '{}' is used by {} contents",
+                    before.content_type.name,
+                    contents.len()
+                ),
+                contents,
+            ));
+        }
+
+        sqlx::query("DELETE FROM content_types WHERE id = ?")
+            .bind(before.content_type.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "content_type",
+            before.content_type.id,
+            Some(&before),
+            None::<&ContentTypeDetail>,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Content type deleted successfully"
+        })),
+        Err(e) => content_type_error(e, "delete"),
+    }
+}
@@ -0,0 +1,222 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+use crate::config::AppConfig;
+use crate::etag;
+use crate::feeds::{self, Feed, FeedFormat, FeedItem, SitemapUrl};
+use crate::handlers::render::{api_links, page_href};
+use crate::languages;
+use crate::render::{self, Alternate};
+
+/// Most recent contents listed in a feed.
+const FEED_LIMIT: usize = 50;
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub section_name: Option<String>,
+    pub lang: Option<String>,
+}
+
+/// Origin for the absolute URLs sitemaps and feeds require:
+/// `PUBLIC_BASE_URL`, or else the scheme and host the request came in on,
+/// which honours `Forwarded` and `X-Forwarded-*` headers from a proxy.
+fn absolute_base(req: &HttpRequest, config: &AppConfig) -> String {
+    let base = config.public_base_url.trim_end_matches('/');
+    if !base.is_empty() {
+        return base.to_string();
+    }
+    let connection = req.connection_info();
+    format!("{}://{}", connection.scheme(), connection.host())
+}
+
+/// Every visible `page_name` in every enabled language it has visible pages
+/// in, linking to the server-side rendering, with hreflang alternates.
+pub async fn get_sitemap(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let rows: Result<Vec<(String, String, String)>, sqlx::Error> = sqlx::query_as(
+        "SELECT p.page_name, p.lang,
+                MAX(p.updated_at, COALESCE(
+                    (SELECT MAX(c.updated_at) FROM contents c WHERE c.ref_id = p.id), ''))
+         FROM pages p JOIN languages l ON l.code = p.lang
+         WHERE p.visible = 1 AND l.enabled = 1
+         ORDER BY p.page_name, p.lang",
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to build sitemap"
+            }))
+        }
+    };
+
+    // Fallback pages make every language of a page_name depend on all of
+    // them, so each URL takes the latest change of the whole page_name.
+    let mut published: BTreeMap<String, (Vec<String>, String)> = BTreeMap::new();
+    for (page_name, lang, updated_at) in rows {
+        let (langs, lastmod) = published.entry(page_name).or_default();
+        if !langs.contains(&lang) {
+            langs.push(lang);
+        }
+        if updated_at > *lastmod {
+            *lastmod = updated_at;
+        }
+    }
+
+    let base = &absolute_base(&req, &config);
+    let mut urls = Vec::new();
+    let mut last_modified: Option<DateTime<Utc>> = None;
+    for (page_name, (langs, lastmod)) in &published {
+        let modified = feeds::parse_timestamp(lastmod);
+        last_modified = last_modified.max(modified);
+        for lang in langs {
+            urls.push(SitemapUrl {
+                loc: page_href(base, page_name, lang),
+                lastmod: modified.map(|modified| modified.format("%Y-%m-%d").to_string()),
+                alternates: langs
+                    .iter()
+                    .map(|alternate| Alternate {
+                        lang: alternate.clone(),
+                        href: page_href(base, page_name, alternate),
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    etag::cacheable(
+        &req,
+        "application/xml; charset=utf-8",
+        feeds::sitemap_xml(&urls),
+        last_modified,
+    )
+}
+
+/// RSS, Atom or JSON Feed of the contents of a `page_name`, optionally one
+/// section, newest first. Languages resolve like the public pages API.
+pub async fn get_feed(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    path: web::Path<(String, String)>,
+    query: web::Query<FeedQuery>,
+) -> impl Responder {
+    let (page_name, format) = path.into_inner();
+    let Some(format) = FeedFormat::parse(&format) else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("Unknown feed format '{}'", format),
+            "formats": ["rss", "atom", "json"]
+        }));
+    };
+
+    let lang = match &query.lang {
+        Some(lang) => languages::normalize_tag(lang).unwrap_or_else(|| lang.clone()),
+        None => match languages::default_code(pool.get_ref()).await {
+            Ok(code) => code.unwrap_or_default(),
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Database error"
+                }))
+            }
+        },
+    };
+
+    let pages = match languages::resolve_slot(
+        pool.get_ref(),
+        &page_name,
+        query.section_name.as_deref(),
+        Some(&lang),
+    )
+    .await
+    {
+        Ok(pages) if pages.is_empty() => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Page not found"
+            }))
+        }
+        Ok(pages) => pages,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to build feed"
+            }))
+        }
+    };
+
+    let base = &absolute_base(&req, &config);
+    let page_href = |page_name: &str, lang: &str| page_href(base, page_name, lang);
+    let links = api_links(base, &page_href);
+    let link = page_href(&page_name, &lang);
+
+    let mut items: Vec<FeedItem> = pages
+        .iter()
+        .flat_map(|page| &page.contents)
+        .map(|content| FeedItem {
+            id: format!("{}#content-{}", link, content.id),
+            title: content
+                .title
+                .clone()
+                .or_else(|| content.short_desc.clone())
+                .unwrap_or_else(|| page_name.clone()),
+            summary: content.short_desc.clone(),
+            link: link.clone(),
+            image: links
+                .images(content.image_path.as_deref())
+                .into_iter()
+                .next()
+                .or_else(|| {
+                    content
+                        .gallery
+                        .first()
+                        .map(|image| links.image(&image.filename))
+                }),
+            updated: feeds::parse_timestamp(&content.updated_at).unwrap_or_default(),
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.updated));
+    items.truncate(FEED_LIMIT);
+
+    let page_updated = pages
+        .iter()
+        .filter_map(|page| feeds::parse_timestamp(&page.page.updated_at))
+        .max();
+    let updated = items
+        .iter()
+        .map(|item| item.updated)
+        .max()
+        .max(page_updated)
+        .unwrap_or_default();
+
+    let mut title = render::document_title(&pages, &page_name);
+    if let Some(section_name) = &query.section_name {
+        title = format!("{} - {}", title, section_name);
+    }
+    let author = if config.feed_author.is_empty() {
+        title.clone()
+    } else {
+        config.feed_author.clone()
+    };
+    let feed = Feed {
+        title,
+        author,
+        lang,
+        link,
+        self_url: format!("{}{}", base, req.uri()),
+        updated,
+        items,
+    };
+
+    etag::cacheable(
+        &req,
+        format.content_type(),
+        feeds::render(format, &feed),
+        Some(updated),
+    )
+}
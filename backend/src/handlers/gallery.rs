@@ -0,0 +1,345 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::config::AppConfig;
+use crate::galleries;
+use crate::models::gallery::{ContentImage, ContentImageCreate, ContentImageUpdate};
+use crate::models::ContentReorder;
+use crate::site;
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+fn check_text(name: &str, text: Option<&str>) -> Result<(), HttpResponse> {
+    match text {
+        Some(text) if text.chars().count() > 200 => Err(bad_request(format!(
+            "{} must be at most 200 characters",
+            name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+async fn check_content(pool: &SqlitePool, content_id: i64) -> Result<(), HttpResponse> {
+    let exists: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM contents WHERE id = ?")
+            .bind(content_id)
+            .fetch_optional(pool)
+            .await;
+    match exists {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Content not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn fetch_image(
+    pool: &SqlitePool,
+    content_id: i64,
+    image_id: i64,
+) -> Result<ContentImage, HttpResponse> {
+    let image = sqlx::query_as::<_, ContentImage>(
+        "SELECT * FROM content_images WHERE id = ? AND content_id = ?",
+    )
+    .bind(image_id)
+    .bind(content_id)
+    .fetch_optional(pool)
+    .await;
+
+    match image {
+        Ok(Some(image)) => Ok(image),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Image not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+pub async fn get_content_images(
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+) -> impl Responder {
+    if let Err(response) = check_content(&pool, *content_id).await {
+        return response;
+    }
+
+    let images: Result<Vec<ContentImage>, sqlx::Error> = async {
+        let mut conn = pool.acquire().await?;
+        galleries::of_content(&mut conn, *content_id).await
+    }
+    .await;
+
+    match images {
+        Ok(images) => HttpResponse::Ok().json(images),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch images"
+        })),
+    }
+}
+
+/// Attaches an uploaded image to a content, at the end of its gallery unless
+/// `display_order` says otherwise. The file then belongs to the content.
+pub async fn add_content_image(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    image_data: web::Json<ContentImageCreate>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    if let Err(response) = check_content(&pool, *content_id).await {
+        return response;
+    }
+    if let Err(response) = check_text("caption", image_data.caption.as_deref())
+        .and_then(|_| check_text("alt", image_data.alt.as_deref()))
+    {
+        return response;
+    }
+    if !site::is_file_name(&image_data.filename)
+        || !Path::new(&config.upload_dir)
+            .join(&image_data.filename)
+            .is_file()
+    {
+        return bad_request(format!(
+            "'{}' is not an uploaded image",
+            image_data.filename
+        ));
+    }
+
+    let result: Result<Option<ContentImage>, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let attached: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM content_images WHERE content_id = ? AND filename = ?",
+        )
+        .bind(*content_id)
+        .bind(&image_data.filename)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if attached.is_some() {
+            return Ok(None);
+        }
+
+        let image = sqlx::query_as::<_, ContentImage>(
+            "INSERT INTO content_images (content_id, filename, caption, alt, display_order)
+             VALUES (?, ?, ?, ?, COALESCE(?, (SELECT COALESCE(MAX(display_order) + 1, 0)
+                 FROM content_images WHERE content_id = ?)))
+             RETURNING *",
+        )
+        .bind(*content_id)
+        .bind(&image_data.filename)
+        .bind(&image_data.caption)
+        .bind(&image_data.alt)
+        .bind(image_data.display_order)
+        .bind(*content_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "content_image",
+            image.id,
+            None::<&ContentImage>,
+            Some(&image),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(Some(image))
+    }
+    .await;
+
+    match result {
+        Ok(Some(image)) => HttpResponse::Created().json(image),
+        Ok(None) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("'{}' is already attached to this content", image_data.filename)
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to attach image"
+        })),
+    }
+}
+
+pub async fn update_content_image(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    image_data: web::Json<ContentImageUpdate>,
+) -> impl Responder {
+    let (content_id, image_id) = path.into_inner();
+    let existing = match fetch_image(&pool, content_id, image_id).await {
+        Ok(image) => image,
+        Err(response) => return response,
+    };
+    if let Err(response) = check_text("caption", image_data.caption.as_deref())
+        .and_then(|_| check_text("alt", image_data.alt.as_deref()))
+    {
+        return response;
+    }
+
+    let result: Result<ContentImage, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let image = sqlx::query_as::<_, ContentImage>(
+            "UPDATE content_images SET caption = ?, alt = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(&image_data.caption)
+        .bind(&image_data.alt)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "content_image",
+            image.id,
+            Some(&existing),
+            Some(&image),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(image)
+    }
+    .await;
+
+    match result {
+        Ok(image) => HttpResponse::Ok().json(image),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to update image"
+        })),
+    }
+}
+
+/// Detaches an image from a content and deletes its file, unless the
+/// content's `image_path` or anything else still refers to it.
+pub async fn delete_content_image(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    let (content_id, image_id) = path.into_inner();
+    let existing = match fetch_image(&pool, content_id, image_id).await {
+        Ok(image) => image,
+        Err(response) => return response,
+    };
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM content_images WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "content_image",
+            existing.id,
+            Some(&existing),
+            None::<&ContentImage>,
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            let _ = galleries::remove_files(
+                &pool,
+                &config.upload_dir,
+                std::slice::from_ref(&existing.filename),
+            )
+            .await;
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "Image removed successfully"
+            }))
+        }
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to remove image"
+        })),
+    }
+}
+
+/// Rewrites `display_order` of a content's images to match `ids`.
+pub async fn reorder_content_images(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    reorder: web::Json<ContentReorder>,
+) -> impl Responder {
+    if let Err(response) = check_content(&pool, *content_id).await {
+        return response;
+    }
+
+    let result: Result<bool, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let gallery = galleries::of_content(&mut tx, *content_id).await?;
+        let expected: BTreeSet<i64> = gallery.iter().map(|image| image.id).collect();
+        let given: BTreeSet<i64> = reorder.ids.iter().copied().collect();
+        if given.len() != reorder.ids.len() || given != expected {
+            return Ok(false);
+        }
+
+        let context = AuditContext::from_request(&req);
+        for (position, id) in reorder.ids.iter().enumerate() {
+            let moved = sqlx::query_as::<_, ContentImage>(
+                "UPDATE content_images SET display_order = ?, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ? AND display_order != ? RETURNING *",
+            )
+            .bind(position as i32)
+            .bind(id)
+            .bind(position as i32)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some(moved) = moved {
+                let before = gallery.iter().find(|image| image.id == moved.id);
+                changes::record(
+                    &mut tx,
+                    &context,
+                    "reorder",
+                    "content_image",
+                    moved.id,
+                    before,
+                    Some(&moved),
+                )
+                .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
+
+    match result {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Images reordered successfully"
+        })),
+        Ok(false) => bad_request("ids must list every image of the content exactly once"),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to reorder images"
+        })),
+    }
+}
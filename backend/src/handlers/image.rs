@@ -1,24 +1,48 @@
 use actix_multipart::Multipart;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use futures_util::stream::StreamExt as _;
+use sqlx::SqlitePool;
 use std::io::Write;
 use std::path::Path;
 use uuid::Uuid;
 
+use crate::audit::AuditContext;
+use crate::changes;
 use crate::config::AppConfig;
+use crate::galleries;
+use crate::site;
+
+/// Fresh `<uuid>_<timestamp>.<ext>` name for a stored image, keeping the
+/// extension of `original`.
+pub fn unique_filename(original: &str) -> String {
+    let extension = Path::new(original)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("jpg");
+
+    format!(
+        "{}_{}.{}",
+        Uuid::new_v4(),
+        chrono::Utc::now().timestamp(),
+        extension
+    )
+}
 
-pub async fn upload_image(mut payload: Multipart, config: web::Data<AppConfig>) -> impl Responder {
+pub async fn upload_image(
+    req: HttpRequest,
+    mut payload: Multipart,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
     let upload_path = Path::new(&config.upload_dir);
 
-    if !upload_path.exists() {
-        if let Err(_) = std::fs::create_dir_all(upload_path) {
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to create upload directory"
-            }));
-        }
+    if !upload_path.exists() && std::fs::create_dir_all(upload_path).is_err() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create upload directory"
+        }));
     }
 
-    while let Some(item) = payload.next().await {
+    if let Some(item) = payload.next().await {
         let mut field = match item {
             Ok(field) => field,
             Err(_) => {
@@ -31,19 +55,7 @@ pub async fn upload_image(mut payload: Multipart, config: web::Data<AppConfig>)
         let content_disposition = field.content_disposition();
         let original_filename = content_disposition.get_filename().unwrap_or("unnamed");
 
-        let extension = Path::new(original_filename)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("jpg");
-
-        let filename = format!(
-            "{}_{}.{}",
-            Uuid::new_v4(),
-            chrono::Utc::now().timestamp(),
-            extension
-        );
-
-        let sanitized_filename = sanitize_filename::sanitize(&filename);
+        let sanitized_filename = sanitize_filename::sanitize(unique_filename(original_filename));
         let filepath = upload_path.join(&sanitized_filename);
 
         let mut file = match std::fs::File::create(&filepath) {
@@ -66,7 +78,7 @@ pub async fn upload_image(mut payload: Multipart, config: web::Data<AppConfig>)
                 }
             };
 
-            if let Err(_) = file.write_all(&data) {
+            if file.write_all(&data).is_err() {
                 let _ = std::fs::remove_file(&filepath);
                 return HttpResponse::InternalServerError().json(serde_json::json!({
                     "error": "Failed to write file"
@@ -74,6 +86,31 @@ pub async fn upload_image(mut payload: Multipart, config: web::Data<AppConfig>)
             }
         }
 
+        let image = serde_json::json!({ "filename": sanitized_filename });
+        let context = AuditContext::from_request(&req);
+        let recorded: Result<(), sqlx::Error> = async {
+            let mut tx = pool.begin().await?;
+            changes::record(
+                &mut tx,
+                &context,
+                "create",
+                "image",
+                &sanitized_filename,
+                None::<&serde_json::Value>,
+                Some(&image),
+            )
+            .await?;
+            tx.commit().await
+        }
+        .await;
+
+        if recorded.is_err() {
+            let _ = std::fs::remove_file(&filepath);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to record upload"
+            }));
+        }
+
         return HttpResponse::Ok().json(serde_json::json!({
             "filename": sanitized_filename,
             "path": format!("/images/{}", sanitized_filename),
@@ -87,9 +124,16 @@ pub async fn upload_image(mut payload: Multipart, config: web::Data<AppConfig>)
 }
 
 pub async fn delete_image(
+    req: HttpRequest,
     filename: web::Path<String>,
+    pool: web::Data<SqlitePool>,
     config: web::Data<AppConfig>,
 ) -> impl Responder {
+    if !site::is_file_name(&filename) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Invalid filename"
+        }));
+    }
     let filepath = Path::new(&config.upload_dir).join(&*filename);
 
     if !filepath.exists() {
@@ -98,11 +142,44 @@ pub async fn delete_image(
         }));
     }
 
-    match std::fs::remove_file(&filepath) {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+    let image = serde_json::json!({ "filename": *filename });
+    let context = AuditContext::from_request(&req);
+    // `None` while a content, block or attachment still uses the file.
+    let result: Result<Option<bool>, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        if galleries::unreferenced(&mut tx, std::slice::from_ref(&*filename))
+            .await?
+            .is_empty()
+        {
+            return Ok(None);
+        }
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "image",
+            &*filename,
+            Some(&image),
+            None::<&serde_json::Value>,
+        )
+        .await?;
+
+        if std::fs::remove_file(&filepath).is_err() {
+            return Ok(Some(false));
+        }
+        tx.commit().await?;
+        Ok(Some(true))
+    }
+    .await;
+
+    match result {
+        Ok(Some(true)) => HttpResponse::Ok().json(serde_json::json!({
             "message": "Image deleted successfully"
         })),
-        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+        Ok(None) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Image is still in use"
+        })),
+        _ => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": "Failed to delete image"
         })),
     }
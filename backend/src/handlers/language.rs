@@ -0,0 +1,415 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::languages::{self, normalize_tag, LanguageError};
+use crate::models::language::{Language, LanguageCreate, LanguageUpdate, DIRECTIONS};
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+fn check_name(name: &str) -> Result<(), HttpResponse> {
+    if name.trim().is_empty() || name.chars().count() > 50 {
+        return Err(bad_request("name must be 1 to 50 characters"));
+    }
+    Ok(())
+}
+
+fn check_direction(direction: &str) -> Result<(), HttpResponse> {
+    if !DIRECTIONS.contains(&direction) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown direction '{}'", direction),
+            "directions": DIRECTIONS
+        })));
+    }
+    Ok(())
+}
+
+/// Joins a fallback chain into the stored form. Every entry must be a
+/// registered language other than `code`, listed once.
+async fn fallback_column(
+    pool: &SqlitePool,
+    code: &str,
+    fallback: &[String],
+) -> Result<String, HttpResponse> {
+    let mut codes: Vec<String> = Vec::with_capacity(fallback.len());
+    for tag in fallback {
+        let Some(fallback_code) = normalize_tag(tag) else {
+            return Err(bad_request(format!(
+                "'{}' is not a valid BCP 47 language tag",
+                tag
+            )));
+        };
+        if fallback_code == code || codes.contains(&fallback_code) {
+            return Err(bad_request(format!(
+                "'{}' appears in the fallback chain more than once or is the language itself",
+                fallback_code
+            )));
+        }
+        match languages::fetch(pool, &fallback_code).await {
+            Ok(Some(_)) => codes.push(fallback_code),
+            Ok(None) => {
+                return Err(bad_request(format!(
+                    "Language '{}' is not registered",
+                    fallback_code
+                )))
+            }
+            Err(_) => return Err(database_error()),
+        }
+    }
+    Ok(codes.join(","))
+}
+
+/// Clears the default flag of whichever other language holds it, so `code`
+/// can take it.
+async fn take_default(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    code: &str,
+) -> Result<(), sqlx::Error> {
+    let previous =
+        sqlx::query_as::<_, Language>("SELECT * FROM languages WHERE is_default = 1 AND code != ?")
+            .bind(code)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    if let Some(previous) = previous {
+        let updated = sqlx::query_as::<_, Language>(
+            "UPDATE languages SET is_default = 0, updated_at = CURRENT_TIMESTAMP
+             WHERE code = ? RETURNING *",
+        )
+        .bind(&previous.code)
+        .fetch_one(&mut *conn)
+        .await?;
+        changes::record(
+            conn,
+            context,
+            "update",
+            "language",
+            &previous.code,
+            Some(&previous),
+            Some(&updated),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn create_language(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    language_data: web::Json<LanguageCreate>,
+) -> impl Responder {
+    let Some(code) = normalize_tag(&language_data.code) else {
+        return bad_request(format!(
+            "'{}' is not a valid BCP 47 language tag",
+            language_data.code
+        ));
+    };
+    if let Err(response) = check_name(&language_data.name) {
+        return response;
+    }
+    let direction = language_data.direction.as_deref().unwrap_or("ltr");
+    if let Err(response) = check_direction(direction) {
+        return response;
+    }
+    let is_default = language_data.is_default.unwrap_or(false);
+    let enabled = language_data.enabled.unwrap_or(true);
+    if is_default && !enabled {
+        return bad_request("The default language must be enabled");
+    }
+
+    match languages::fetch(pool.get_ref(), &code).await {
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("Language '{}' already exists", code)
+            }))
+        }
+        Ok(None) => {}
+        Err(_) => return database_error(),
+    }
+
+    let fallback = match &language_data.fallback {
+        Some(fallback) => match fallback_column(&pool, &code, fallback).await {
+            Ok(fallback) => fallback,
+            Err(response) => return response,
+        },
+        None => String::new(),
+    };
+
+    let result: Result<Language, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        if is_default {
+            take_default(&mut tx, &context, &code).await?;
+        }
+
+        let language = sqlx::query_as::<_, Language>(
+            "INSERT INTO languages (code, name, direction, is_default, enabled, fallback)
+             VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&code)
+        .bind(language_data.name.trim())
+        .bind(direction)
+        .bind(is_default)
+        .bind(enabled)
+        .bind(&fallback)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "language",
+            &language.code,
+            None::<&Language>,
+            Some(&language),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(language)
+    }
+    .await;
+
+    match result {
+        Ok(language) => HttpResponse::Created().json(language),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create language"
+        })),
+    }
+}
+
+pub async fn get_languages(pool: web::Data<SqlitePool>) -> impl Responder {
+    let languages =
+        sqlx::query_as::<_, Language>("SELECT * FROM languages ORDER BY is_default DESC, code")
+            .fetch_all(pool.get_ref())
+            .await;
+
+    match languages {
+        Ok(languages) => HttpResponse::Ok().json(languages),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch languages"
+        })),
+    }
+}
+
+/// Enabled languages for unauthenticated readers, such as a site's language
+/// switcher.
+pub async fn get_public_languages(pool: web::Data<SqlitePool>) -> impl Responder {
+    let languages = sqlx::query_as::<_, Language>(
+        "SELECT * FROM languages WHERE enabled = 1 ORDER BY is_default DESC, code",
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match languages {
+        Ok(languages) => HttpResponse::Ok().json(languages),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch languages"
+        })),
+    }
+}
+
+pub async fn get_language(pool: web::Data<SqlitePool>, code: web::Path<String>) -> impl Responder {
+    match fetch_language(&pool, &code).await {
+        Ok(language) => HttpResponse::Ok().json(language),
+        Err(response) => response,
+    }
+}
+
+pub async fn update_language(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    code: web::Path<String>,
+    language_data: web::Json<LanguageUpdate>,
+) -> impl Responder {
+    let existing = match fetch_language(&pool, &code).await {
+        Ok(language) => language,
+        Err(response) => return response,
+    };
+
+    let name = language_data.name.as_deref().unwrap_or(&existing.name);
+    if let Err(response) = check_name(name) {
+        return response;
+    }
+    let direction = language_data
+        .direction
+        .as_deref()
+        .unwrap_or(&existing.direction);
+    if let Err(response) = check_direction(direction) {
+        return response;
+    }
+    let is_default = language_data.is_default.unwrap_or(existing.is_default);
+    if existing.is_default && !is_default {
+        return bad_request("Make another language the default instead");
+    }
+    let enabled = language_data.enabled.unwrap_or(existing.enabled);
+    if is_default && !enabled {
+        return bad_request("The default language must be enabled");
+    }
+
+    let fallback = match &language_data.fallback {
+        Some(fallback) => match fallback_column(&pool, &existing.code, fallback).await {
+            Ok(fallback) => fallback,
+            Err(response) => return response,
+        },
+        None => existing.fallback.clone(),
+    };
+
+    let result: Result<Language, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        if is_default && !existing.is_default {
+            take_default(&mut tx, &context, &existing.code).await?;
+        }
+
+        let updated = sqlx::query_as::<_, Language>(
+            "UPDATE languages SET name = ?, direction = ?, is_default = ?, enabled = ?,
+             fallback = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE code = ? RETURNING *",
+        )
+        .bind(name.trim())
+        .bind(direction)
+        .bind(is_default)
+        .bind(enabled)
+        .bind(&fallback)
+        .bind(&existing.code)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "language",
+            &existing.code,
+            Some(&existing),
+            Some(&updated),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    match result {
+        Ok(language) => HttpResponse::Ok().json(language),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to update language"
+        })),
+    }
+}
+
+/// Deletes a language no page uses. The default language and languages named
+/// in another language's fallback chain cannot be deleted.
+pub async fn delete_language(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    code: web::Path<String>,
+) -> impl Responder {
+    let existing = match fetch_language(&pool, &code).await {
+        Ok(language) => language,
+        Err(response) => return response,
+    };
+
+    if existing.is_default {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "The default language cannot be deleted"
+        }));
+    }
+
+    let in_use: Result<i64, sqlx::Error> =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pages WHERE lang = ?")
+            .bind(&existing.code)
+            .fetch_one(pool.get_ref())
+            .await;
+    match in_use {
+        Ok(0) => {}
+        Ok(pages) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("Language '{}' is used by {} pages", existing.code, pages)
+            }))
+        }
+        Err(_) => return database_error(),
+    }
+
+    let all = match sqlx::query_as::<_, Language>("SELECT * FROM languages")
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(all) => all,
+        Err(_) => return database_error(),
+    };
+    let referenced_by: Vec<&str> = all
+        .iter()
+        .filter(|language| language.fallback_codes().any(|c| c == existing.code))
+        .map(|language| language.code.as_str())
+        .collect();
+    if !referenced_by.is_empty() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Language '{}' is in other fallback chains", existing.code),
+            "referenced_by": referenced_by
+        }));
+    }
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM languages WHERE code = ?")
+            .bind(&existing.code)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "language",
+            &existing.code,
+            Some(&existing),
+            None::<&Language>,
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Language deleted successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete language"
+        })),
+    }
+}
+
+async fn fetch_language(pool: &SqlitePool, tag: &str) -> Result<Language, HttpResponse> {
+    let code = normalize_tag(tag).unwrap_or_else(|| tag.to_string());
+    match languages::fetch(pool, &code).await {
+        Ok(Some(language)) => Ok(language),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Language not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+/// Response for a page write whose `lang` failed [`languages::validate`].
+pub fn error_response(e: LanguageError) -> HttpResponse {
+    match e {
+        LanguageError::Invalid(message) => bad_request(message),
+        LanguageError::Database(_) => database_error(),
+    }
+}
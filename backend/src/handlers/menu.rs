@@ -0,0 +1,762 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::languages::{self, LanguageError};
+use crate::menus::{self, MenuError};
+use crate::models::menu::{
+    Menu, MenuCreate, MenuDetail, MenuItem, MenuItemCreate, MenuItemMove, MenuItemUpdate,
+    MenuQuery, MenuUpdate, LINK_TYPES, LOCATIONS,
+};
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+fn menu_error(e: MenuError, action: &str) -> HttpResponse {
+    match e {
+        MenuError::Invalid(message) => bad_request(message),
+        MenuError::Conflict(message) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": message
+        })),
+        MenuError::Database(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to {} menu item", action)
+        })),
+    }
+}
+
+/// Menu names appear in public URLs, so they are limited to lowercase
+/// letters, digits, `-` and `_`.
+fn check_menu_name(name: &str) -> Result<(), HttpResponse> {
+    let valid = !name.is_empty()
+        && name.len() <= 50
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(bad_request(
+            "name must be 1 to 50 lowercase letters, digits, '-' or '_'",
+        ));
+    }
+    Ok(())
+}
+
+fn check_location(location: &str) -> Result<(), HttpResponse> {
+    if !LOCATIONS.contains(&location) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown location '{}'", location),
+            "locations": LOCATIONS
+        })));
+    }
+    Ok(())
+}
+
+fn check_label(label: &str) -> Result<(), HttpResponse> {
+    if label.trim().is_empty() || label.chars().count() > 100 {
+        return Err(bad_request("label must be 1 to 100 characters"));
+    }
+    Ok(())
+}
+
+async fn canonical_lang(pool: &SqlitePool, tag: &str) -> Result<String, HttpResponse> {
+    match languages::validate(pool, tag).await {
+        Ok(code) => Ok(code),
+        Err(LanguageError::Invalid(message)) => Err(bad_request(message)),
+        Err(LanguageError::Database(_)) => Err(database_error()),
+    }
+}
+
+/// Labels keyed by canonical language code. Every language must be
+/// registered and enabled.
+async fn check_labels(
+    pool: &SqlitePool,
+    labels: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, HttpResponse> {
+    let mut checked = BTreeMap::new();
+    for (tag, label) in labels {
+        check_label(label)?;
+        let code = canonical_lang(pool, tag).await?;
+        if checked
+            .insert(code.clone(), label.trim().to_string())
+            .is_some()
+        {
+            return Err(bad_request(format!("labels has '{}' more than once", code)));
+        }
+    }
+    Ok(checked)
+}
+
+/// Joins the languages an item is limited to into the stored form.
+async fn langs_column(pool: &SqlitePool, langs: &[String]) -> Result<String, HttpResponse> {
+    let mut codes: Vec<String> = Vec::with_capacity(langs.len());
+    for tag in langs {
+        let code = canonical_lang(pool, tag).await?;
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    Ok(codes.join(","))
+}
+
+/// Where an item links to, with the fields its `link_type` does not use
+/// cleared.
+struct Target {
+    page_id: Option<i64>,
+    url: Option<String>,
+    anchor: Option<String>,
+}
+
+async fn check_target(
+    pool: &SqlitePool,
+    link_type: &str,
+    page_id: Option<i64>,
+    url: Option<&str>,
+    anchor: Option<&str>,
+) -> Result<Target, HttpResponse> {
+    if !LINK_TYPES.contains(&link_type) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown link_type '{}'", link_type),
+            "link_types": LINK_TYPES
+        })));
+    }
+
+    // An empty anchor clears the fragment of a page link.
+    let anchor = anchor
+        .map(|anchor| anchor.trim().trim_start_matches('#'))
+        .filter(|anchor| !anchor.is_empty());
+    if let Some(anchor) = anchor {
+        let valid = anchor.len() <= 100 && !anchor.chars().any(|c| c.is_whitespace() || c == '#');
+        if !valid {
+            return Err(bad_request(
+                "anchor must be at most 100 characters without spaces or '#'",
+            ));
+        }
+    }
+
+    match link_type {
+        "page" => {
+            let Some(page_id) = page_id else {
+                return Err(bad_request("page_id is required for page links"));
+            };
+            let exists: Result<Option<i64>, sqlx::Error> =
+                sqlx::query_scalar("SELECT id FROM pages WHERE id = ?")
+                    .bind(page_id)
+                    .fetch_optional(pool)
+                    .await;
+            match exists {
+                Ok(Some(_)) => Ok(Target {
+                    page_id: Some(page_id),
+                    url: None,
+                    anchor: anchor.map(str::to_string),
+                }),
+                Ok(None) => Err(bad_request(format!("Page {} not found", page_id))),
+                Err(_) => Err(database_error()),
+            }
+        }
+        "url" => {
+            let url = url.map(str::trim).unwrap_or_default();
+            let valid = ["http://", "https://", "mailto:", "/"]
+                .iter()
+                .any(|prefix| url.starts_with(prefix) && url.len() > prefix.len())
+                || url == "/";
+            if !valid {
+                return Err(bad_request(
+                    "url must start with http://, https://, mailto: or /",
+                ));
+            }
+            Ok(Target {
+                page_id: None,
+                url: Some(url.to_string()),
+                anchor: None,
+            })
+        }
+        _ => match anchor {
+            Some(anchor) => Ok(Target {
+                page_id: None,
+                url: None,
+                anchor: Some(anchor.to_string()),
+            }),
+            None => Err(bad_request("anchor is required for anchor links")),
+        },
+    }
+}
+
+/// An item with its labels, as recorded in the change log.
+async fn snapshot(
+    conn: &mut SqliteConnection,
+    item: &MenuItem,
+) -> Result<serde_json::Value, sqlx::Error> {
+    let labels: Vec<(String, String)> =
+        sqlx::query_as("SELECT lang, label FROM menu_item_labels WHERE item_id = ?")
+            .bind(item.id)
+            .fetch_all(conn)
+            .await?;
+    let mut value = serde_json::to_value(item).unwrap_or_default();
+    value["labels"] = serde_json::json!(labels.into_iter().collect::<BTreeMap<_, _>>());
+    Ok(value)
+}
+
+async fn fetch_menu(pool: &SqlitePool, menu_id: i64) -> Result<Menu, HttpResponse> {
+    let menu = sqlx::query_as::<_, Menu>("SELECT * FROM menus WHERE id = ?")
+        .bind(menu_id)
+        .fetch_optional(pool)
+        .await;
+
+    match menu {
+        Ok(Some(menu)) => Ok(menu),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Menu not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn fetch_item(
+    pool: &SqlitePool,
+    menu_id: i64,
+    item_id: i64,
+) -> Result<MenuItem, HttpResponse> {
+    let item =
+        sqlx::query_as::<_, MenuItem>("SELECT * FROM menu_items WHERE id = ? AND menu_id = ?")
+            .bind(item_id)
+            .bind(menu_id)
+            .fetch_optional(pool)
+            .await;
+
+    match item {
+        Ok(Some(item)) => Ok(item),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Menu item not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn menu_detail(pool: &SqlitePool, menu: Menu) -> HttpResponse {
+    let items: Result<_, sqlx::Error> = async {
+        let mut conn = pool.acquire().await?;
+        menus::tree(&mut conn, menu.id).await
+    }
+    .await;
+
+    match items {
+        Ok(items) => HttpResponse::Ok().json(MenuDetail { menu, items }),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch menu items"
+        })),
+    }
+}
+
+async fn name_taken(pool: &SqlitePool, name: &str, menu_id: i64) -> Result<(), HttpResponse> {
+    let taken: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM menus WHERE name = ? AND id != ?")
+            .bind(name)
+            .bind(menu_id)
+            .fetch_optional(pool)
+            .await;
+    match taken {
+        Ok(None) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Menu '{}' already exists", name)
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+pub async fn create_menu(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    menu_data: web::Json<MenuCreate>,
+) -> impl Responder {
+    if let Err(response) = check_menu_name(&menu_data.name) {
+        return response;
+    }
+    if let Err(response) = check_location(&menu_data.location) {
+        return response;
+    }
+    if let Err(response) = name_taken(&pool, &menu_data.name, 0).await {
+        return response;
+    }
+
+    let result: Result<Menu, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let menu = sqlx::query_as::<_, Menu>(
+            "INSERT INTO menus (name, location) VALUES (?, ?) RETURNING *",
+        )
+        .bind(&menu_data.name)
+        .bind(&menu_data.location)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "menu",
+            menu.id,
+            None::<&Menu>,
+            Some(&menu),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(menu)
+    }
+    .await;
+
+    match result {
+        Ok(menu) => HttpResponse::Created().json(menu),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create menu"
+        })),
+    }
+}
+
+pub async fn get_menus(pool: web::Data<SqlitePool>) -> impl Responder {
+    let menus = sqlx::query_as::<_, Menu>("SELECT * FROM menus ORDER BY location, name")
+        .fetch_all(pool.get_ref())
+        .await;
+
+    match menus {
+        Ok(menus) => HttpResponse::Ok().json(menus),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch menus"
+        })),
+    }
+}
+
+/// A menu with its whole item tree, hidden items and every label included.
+pub async fn get_menu(pool: web::Data<SqlitePool>, menu_id: web::Path<i64>) -> impl Responder {
+    match fetch_menu(&pool, *menu_id).await {
+        Ok(menu) => menu_detail(&pool, menu).await,
+        Err(response) => response,
+    }
+}
+
+pub async fn update_menu(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    menu_id: web::Path<i64>,
+    menu_data: web::Json<MenuUpdate>,
+) -> impl Responder {
+    let existing = match fetch_menu(&pool, *menu_id).await {
+        Ok(menu) => menu,
+        Err(response) => return response,
+    };
+
+    let name = menu_data.name.as_deref().unwrap_or(&existing.name);
+    if let Err(response) = check_menu_name(name) {
+        return response;
+    }
+    let location = menu_data.location.as_deref().unwrap_or(&existing.location);
+    if let Err(response) = check_location(location) {
+        return response;
+    }
+    if let Err(response) = name_taken(&pool, name, existing.id).await {
+        return response;
+    }
+
+    let result: Result<Menu, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let updated = sqlx::query_as::<_, Menu>(
+            "UPDATE menus SET name = ?, location = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(name)
+        .bind(location)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "menu",
+            existing.id,
+            Some(&existing),
+            Some(&updated),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    match result {
+        Ok(menu) => HttpResponse::Ok().json(menu),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to update menu"
+        })),
+    }
+}
+
+/// Deletes a menu and all of its items.
+pub async fn delete_menu(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    menu_id: web::Path<i64>,
+) -> impl Responder {
+    let existing = match fetch_menu(&pool, *menu_id).await {
+        Ok(menu) => menu,
+        Err(response) => return response,
+    };
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM menus WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "menu",
+            existing.id,
+            Some(&existing),
+            None::<&Menu>,
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Menu deleted successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete menu"
+        })),
+    }
+}
+
+pub async fn create_menu_item(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    menu_id: web::Path<i64>,
+    item_data: web::Json<MenuItemCreate>,
+) -> impl Responder {
+    let menu = match fetch_menu(&pool, *menu_id).await {
+        Ok(menu) => menu,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = check_label(&item_data.label) {
+        return response;
+    }
+    let target = match check_target(
+        &pool,
+        &item_data.link_type,
+        item_data.page_id,
+        item_data.url.as_deref(),
+        item_data.anchor.as_deref(),
+    )
+    .await
+    {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+    let labels = match &item_data.labels {
+        Some(labels) => match check_labels(&pool, labels).await {
+            Ok(labels) => labels,
+            Err(response) => return response,
+        },
+        None => BTreeMap::new(),
+    };
+    let langs = match &item_data.langs {
+        Some(langs) => match langs_column(&pool, langs).await {
+            Ok(langs) => langs,
+            Err(response) => return response,
+        },
+        None => String::new(),
+    };
+
+    let result: Result<MenuItem, MenuError> = async {
+        let mut tx = pool.begin().await?;
+        menus::check_parent(&mut tx, menu.id, item_data.parent_id, None).await?;
+
+        let item = sqlx::query_as::<_, MenuItem>(
+            "INSERT INTO menu_items (menu_id, parent_id, display_order, link_type, page_id, url,
+             anchor, label, visible, langs)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(menu.id)
+        .bind(item_data.parent_id)
+        .bind(item_data.display_order.unwrap_or(0))
+        .bind(&item_data.link_type)
+        .bind(target.page_id)
+        .bind(&target.url)
+        .bind(&target.anchor)
+        .bind(item_data.label.trim())
+        .bind(item_data.visible.unwrap_or(true))
+        .bind(&langs)
+        .fetch_one(&mut *tx)
+        .await?;
+        menus::set_labels(&mut tx, item.id, &labels).await?;
+
+        let after = snapshot(&mut tx, &item).await?;
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "menu_item",
+            item.id,
+            None::<&MenuItem>,
+            Some(&after),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(item)
+    }
+    .await;
+
+    match result {
+        Ok(item) => HttpResponse::Created().json(item),
+        Err(e) => menu_error(e, "create"),
+    }
+}
+
+pub async fn update_menu_item(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    item_data: web::Json<MenuItemUpdate>,
+) -> impl Responder {
+    let (menu_id, item_id) = path.into_inner();
+    let existing = match fetch_item(&pool, menu_id, item_id).await {
+        Ok(item) => item,
+        Err(response) => return response,
+    };
+
+    let label = item_data.label.as_deref().unwrap_or(&existing.label);
+    if let Err(response) = check_label(label) {
+        return response;
+    }
+    let link_type = item_data
+        .link_type
+        .as_deref()
+        .unwrap_or(&existing.link_type);
+    let target = match check_target(
+        &pool,
+        link_type,
+        item_data.page_id.or(existing.page_id),
+        item_data.url.as_deref().or(existing.url.as_deref()),
+        item_data.anchor.as_deref().or(existing.anchor.as_deref()),
+    )
+    .await
+    {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+    let labels = match &item_data.labels {
+        Some(labels) => match check_labels(&pool, labels).await {
+            Ok(labels) => Some(labels),
+            Err(response) => return response,
+        },
+        None => None,
+    };
+    let langs = match &item_data.langs {
+        Some(langs) => match langs_column(&pool, langs).await {
+            Ok(langs) => langs,
+            Err(response) => return response,
+        },
+        None => existing.langs.clone(),
+    };
+
+    let result: Result<MenuItem, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let before = snapshot(&mut tx, &existing).await?;
+
+        let updated = sqlx::query_as::<_, MenuItem>(
+            "UPDATE menu_items SET link_type = ?, page_id = ?, url = ?, anchor = ?, label = ?,
+             visible = ?, langs = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(link_type)
+        .bind(target.page_id)
+        .bind(&target.url)
+        .bind(&target.anchor)
+        .bind(label.trim())
+        .bind(item_data.visible.unwrap_or(existing.visible))
+        .bind(&langs)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(labels) = &labels {
+            menus::set_labels(&mut tx, existing.id, labels).await?;
+        }
+
+        let after = snapshot(&mut tx, &updated).await?;
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "menu_item",
+            existing.id,
+            Some(&before),
+            Some(&after),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    match result {
+        Ok(item) => HttpResponse::Ok().json(item),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to update menu item"
+        })),
+    }
+}
+
+pub async fn move_menu_item(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    move_data: web::Json<MenuItemMove>,
+) -> impl Responder {
+    let (menu_id, item_id) = path.into_inner();
+    let existing = match fetch_item(&pool, menu_id, item_id).await {
+        Ok(item) => item,
+        Err(response) => return response,
+    };
+
+    let result: Result<MenuItem, MenuError> = async {
+        let mut tx = pool.begin().await?;
+        menus::check_parent(&mut tx, menu_id, move_data.parent_id, Some(existing.id)).await?;
+
+        let moved = sqlx::query_as::<_, MenuItem>(
+            "UPDATE menu_items SET parent_id = ?, display_order = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(move_data.parent_id)
+        .bind(move_data.display_order.unwrap_or(existing.display_order))
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "move",
+            "menu_item",
+            existing.id,
+            Some(&existing),
+            Some(&moved),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(moved)
+    }
+    .await;
+
+    match result {
+        Ok(item) => HttpResponse::Ok().json(item),
+        Err(e) => menu_error(e, "move"),
+    }
+}
+
+/// Deletes an item and every item below it.
+pub async fn delete_menu_item(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let (menu_id, item_id) = path.into_inner();
+    let existing = match fetch_item(&pool, menu_id, item_id).await {
+        Ok(item) => item,
+        Err(response) => return response,
+    };
+
+    let result: Result<u64, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let before = snapshot(&mut tx, &existing).await?;
+        let descendants: i64 = sqlx::query_scalar(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT id FROM menu_items WHERE parent_id = ?
+                 UNION ALL
+                 SELECT i.id FROM menu_items i JOIN subtree s ON i.parent_id = s.id
+             )
+             SELECT COUNT(*) FROM subtree",
+        )
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM menu_items WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "menu_item",
+            existing.id,
+            Some(&before),
+            None::<&MenuItem>,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(descendants as u64 + 1)
+    }
+    .await;
+
+    match result {
+        Ok(deleted) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Menu item deleted successfully",
+            "deleted": deleted
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete menu item"
+        })),
+    }
+}
+
+/// The menu named `name` as a site shows it in `lang`.
+pub async fn get_public_menu(
+    pool: web::Data<SqlitePool>,
+    name: web::Path<String>,
+    query: web::Query<MenuQuery>,
+) -> impl Responder {
+    let menu = sqlx::query_as::<_, Menu>("SELECT * FROM menus WHERE name = ?")
+        .bind(name.as_str())
+        .fetch_optional(pool.get_ref())
+        .await;
+    let menu = match menu {
+        Ok(Some(menu)) => menu,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Menu not found"
+            }))
+        }
+        Err(_) => return database_error(),
+    };
+
+    match menus::resolve(&pool, menu, query.lang.as_deref()).await {
+        Ok(menu) => HttpResponse::Ok().json(menu),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch menu"
+        })),
+    }
+}
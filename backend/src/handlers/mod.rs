@@ -4,3 +4,22 @@ pub mod page;
 pub mod content;
 pub mod image;
 pub mod search;
+pub mod batch;
+pub mod audit;
+pub mod webhook;
+pub mod bundle;
+pub mod backup;
+pub mod change;
+pub mod feed;
+pub mod language;
+pub mod menu;
+pub mod redirect;
+pub mod render;
+pub mod site;
+pub mod translation;
+pub mod taxonomy;
+pub mod block;
+pub mod content_type;
+pub mod gallery;
+pub mod relation;
+pub mod preview;
@@ -1,45 +1,62 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
 use sqlx::SqlitePool;
 
-use crate::models::{Page, PageCreate, PageUpdate};
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::etag;
+use crate::merge_patch;
+use crate::models::{Page, PageCreate, PageFields, PageMove, PageReorder, PageTreeQuery, PageUpdate};
+use crate::page_tree::{self, TreeError};
+use crate::pages::{self, PageError};
+use crate::redirects;
 use serde::Deserialize;
+use std::collections::BTreeSet;
 
 #[derive(Deserialize)]
 pub struct PageQuery {
     pub section_name: Option<String>,
 }
+
+/// Response for a failed page write; `action` names the write for database
+/// errors.
+fn page_error(e: PageError, action: &str) -> HttpResponse {
+    match e {
+        PageError::Invalid(message) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        })),
+        PageError::Conflict(message) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": message
+        })),
+        PageError::Database(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to {} page", action)
+        })),
+    }
+}
+
 pub async fn create_page(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     page_data: web::Json<PageCreate>,
 ) -> impl Responder {
-    let visible = page_data.visible.unwrap_or(true);
-    let display_order = page_data.display_order.unwrap_or(0);
-
-    let result = sqlx::query(
-        "INSERT INTO pages (page_name, section_name, lang, content_type, visible, display_order, attributes)
-         VALUES (?, ?, ?, ?, ?, ?, ?)"
-    )
-    .bind(&page_data.page_name)
-    .bind(&page_data.section_name)
-    .bind(&page_data.lang)
-    .bind(&page_data.content_type)
-    .bind(visible)
-    .bind(display_order)
-    .bind(&page_data.attributes)
-    .execute(pool.get_ref())
+    let result: Result<Page, PageError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        let page = pages::create(&mut tx, &context, &page_data).await?;
+        tx.commit().await?;
+        Ok(page)
+    }
     .await;
 
     match result {
-        Ok(result) => {
-            let page_id = result.last_insert_rowid();
-            HttpResponse::Created().json(serde_json::json!({
-                "id": page_id,
+        Ok(page) => HttpResponse::Created()
+            .insert_header((header::ETAG, etag::etag(page.version)))
+            .json(serde_json::json!({
+                "id": page.id,
+                "version": page.version,
+                "path": page.path,
                 "message": "Page created successfully"
-            }))
-        }
-        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create page"
-        })),
+            })),
+        Err(e) => page_error(e, "create"),
     }
 }
 
@@ -81,7 +98,9 @@ pub async fn get_page(
         .await;
 
     match page {
-        Ok(Some(page)) => HttpResponse::Ok().json(page),
+        Ok(Some(page)) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag::etag(page.version)))
+            .json(page),
         Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
             "error": "Page not found"
         })),
@@ -92,22 +111,76 @@ pub async fn get_page(
 }
 
 pub async fn update_page(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     page_id: web::Path<i64>,
     page_data: web::Json<PageUpdate>,
 ) -> impl Responder {
-    let existing_page = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
-        .bind(*page_id)
-        .fetch_optional(pool.get_ref())
-        .await;
+    let existing = match fetch_page(&pool, *page_id).await {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    let fields = PageFields {
+        page_name: page_data.page_name.clone().unwrap_or_else(|| existing.page_name.clone()),
+        section_name: page_data
+            .section_name
+            .clone()
+            .unwrap_or_else(|| existing.section_name.clone()),
+        lang: page_data.lang.clone().unwrap_or_else(|| existing.lang.clone()),
+        content_type: page_data
+            .content_type
+            .clone()
+            .unwrap_or_else(|| existing.content_type.clone()),
+        visible: page_data.visible.unwrap_or(existing.visible),
+        display_order: page_data.display_order.unwrap_or(existing.display_order),
+        attributes: page_data.attributes.clone().or_else(|| existing.attributes.clone()),
+        slug: page_data.slug.clone().or_else(|| existing.slug.clone()),
+    };
+
+    save_page(&req, &pool, &existing, &fields).await
+}
+
+pub async fn patch_page(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+    patch: web::Json<serde_json::Value>,
+) -> impl Responder {
+    let existing = match fetch_page(&pool, *page_id).await {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
 
-    let existing = match existing_page {
-        Ok(Some(page)) => page,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Page not found"
+    let fields = match merge_patch::apply(&PageFields::from(&existing), &patch) {
+        Ok(fields) => fields,
+        Err(error) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": error
             }))
         }
+    };
+
+    save_page(&req, &pool, &existing, &fields).await
+}
+
+/// Rewrites `display_order` of every page in the scope to match `ids`, keeping
+/// the ordering dense. Pages whose position changes get a new version.
+pub async fn reorder_pages(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    reorder: web::Json<PageReorder>,
+) -> impl Responder {
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
         Err(_) => {
             return HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": "Database error"
@@ -115,63 +188,278 @@ pub async fn update_page(
         }
     };
 
-    let page_name = page_data.page_name.as_ref().unwrap_or(&existing.page_name);
-    let section_name = page_data.section_name.as_ref().unwrap_or(&existing.section_name);
-    let lang = page_data.lang.as_ref().unwrap_or(&existing.lang);
-    let content_type = page_data.content_type.as_ref().unwrap_or(&existing.content_type);
-    let visible = page_data.visible.unwrap_or(existing.visible);
-    let display_order = page_data.display_order.unwrap_or(existing.display_order);
-    let attributes = page_data.attributes.as_ref().or(existing.attributes.as_ref());
-
-    let result = sqlx::query(
-        "UPDATE pages SET page_name = ?, section_name = ?, lang = ?, content_type = ?,
-         visible = ?, display_order = ?, attributes = ?, updated_at = CURRENT_TIMESTAMP
-         WHERE id = ?"
+    let scope = sqlx::query_as::<_, Page>(
+        "SELECT * FROM pages WHERE page_name = ? AND section_name = ?
+         AND (? IS NULL OR lang = ?)"
     )
-    .bind(page_name)
-    .bind(section_name)
-    .bind(lang)
-    .bind(content_type)
-    .bind(visible)
-    .bind(display_order)
-    .bind(attributes)
-    .bind(*page_id)
-    .execute(pool.get_ref())
+    .bind(&reorder.page_name)
+    .bind(&reorder.section_name)
+    .bind(&reorder.lang)
+    .bind(&reorder.lang)
+    .fetch_all(&mut *tx)
+    .await;
+
+    let scope = match scope {
+        Ok(pages) => pages,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Database error"
+            }))
+        }
+    };
+
+    let expected: BTreeSet<i64> = scope.iter().map(|page| page.id).collect();
+    let given: BTreeSet<i64> = reorder.ids.iter().copied().collect();
+    if given.len() != reorder.ids.len() || given != expected {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "ids must list every page in the scope exactly once",
+            "expected": expected
+        }));
+    }
+
+    let context = AuditContext::from_request(&req);
+    let result: Result<(), sqlx::Error> = async {
+        for (position, id) in reorder.ids.iter().enumerate() {
+            let moved = sqlx::query_as::<_, Page>(
+                "UPDATE pages SET display_order = ?, version = version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ? AND display_order != ? RETURNING *"
+            )
+            .bind(position as i32)
+            .bind(id)
+            .bind(position as i32)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some(moved) = moved {
+                let before = scope.iter().find(|page| page.id == moved.id);
+                changes::record(&mut tx, &context, "reorder", "page", moved.id, before, Some(&moved))
+                    .await?;
+            }
+        }
+        tx.commit().await
+    }
     .await;
 
     match result {
         Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "message": "Page updated successfully"
+            "message": "Pages reordered successfully"
         })),
         Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update page"
+            "error": "Failed to reorder pages"
         })),
     }
 }
 
 pub async fn delete_page(
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     page_id: web::Path<i64>,
 ) -> impl Responder {
-    let result = sqlx::query("DELETE FROM pages WHERE id = ?")
-        .bind(*page_id)
-        .execute(pool.get_ref())
-        .await;
+    let existing = match fetch_page(&pool, *page_id).await {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    let children = match pool.acquire().await {
+        Ok(mut conn) => page_tree::child_ids(&mut conn, existing.id).await,
+        Err(e) => Err(e),
+    };
+    match children {
+        Ok(children) if children.is_empty() => {}
+        Ok(children) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Page has child pages; move or delete them first",
+                "children": children
+            }))
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Database error"
+            }))
+        }
+    }
+
+    let result: Result<bool, PageError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        if !pages::delete(&mut tx, &context, &existing).await? {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
 
     match result {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "message": "Page deleted successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "Page not found"
-                }))
-            }
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Page deleted successfully"
+        })),
+        Ok(false) => conflict(&pool, existing.id).await,
+        Err(e) => page_error(e, "delete"),
+    }
+}
+
+/// Moves a page, with everything below it, under another parent or to the
+/// top level. Its slug must be free among the new siblings.
+pub async fn move_page(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+    move_data: web::Json<PageMove>,
+) -> impl Responder {
+    let existing = match fetch_page(&pool, *page_id).await {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = etag::check(&req, existing.version, &existing) {
+        return response;
+    }
+
+    let result: Result<Option<Page>, TreeError> = async {
+        let mut tx = pool.begin().await?;
+        page_tree::check_parent(&mut tx, move_data.parent_id, &existing.lang, Some(existing.id)).await?;
+        page_tree::check_slug(
+            &mut tx,
+            move_data.parent_id,
+            &existing.lang,
+            existing.slug.as_deref(),
+            Some(existing.id),
+        )
+        .await?;
+        let old_paths = redirects::subtree_paths(&mut tx, existing.id).await?;
+
+        let moved = sqlx::query_scalar::<_, i64>(
+            "UPDATE pages SET parent_id = ?, display_order = ?, version = version + 1,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND version = ? RETURNING id"
+        )
+        .bind(move_data.parent_id)
+        .bind(move_data.display_order.unwrap_or(existing.display_order))
+        .bind(existing.id)
+        .bind(existing.version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if moved.is_none() {
+            return Ok(None);
         }
+
+        page_tree::refresh_paths(&mut tx, existing.id).await?;
+        let moved = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+            .bind(existing.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(&mut tx, &context, "move", "page", existing.id, Some(&existing), Some(&moved))
+            .await?;
+        redirects::follow_path_changes(&mut tx, &context, &old_paths).await?;
+        tx.commit().await?;
+        Ok(Some(moved))
+    }
+    .await;
+
+    match result {
+        Ok(Some(page)) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag::etag(page.version)))
+            .json(serde_json::json!({
+                "message": "Page moved successfully",
+                "version": page.version,
+                "path": page.path
+            })),
+        Ok(None) => conflict(&pool, existing.id).await,
+        Err(e) => page_error(e.into(), "move"),
+    }
+}
+
+/// Every top-level page with its descendants.
+pub async fn get_page_tree(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<PageTreeQuery>,
+) -> impl Responder {
+    match page_tree::subtree(&pool, None, query.lang.as_deref(), query.depth).await {
+        Ok(nodes) => HttpResponse::Ok().json(nodes),
         Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete page"
+            "error": "Failed to fetch page tree"
         })),
     }
 }
+
+/// A page with its descendants.
+pub async fn get_subtree(
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+    query: web::Query<PageTreeQuery>,
+) -> impl Responder {
+    match page_tree::subtree(&pool, Some(*page_id), query.lang.as_deref(), query.depth).await {
+        Ok(mut nodes) if !nodes.is_empty() => HttpResponse::Ok().json(nodes.remove(0)),
+        Ok(_) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Page not found"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch page tree"
+        })),
+    }
+}
+
+async fn fetch_page(pool: &SqlitePool, page_id: i64) -> Result<Page, HttpResponse> {
+    match sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+        .bind(page_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(page)) => Ok(page),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Page not found"
+        }))),
+        Err(_) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+/// Writes `fields` over `existing` if its version is still current, recording
+/// the change in the audit log within the same transaction.
+async fn save_page(
+    req: &HttpRequest,
+    pool: &SqlitePool,
+    existing: &Page,
+    fields: &PageFields,
+) -> HttpResponse {
+    let result: Result<Option<Page>, PageError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(req);
+        let Some(updated) = pages::update(&mut tx, &context, existing, fields).await? else {
+            return Ok(None);
+        };
+        tx.commit().await?;
+        Ok(Some(updated))
+    }
+    .await;
+
+    match result {
+        Ok(Some(page)) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag::etag(page.version)))
+            .json(serde_json::json!({
+                "message": "Page updated successfully",
+                "version": page.version,
+                "path": page.path
+            })),
+        Ok(None) => conflict(pool, existing.id).await,
+        Err(e) => page_error(e, "update"),
+    }
+}
+
+/// Response for a guarded write that matched no row: the page either changed
+/// since it was read (412 with the current state) or is gone (404).
+async fn conflict(pool: &SqlitePool, page_id: i64) -> HttpResponse {
+    match fetch_page(pool, page_id).await {
+        Ok(current) => etag::precondition_failed(current.version, &current),
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,299 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::config::AppConfig;
+use crate::handlers::render::{api_links, page_href};
+use crate::languages;
+use crate::models::preview::{Preview, PreviewCreate, PreviewLink, PreviewQuery};
+use crate::models::translation::PublicPage;
+use crate::models::Page;
+use crate::previews::{self, PreviewError};
+use crate::render::{self, Renderer};
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+async fn fetch_page(pool: &SqlitePool, page_id: i64) -> Result<Page, HttpResponse> {
+    match sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+        .bind(page_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(page)) => Ok(page),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Page not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+/// `preview` with its token and URL while it is active.
+fn link(preview: Preview, config: &AppConfig) -> PreviewLink {
+    let active = previews::is_active(&preview);
+    let token = if active {
+        previews::sign(&preview, &config.jwt_secret).ok()
+    } else {
+        None
+    };
+    let url = token
+        .as_deref()
+        .map(|token| previews::url(config.public_base_url.trim_end_matches('/'), token));
+    PreviewLink {
+        preview,
+        active,
+        token,
+        url,
+    }
+}
+
+fn preview_error(e: PreviewError) -> HttpResponse {
+    match e {
+        PreviewError::Invalid => HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+        PreviewError::NotFound => HttpResponse::NotFound().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+        PreviewError::Database(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to load preview"
+        })),
+    }
+}
+
+/// Creates a preview link to a page, or to its translation into `lang`, as it
+/// currently stands. The link works without a login until it expires or is
+/// revoked.
+pub async fn create_preview(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    page_id: web::Path<i64>,
+    preview_data: web::Json<PreviewCreate>,
+) -> impl Responder {
+    let page = match fetch_page(&pool, *page_id).await {
+        Ok(page) => page,
+        Err(response) => return response,
+    };
+
+    let expires_in = preview_data.expires_in.unwrap_or(previews::DEFAULT_TTL);
+    if expires_in <= 0 || expires_in > previews::MAX_TTL {
+        return bad_request(format!(
+            "expires_in must be between 1 and {} seconds",
+            previews::MAX_TTL
+        ));
+    }
+    if preview_data
+        .note
+        .as_deref()
+        .is_some_and(|note| note.chars().count() > 200)
+    {
+        return bad_request("note must be at most 200 characters");
+    }
+
+    let lang = match preview_data.lang.as_deref() {
+        None => page.lang.clone(),
+        Some(lang) => match languages::normalize_tag(lang) {
+            Some(lang) => lang,
+            None => return bad_request(format!("'{}' is not a valid BCP 47 language tag", lang)),
+        },
+    };
+    let page_lang = page.lang.clone();
+    match previews::target_page(pool.get_ref(), page, &lang).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return bad_request(format!(
+                "Page is in '{}' and has no translation into '{}'",
+                page_lang, lang
+            ))
+        }
+        Err(_) => return database_error(),
+    }
+
+    let context = AuditContext::from_request(&req);
+    let result: Result<Preview, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let preview = sqlx::query_as::<_, Preview>(
+            "INSERT INTO previews (page_id, lang, note, created_by, expires_at)
+             VALUES (?, ?, ?, ?, datetime('now', '+' || ? || ' seconds'))
+             RETURNING *",
+        )
+        .bind(*page_id)
+        .bind(&lang)
+        .bind(&preview_data.note)
+        .bind(context.agent_id)
+        .bind(expires_in)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "preview",
+            preview.id,
+            None::<&Preview>,
+            Some(&preview),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(preview)
+    }
+    .await;
+
+    match result {
+        Ok(preview) => HttpResponse::Created().json(link(preview, &config)),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create preview"
+        })),
+    }
+}
+
+/// A page's previews, newest first, revoked and expired ones included.
+pub async fn get_previews(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    page_id: web::Path<i64>,
+) -> impl Responder {
+    if let Err(response) = fetch_page(&pool, *page_id).await {
+        return response;
+    }
+
+    match sqlx::query_as::<_, Preview>(
+        "SELECT * FROM previews WHERE page_id = ? ORDER BY created_at DESC, id DESC",
+    )
+    .bind(*page_id)
+    .fetch_all(pool.get_ref())
+    .await
+    {
+        Ok(previews) => HttpResponse::Ok().json(
+            previews
+                .into_iter()
+                .map(|preview| link(preview, &config))
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch previews"
+        })),
+    }
+}
+
+/// Revokes a preview; its link stops working immediately. Revoking twice is
+/// harmless.
+pub async fn revoke_preview(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let (page_id, preview_id) = path.into_inner();
+    let existing =
+        match sqlx::query_as::<_, Preview>("SELECT * FROM previews WHERE id = ? AND page_id = ?")
+            .bind(preview_id)
+            .bind(page_id)
+            .fetch_optional(pool.get_ref())
+            .await
+        {
+            Ok(Some(preview)) => preview,
+            Ok(None) => {
+                return HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Preview not found"
+                }))
+            }
+            Err(_) => return database_error(),
+        };
+    if existing.revoked_at.is_some() {
+        return HttpResponse::Ok().json(link(existing, &config));
+    }
+
+    let result: Result<Preview, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let preview = sqlx::query_as::<_, Preview>(
+            "UPDATE previews SET revoked_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING *",
+        )
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "revoke",
+            "preview",
+            preview.id,
+            Some(&existing),
+            Some(&preview),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(preview)
+    }
+    .await;
+
+    match result {
+        Ok(preview) => HttpResponse::Ok().json(link(preview, &config)),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to revoke preview"
+        })),
+    }
+}
+
+/// The previewed page with its contents, in the shape of the public pages
+/// API. Needs no login, only the preview token.
+pub async fn get_public_preview(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    query: web::Query<PreviewQuery>,
+) -> impl Responder {
+    match previews::resolve(pool.get_ref(), &config.jwt_secret, &query.token).await {
+        Ok((_, page)) => HttpResponse::Ok()
+            .insert_header(("X-Robots-Tag", "noindex"))
+            .insert_header(("Cache-Control", "no-store"))
+            .json(page),
+        Err(e) => preview_error(e),
+    }
+}
+
+/// The previewed page rendered as HTML, the page a preview link opens.
+pub async fn render_public_preview(
+    pool: web::Data<SqlitePool>,
+    renderer: web::Data<Renderer>,
+    config: web::Data<AppConfig>,
+    query: web::Query<PreviewQuery>,
+) -> impl Responder {
+    let page: PublicPage =
+        match previews::resolve(pool.get_ref(), &config.jwt_secret, &query.token).await {
+            Ok((_, page)) => page,
+            Err(e) => return preview_error(e),
+        };
+
+    let base = config.public_base_url.trim_end_matches('/');
+    let page_href = |page_name: &str, lang: &str| page_href(base, page_name, lang);
+    let links = api_links(base, &page_href);
+    let page_id = page.page.id;
+
+    match render::render_preview(pool.get_ref(), renderer.get_ref(), &links, page).await {
+        Ok(document) => HttpResponse::Ok()
+            .insert_header(("X-Robots-Tag", "noindex"))
+            .insert_header(("Cache-Control", "no-store"))
+            .content_type("text/html; charset=utf-8")
+            .body(document.html),
+        Err(e) => {
+            log::error!("Failed to render preview of page {}: {}", page_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to render preview"
+            }))
+        }
+    }
+}
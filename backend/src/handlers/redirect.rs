@@ -0,0 +1,258 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+
+use crate::audit::AuditContext;
+use crate::models::redirect::{
+    Redirect, RedirectCreate, RedirectLookupQuery, RedirectQuery, RedirectUpdate, STATUS_CODES,
+};
+use crate::redirects::{self, RedirectError, Target};
+
+fn redirect_error(e: RedirectError, action: &str) -> HttpResponse {
+    match e {
+        RedirectError::Invalid(message) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        })),
+        RedirectError::Conflict(message) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": message
+        })),
+        RedirectError::Database(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to {} redirect", action)
+        })),
+    }
+}
+
+fn check_status(status_code: i32) -> Result<(), HttpResponse> {
+    if !STATUS_CODES.contains(&status_code) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unsupported status_code {}", status_code),
+            "status_codes": STATUS_CODES
+        })));
+    }
+    Ok(())
+}
+
+async fn check_page(pool: &SqlitePool, target: &Target) -> Result<(), HttpResponse> {
+    let Target::Page(page_id) = target else {
+        return Ok(());
+    };
+    let exists: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM pages WHERE id = ?")
+            .bind(page_id)
+            .fetch_optional(pool)
+            .await;
+    match exists {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Page {} not found", page_id)
+        }))),
+        Err(_) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+async fn fetch_redirect(pool: &SqlitePool, redirect_id: i64) -> Result<Redirect, HttpResponse> {
+    let redirect = sqlx::query_as::<_, Redirect>("SELECT * FROM redirects WHERE id = ?")
+        .bind(redirect_id)
+        .fetch_optional(pool)
+        .await;
+
+    match redirect {
+        Ok(Some(redirect)) => Ok(redirect),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Redirect not found"
+        }))),
+        Err(_) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+pub async fn create_redirect(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    redirect_data: web::Json<RedirectCreate>,
+) -> impl Responder {
+    let status_code = redirect_data.status_code.unwrap_or(301);
+    if let Err(response) = check_status(status_code) {
+        return response;
+    }
+    let source = match redirects::normalize_source(&redirect_data.source_path) {
+        Ok(source) => source,
+        Err(e) => return redirect_error(e, "create"),
+    };
+    let target = match Target::from_fields(
+        redirect_data.target_path.as_deref(),
+        redirect_data.target_page_id,
+    ) {
+        Ok(target) => target,
+        Err(e) => return redirect_error(e, "create"),
+    };
+    if let Err(response) = check_page(&pool, &target).await {
+        return response;
+    }
+
+    let result: Result<Redirect, RedirectError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        let redirect =
+            redirects::save(&mut tx, &context, None, &source, target, status_code, false).await?;
+        tx.commit().await?;
+        Ok(redirect)
+    }
+    .await;
+
+    match result {
+        Ok(redirect) => HttpResponse::Created().json(redirect),
+        Err(e) => redirect_error(e, "create"),
+    }
+}
+
+pub async fn get_redirects(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<RedirectQuery>,
+) -> impl Responder {
+    let redirects = sqlx::query_as::<_, Redirect>(
+        "SELECT * FROM redirects WHERE (? IS NULL OR target_page_id = ?)
+         AND (? IS NULL OR automatic = ?) ORDER BY source_path",
+    )
+    .bind(query.page_id)
+    .bind(query.page_id)
+    .bind(query.automatic)
+    .bind(query.automatic)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match redirects {
+        Ok(redirects) => HttpResponse::Ok().json(redirects),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch redirects"
+        })),
+    }
+}
+
+pub async fn get_redirect(
+    pool: web::Data<SqlitePool>,
+    redirect_id: web::Path<i64>,
+) -> impl Responder {
+    match fetch_redirect(&pool, *redirect_id).await {
+        Ok(redirect) => HttpResponse::Ok().json(redirect),
+        Err(response) => response,
+    }
+}
+
+/// Updates a redirect. An edited redirect counts as a manual one from then
+/// on, so later page changes no longer replace it.
+pub async fn update_redirect(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    redirect_id: web::Path<i64>,
+    redirect_data: web::Json<RedirectUpdate>,
+) -> impl Responder {
+    let existing = match fetch_redirect(&pool, *redirect_id).await {
+        Ok(redirect) => redirect,
+        Err(response) => return response,
+    };
+
+    let status_code = redirect_data.status_code.unwrap_or(existing.status_code);
+    if let Err(response) = check_status(status_code) {
+        return response;
+    }
+    let source = match redirects::normalize_source(
+        redirect_data
+            .source_path
+            .as_deref()
+            .unwrap_or(&existing.source_path),
+    ) {
+        Ok(source) => source,
+        Err(e) => return redirect_error(e, "update"),
+    };
+    let target = if redirect_data.target_path.is_some() || redirect_data.target_page_id.is_some() {
+        match Target::from_fields(
+            redirect_data.target_path.as_deref(),
+            redirect_data.target_page_id,
+        ) {
+            Ok(target) => target,
+            Err(e) => return redirect_error(e, "update"),
+        }
+    } else {
+        Target::of(&existing)
+    };
+    if let Err(response) = check_page(&pool, &target).await {
+        return response;
+    }
+
+    let result: Result<Redirect, RedirectError> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        let redirect = redirects::save(
+            &mut tx,
+            &context,
+            Some(existing.id),
+            &source,
+            target,
+            status_code,
+            false,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(redirect)
+    }
+    .await;
+
+    match result {
+        Ok(redirect) => HttpResponse::Ok().json(redirect),
+        Err(e) => redirect_error(e, "update"),
+    }
+}
+
+pub async fn delete_redirect(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    redirect_id: web::Path<i64>,
+) -> impl Responder {
+    let existing = match fetch_redirect(&pool, *redirect_id).await {
+        Ok(redirect) => redirect,
+        Err(response) => return response,
+    };
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let context = AuditContext::from_request(&req);
+        redirects::remove(&mut tx, &context, &existing).await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Redirect deleted successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete redirect"
+        })),
+    }
+}
+
+/// Tells a web server where to send a request for `path`: `200` with the
+/// `location` and `status_code` to answer with, `404` when the path is not
+/// redirected, or `508` when the redirects from it loop.
+pub async fn lookup_redirect(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<RedirectLookupQuery>,
+) -> impl Responder {
+    match redirects::lookup(&pool, &query.path).await {
+        Ok(Some(lookup)) => HttpResponse::Ok().json(lookup),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "No redirect for this path"
+        })),
+        Err(RedirectError::Conflict(message)) => {
+            HttpResponse::LoopDetected().json(serde_json::json!({
+                "error": message
+            }))
+        }
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to look up redirect"
+        })),
+    }
+}
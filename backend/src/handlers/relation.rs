@@ -0,0 +1,322 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::models::relation::{RelatedQuery, Relation, RelationCreate, SimilarQuery};
+use crate::models::Content;
+use crate::relations::{self, Kind};
+
+const SIMILAR_LIMIT: usize = 5;
+const SIMILAR_MAX: usize = 50;
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+/// Relation types are read as handles by templates and clients, so they are
+/// limited to lowercase letters, digits, `-` and `_`.
+fn check_relation_type(relation_type: &str) -> Result<(), HttpResponse> {
+    let valid = !relation_type.is_empty()
+        && relation_type.len() <= 50
+        && relation_type
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(bad_request(
+            "relation_type must be 1 to 50 lowercase letters, digits, '-' or '_'",
+        ));
+    }
+    Ok(())
+}
+
+async fn check_source(pool: &SqlitePool, kind: Kind, id: i64) -> Result<(), HttpResponse> {
+    let exists: Result<bool, sqlx::Error> = async {
+        let mut conn = pool.acquire().await?;
+        relations::exists(&mut conn, kind, id).await
+    }
+    .await;
+    match exists {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": match kind {
+                Kind::Content => "Content not found",
+                Kind::Page => "Page not found",
+            }
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+/// Links a content or page to another one. A relation is directional: it
+/// reads from the item it is created on to `target_kind` `target_id`.
+async fn create_relation(
+    req: &HttpRequest,
+    pool: &SqlitePool,
+    kind: Kind,
+    id: i64,
+    relation_data: &RelationCreate,
+) -> HttpResponse {
+    if let Err(response) = check_relation_type(&relation_data.relation_type) {
+        return response;
+    }
+    let Some(target_kind) = Kind::parse(&relation_data.target_kind) else {
+        return bad_request("target_kind must be 'content' or 'page'");
+    };
+    if target_kind == kind && relation_data.target_id == id {
+        return bad_request("An item cannot be related to itself");
+    }
+    if let Err(response) = check_source(pool, kind, id).await {
+        return response;
+    }
+
+    let result: Result<Result<Relation, HttpResponse>, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        if !relations::exists(&mut tx, target_kind, relation_data.target_id).await? {
+            return Ok(Err(bad_request(format!(
+                "Target {} {} not found",
+                target_kind.as_str(),
+                relation_data.target_id
+            ))));
+        }
+        let duplicate: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM relations WHERE source_kind = ? AND source_id = ?
+             AND relation_type = ? AND target_kind = ? AND target_id = ?",
+        )
+        .bind(kind.as_str())
+        .bind(id)
+        .bind(&relation_data.relation_type)
+        .bind(target_kind.as_str())
+        .bind(relation_data.target_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(duplicate) = duplicate {
+            return Ok(Err(HttpResponse::Conflict().json(serde_json::json!({
+                "error": "This relation already exists",
+                "id": duplicate
+            }))));
+        }
+
+        let relation = sqlx::query_as::<_, Relation>(
+            "INSERT INTO relations (source_kind, source_id, relation_type, target_kind,
+             target_id, display_order)
+             VALUES (?, ?, ?, ?, ?, COALESCE(?, (SELECT COALESCE(MAX(display_order) + 1, 0)
+                 FROM relations WHERE source_kind = ? AND source_id = ? AND relation_type = ?)))
+             RETURNING *",
+        )
+        .bind(kind.as_str())
+        .bind(id)
+        .bind(&relation_data.relation_type)
+        .bind(target_kind.as_str())
+        .bind(relation_data.target_id)
+        .bind(relation_data.display_order)
+        .bind(kind.as_str())
+        .bind(id)
+        .bind(&relation_data.relation_type)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "relation",
+            relation.id,
+            None::<&Relation>,
+            Some(&relation),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(Ok(relation))
+    }
+    .await;
+
+    match result {
+        Ok(Ok(relation)) => HttpResponse::Created().json(relation),
+        Ok(Err(response)) => response,
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create relation"
+        })),
+    }
+}
+
+/// Removes a relation from the item it starts at.
+async fn delete_relation(
+    req: &HttpRequest,
+    pool: &SqlitePool,
+    kind: Kind,
+    id: i64,
+    relation_id: i64,
+) -> HttpResponse {
+    let existing = sqlx::query_as::<_, Relation>(
+        "SELECT * FROM relations WHERE id = ? AND source_kind = ? AND source_id = ?",
+    )
+    .bind(relation_id)
+    .bind(kind.as_str())
+    .bind(id)
+    .fetch_optional(pool)
+    .await;
+    let existing = match existing {
+        Ok(Some(relation)) => relation,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Relation not found"
+            }))
+        }
+        Err(_) => return database_error(),
+    };
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM relations WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "relation",
+            existing.id,
+            Some(&existing),
+            None::<&Relation>,
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Relation deleted successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete relation"
+        })),
+    }
+}
+
+async fn get_related(pool: &SqlitePool, kind: Kind, id: i64, query: &RelatedQuery) -> HttpResponse {
+    let (outgoing, incoming) = match query.direction.as_deref() {
+        None | Some("both") => (true, true),
+        Some("outgoing") => (true, false),
+        Some("incoming") => (false, true),
+        Some(_) => return bad_request("direction must be 'outgoing', 'incoming' or 'both'"),
+    };
+    if let Err(response) = check_source(pool, kind, id).await {
+        return response;
+    }
+
+    match relations::related(
+        pool,
+        kind,
+        id,
+        query.relation_type.as_deref(),
+        outgoing,
+        incoming,
+    )
+    .await
+    {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch related items"
+        })),
+    }
+}
+
+pub async fn create_content_relation(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    relation_data: web::Json<RelationCreate>,
+) -> impl Responder {
+    create_relation(&req, &pool, Kind::Content, *content_id, &relation_data).await
+}
+
+pub async fn delete_content_relation(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let (content_id, relation_id) = path.into_inner();
+    delete_relation(&req, &pool, Kind::Content, content_id, relation_id).await
+}
+
+pub async fn get_content_related(
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    query: web::Query<RelatedQuery>,
+) -> impl Responder {
+    get_related(&pool, Kind::Content, *content_id, &query).await
+}
+
+pub async fn create_page_relation(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+    relation_data: web::Json<RelationCreate>,
+) -> impl Responder {
+    create_relation(&req, &pool, Kind::Page, *page_id, &relation_data).await
+}
+
+pub async fn delete_page_relation(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let (page_id, relation_id) = path.into_inner();
+    delete_relation(&req, &pool, Kind::Page, page_id, relation_id).await
+}
+
+pub async fn get_page_related(
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+    query: web::Query<RelatedQuery>,
+) -> impl Responder {
+    get_related(&pool, Kind::Page, *page_id, &query).await
+}
+
+/// "More like this": the contents whose title, summary and text share the
+/// most distinctive words with this one, scored from 0 to 1.
+pub async fn get_similar_contents(
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    query: web::Query<SimilarQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(SIMILAR_LIMIT);
+    if limit == 0 || limit > SIMILAR_MAX {
+        return bad_request(format!("limit must be between 1 and {}", SIMILAR_MAX));
+    }
+
+    let content = match sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
+        .bind(*content_id)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Content not found"
+            }))
+        }
+        Err(_) => return database_error(),
+    };
+
+    match relations::similar(&pool, &content, limit).await {
+        Ok(similar) => HttpResponse::Ok().json(similar),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to find similar contents"
+        })),
+    }
+}
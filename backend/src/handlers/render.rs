@@ -0,0 +1,65 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::config::AppConfig;
+use crate::render::{self, Links, Renderer};
+
+#[derive(Deserialize)]
+pub struct RenderQuery {
+    pub lang: Option<String>,
+}
+
+/// URL of the server-side rendering of `page_name` in `lang`.
+pub fn page_href(base: &str, page_name: &str, lang: &str) -> String {
+    format!("{}/api/public/render/{}?lang={}", base, page_name, lang)
+}
+
+/// Links into this API, absolute when `PUBLIC_BASE_URL` is set.
+pub fn api_links<'a>(
+    base: &str,
+    page_href: &'a (dyn Fn(&str, &str) -> String + Sync),
+) -> Links<'a> {
+    Links {
+        image_base: format!("{}/api/pre-view/images", base),
+        page_href,
+    }
+}
+
+/// Server-side rendered HTML of a `page_name`, for clients that cannot run
+/// the React app such as crawlers, email and link previews. Links are
+/// absolute when `PUBLIC_BASE_URL` is set.
+pub async fn render_page(
+    pool: web::Data<SqlitePool>,
+    renderer: web::Data<Renderer>,
+    config: web::Data<AppConfig>,
+    page_name: web::Path<String>,
+    query: web::Query<RenderQuery>,
+) -> impl Responder {
+    let base = config.public_base_url.trim_end_matches('/');
+    let page_href = |page_name: &str, lang: &str| page_href(base, page_name, lang);
+    let links = api_links(base, &page_href);
+
+    match render::render_page(
+        pool.get_ref(),
+        renderer.get_ref(),
+        &links,
+        &page_name,
+        query.lang.as_deref(),
+    )
+    .await
+    {
+        Ok(Some(document)) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(document.html),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Page not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to render page {}: {}", page_name, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to render page"
+            }))
+        }
+    }
+}
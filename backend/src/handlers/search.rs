@@ -1,12 +1,18 @@
 use actix_web::{web, HttpResponse, Responder};
 use serde::Deserialize;
 use sqlx::SqlitePool;
+use std::collections::HashSet;
 
+use crate::models::taxonomy::FacetQuery;
 use crate::models::{Agent, Page, Content};
+use crate::taxonomies::{self, Tagged};
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
     pub q: String,
+    /// Comma-separated term ids; only pages and contents tagged with each of
+    /// them, or with one of its descendants, are returned.
+    pub terms: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -16,21 +22,27 @@ pub struct SearchResults {
     pub contents: Vec<Content>,
 }
 
-pub async fn search_all(
-    pool: web::Data<SqlitePool>,
-    query: web::Query<SearchQuery>,
-) -> impl Responder {
-    let search_term = format!("%{}%", query.q);
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message
+    }))
+}
 
-    let agents = sqlx::query_as::<_, Agent>(
-        "SELECT * FROM agents
-         WHERE agent_number LIKE ?
-         ORDER BY id DESC"
-    )
-    .bind(&search_term)
-    .fetch_all(pool.get_ref())
-    .await
-    .unwrap_or_default();
+/// The term ids in a `terms` parameter; `None` when absent or empty.
+fn term_filter(terms: Option<&str>) -> Result<Option<Vec<i64>>, HttpResponse> {
+    match terms.map(taxonomies::parse_terms) {
+        Some(Ok(term_ids)) if !term_ids.is_empty() => Ok(Some(term_ids)),
+        Some(Err(message)) => Err(bad_request(message)),
+        _ => Ok(None),
+    }
+}
+
+async fn find_pages(
+    pool: &SqlitePool,
+    q: &str,
+    term_ids: Option<&[i64]>,
+) -> Result<Vec<Page>, sqlx::Error> {
+    let search_term = format!("%{}%", q);
 
     let pages = sqlx::query_as::<_, Page>(
         "SELECT * FROM pages
@@ -46,9 +58,24 @@ pub async fn search_all(
     .bind(&search_term)
     .bind(&search_term)
     .bind(&search_term)
-    .fetch_all(pool.get_ref())
-    .await
-    .unwrap_or_default();
+    .fetch_all(pool)
+    .await?;
+
+    match term_ids {
+        Some(term_ids) => {
+            let tagged = taxonomies::matching(pool, Tagged::Page, term_ids).await?;
+            Ok(pages.into_iter().filter(|page| tagged.contains(&page.id)).collect())
+        }
+        None => Ok(pages),
+    }
+}
+
+async fn find_contents(
+    pool: &SqlitePool,
+    q: &str,
+    term_ids: Option<&[i64]>,
+) -> Result<Vec<Content>, sqlx::Error> {
+    let search_term = format!("%{}%", q);
 
     let contents = sqlx::query_as::<_, Content>(
         "SELECT * FROM contents
@@ -56,15 +83,60 @@ pub async fn search_all(
          OR long_desc LIKE ?
          OR title LIKE ?
          OR image_path LIKE ?
+         OR fields LIKE ?
          ORDER BY id DESC"
     )
     .bind(&search_term)
     .bind(&search_term)
     .bind(&search_term)
     .bind(&search_term)
-    .fetch_all(pool.get_ref())
-    .await
-    .unwrap_or_default();
+    .bind(&search_term)
+    .fetch_all(pool)
+    .await?;
+
+    match term_ids {
+        Some(term_ids) => {
+            let tagged = taxonomies::matching(pool, Tagged::Content, term_ids).await?;
+            Ok(contents
+                .into_iter()
+                .filter(|content| tagged.contains(&content.id))
+                .collect())
+        }
+        None => Ok(contents),
+    }
+}
+
+pub async fn search_all(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    let term_ids = match term_filter(query.terms.as_deref()) {
+        Ok(term_ids) => term_ids,
+        Err(response) => return response,
+    };
+    let search_term = format!("%{}%", query.q);
+
+    // Agents carry no terms, so a term filter leaves none of them.
+    let agents = match term_ids {
+        Some(_) => Vec::new(),
+        None => sqlx::query_as::<_, Agent>(
+            "SELECT * FROM agents
+             WHERE agent_number LIKE ?
+             ORDER BY id DESC"
+        )
+        .bind(&search_term)
+        .fetch_all(pool.get_ref())
+        .await
+        .unwrap_or_default(),
+    };
+
+    let pages = find_pages(&pool, &query.q, term_ids.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let contents = find_contents(&pool, &query.q, term_ids.as_deref())
+        .await
+        .unwrap_or_default();
 
     let results = SearchResults {
         agents,
@@ -79,26 +151,12 @@ pub async fn search_pages(
     pool: web::Data<SqlitePool>,
     query: web::Query<SearchQuery>,
 ) -> impl Responder {
-    let search_term = format!("%{}%", query.q);
-
-    let pages = sqlx::query_as::<_, Page>(
-        "SELECT * FROM pages
-         WHERE page_name LIKE ?
-         OR section_name LIKE ?
-         OR lang LIKE ?
-         OR content_type LIKE ?
-         OR attributes LIKE ?
-         ORDER BY display_order, id DESC"
-    )
-    .bind(&search_term)
-    .bind(&search_term)
-    .bind(&search_term)
-    .bind(&search_term)
-    .bind(&search_term)
-    .fetch_all(pool.get_ref())
-    .await;
+    let term_ids = match term_filter(query.terms.as_deref()) {
+        Ok(term_ids) => term_ids,
+        Err(response) => return response,
+    };
 
-    match pages {
+    match find_pages(&pool, &query.q, term_ids.as_deref()).await {
         Ok(pages) => HttpResponse::Ok().json(pages),
         Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": "Failed to search pages"
@@ -110,27 +168,57 @@ pub async fn search_contents(
     pool: web::Data<SqlitePool>,
     query: web::Query<SearchQuery>,
 ) -> impl Responder {
-    let search_term = format!("%{}%", query.q);
-
-    let contents = sqlx::query_as::<_, Content>(
-        "SELECT * FROM contents
-         WHERE short_desc LIKE ?
-         OR long_desc LIKE ?
-         OR title LIKE ?
-         OR image_path LIKE ?
-         ORDER BY id DESC"
-    )
-    .bind(&search_term)
-    .bind(&search_term)
-    .bind(&search_term)
-    .bind(&search_term)
-    .fetch_all(pool.get_ref())
-    .await;
+    let term_ids = match term_filter(query.terms.as_deref()) {
+        Ok(term_ids) => term_ids,
+        Err(response) => return response,
+    };
 
-    match contents {
+    match find_contents(&pool, &query.q, term_ids.as_deref()).await {
         Ok(contents) => HttpResponse::Ok().json(contents),
         Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
             "error": "Failed to search contents"
         })),
     }
 }
+
+/// Term counts over the pages and contents a search with the same `q` and
+/// `terms` would return, for building filter sidebars.
+pub async fn search_facets(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<FacetQuery>,
+) -> impl Responder {
+    let term_ids = match term_filter(query.terms.as_deref()) {
+        Ok(term_ids) => term_ids,
+        Err(response) => return response,
+    };
+    let q = query.q.as_deref().unwrap_or_default();
+
+    let facets: Result<_, sqlx::Error> = async {
+        let pages: HashSet<i64> = find_pages(&pool, q, term_ids.as_deref())
+            .await?
+            .into_iter()
+            .map(|page| page.id)
+            .collect();
+        let contents: HashSet<i64> = find_contents(&pool, q, term_ids.as_deref())
+            .await?
+            .into_iter()
+            .map(|content| content.id)
+            .collect();
+        taxonomies::facets(
+            &pool,
+            query.taxonomy_id,
+            query.lang.as_deref(),
+            &contents,
+            &pages,
+        )
+        .await
+    }
+    .await;
+
+    match facets {
+        Ok(facets) => HttpResponse::Ok().json(facets),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to count terms"
+        })),
+    }
+}
@@ -0,0 +1,60 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::audit::{self, AuditContext};
+use crate::config::AppConfig;
+use crate::models::site::SiteBuildQuery;
+use crate::render::Renderer;
+use crate::site::{self, SiteError};
+
+/// Builds the static site into `SITE_DIR`, re-rendering only page_names
+/// that changed since the last build unless `?full=true`.
+pub async fn build_site(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    renderer: web::Data<Renderer>,
+    config: web::Data<AppConfig>,
+    query: web::Query<SiteBuildQuery>,
+) -> impl Responder {
+    let report = match site::build(
+        pool.get_ref(),
+        renderer.get_ref(),
+        &config.site,
+        Path::new(&config.upload_dir),
+        query.full.unwrap_or(false),
+    )
+    .await
+    {
+        Ok(report) => report,
+        Err(SiteError::Busy) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "A site build is already running"
+            }))
+        }
+        Err(e) => {
+            log::error!("Site build failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to build site"
+            }));
+        }
+    };
+
+    let context = AuditContext::from_request(&req);
+    let _ = audit::record(
+        pool.get_ref(),
+        &context,
+        "build",
+        "site",
+        &config.site.dir,
+        None::<&serde_json::Value>,
+        Some(&serde_json::json!({
+            "full": report.full,
+            "rendered": report.rendered.len(),
+            "removed": report.removed.len()
+        })),
+    )
+    .await;
+
+    HttpResponse::Ok().json(report)
+}
@@ -0,0 +1,728 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::languages::{self, LanguageError};
+use crate::models::taxonomy::{
+    Taxonomy, TaxonomyCreate, TaxonomyDetail, TaxonomyUpdate, Term, TermAssignment, TermCreate,
+    TermMove, TermUpdate,
+};
+use crate::page_tree::{self, TreeError};
+use crate::taxonomies::{self, Tagged, TaxonomyError};
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": message.into()
+    }))
+}
+
+fn database_error() -> HttpResponse {
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Database error"
+    }))
+}
+
+fn taxonomy_error(e: TaxonomyError, action: &str) -> HttpResponse {
+    match e {
+        TaxonomyError::Invalid(message) => bad_request(message),
+        TaxonomyError::Conflict(message) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": message
+        })),
+        TaxonomyError::Database(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to {} term", action)
+        })),
+    }
+}
+
+/// Taxonomy names are used as handles by clients, so they are limited to
+/// lowercase letters, digits, `-` and `_`.
+fn check_taxonomy_name(name: &str) -> Result<(), HttpResponse> {
+    let valid = !name.is_empty()
+        && name.len() <= 50
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(bad_request(
+            "name must be 1 to 50 lowercase letters, digits, '-' or '_'",
+        ));
+    }
+    Ok(())
+}
+
+fn check_term_name(name: &str) -> Result<(), HttpResponse> {
+    if name.trim().is_empty() || name.chars().count() > 100 {
+        return Err(bad_request("name must be 1 to 100 characters"));
+    }
+    Ok(())
+}
+
+fn check_slug(slug: &str) -> Result<String, HttpResponse> {
+    match page_tree::normalize_slug(slug) {
+        Ok(slug) => Ok(slug),
+        Err(TreeError::Invalid(message)) | Err(TreeError::Conflict(message)) => {
+            Err(bad_request(message))
+        }
+        Err(TreeError::Database(_)) => Err(database_error()),
+    }
+}
+
+/// Names keyed by canonical language code. Every language must be
+/// registered and enabled.
+async fn check_names(
+    pool: &SqlitePool,
+    names: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, HttpResponse> {
+    let mut checked = BTreeMap::new();
+    for (tag, name) in names {
+        check_term_name(name)?;
+        let code = match languages::validate(pool, tag).await {
+            Ok(code) => code,
+            Err(LanguageError::Invalid(message)) => return Err(bad_request(message)),
+            Err(LanguageError::Database(_)) => return Err(database_error()),
+        };
+        if checked
+            .insert(code.clone(), name.trim().to_string())
+            .is_some()
+        {
+            return Err(bad_request(format!("names has '{}' more than once", code)));
+        }
+    }
+    Ok(checked)
+}
+
+/// A term with its names, as recorded in the change log.
+async fn snapshot(
+    conn: &mut SqliteConnection,
+    term: &Term,
+) -> Result<serde_json::Value, sqlx::Error> {
+    let names: Vec<(String, String)> =
+        sqlx::query_as("SELECT lang, name FROM term_names WHERE term_id = ?")
+            .bind(term.id)
+            .fetch_all(conn)
+            .await?;
+    let mut value = serde_json::to_value(term).unwrap_or_default();
+    value["names"] = serde_json::json!(names.into_iter().collect::<BTreeMap<_, _>>());
+    Ok(value)
+}
+
+async fn fetch_taxonomy(pool: &SqlitePool, taxonomy_id: i64) -> Result<Taxonomy, HttpResponse> {
+    let taxonomy = sqlx::query_as::<_, Taxonomy>("SELECT * FROM taxonomies WHERE id = ?")
+        .bind(taxonomy_id)
+        .fetch_optional(pool)
+        .await;
+
+    match taxonomy {
+        Ok(Some(taxonomy)) => Ok(taxonomy),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Taxonomy not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn fetch_term(
+    pool: &SqlitePool,
+    taxonomy_id: i64,
+    term_id: i64,
+) -> Result<Term, HttpResponse> {
+    let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE id = ? AND taxonomy_id = ?")
+        .bind(term_id)
+        .bind(taxonomy_id)
+        .fetch_optional(pool)
+        .await;
+
+    match term {
+        Ok(Some(term)) => Ok(term),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Term not found"
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn name_taken(pool: &SqlitePool, name: &str, taxonomy_id: i64) -> Result<(), HttpResponse> {
+    let taken: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT id FROM taxonomies WHERE name = ? AND id != ?")
+            .bind(name)
+            .bind(taxonomy_id)
+            .fetch_optional(pool)
+            .await;
+    match taken {
+        Ok(None) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Taxonomy '{}' already exists", name)
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+pub async fn create_taxonomy(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    taxonomy_data: web::Json<TaxonomyCreate>,
+) -> impl Responder {
+    if let Err(response) = check_taxonomy_name(&taxonomy_data.name) {
+        return response;
+    }
+    if let Err(response) = name_taken(&pool, &taxonomy_data.name, 0).await {
+        return response;
+    }
+
+    let result: Result<Taxonomy, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let taxonomy = sqlx::query_as::<_, Taxonomy>(
+            "INSERT INTO taxonomies (name, hierarchical) VALUES (?, ?) RETURNING *",
+        )
+        .bind(&taxonomy_data.name)
+        .bind(taxonomy_data.hierarchical.unwrap_or(false))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "taxonomy",
+            taxonomy.id,
+            None::<&Taxonomy>,
+            Some(&taxonomy),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(taxonomy)
+    }
+    .await;
+
+    match result {
+        Ok(taxonomy) => HttpResponse::Created().json(taxonomy),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create taxonomy"
+        })),
+    }
+}
+
+pub async fn get_taxonomies(pool: web::Data<SqlitePool>) -> impl Responder {
+    let taxonomies = sqlx::query_as::<_, Taxonomy>("SELECT * FROM taxonomies ORDER BY name")
+        .fetch_all(pool.get_ref())
+        .await;
+
+    match taxonomies {
+        Ok(taxonomies) => HttpResponse::Ok().json(taxonomies),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch taxonomies"
+        })),
+    }
+}
+
+/// A taxonomy with its whole term tree and every name.
+pub async fn get_taxonomy(
+    pool: web::Data<SqlitePool>,
+    taxonomy_id: web::Path<i64>,
+) -> impl Responder {
+    let taxonomy = match fetch_taxonomy(&pool, *taxonomy_id).await {
+        Ok(taxonomy) => taxonomy,
+        Err(response) => return response,
+    };
+
+    let terms: Result<_, sqlx::Error> = async {
+        let mut conn = pool.acquire().await?;
+        taxonomies::tree(&mut conn, taxonomy.id).await
+    }
+    .await;
+
+    match terms {
+        Ok(terms) => HttpResponse::Ok().json(TaxonomyDetail { taxonomy, terms }),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch terms"
+        })),
+    }
+}
+
+pub async fn update_taxonomy(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    taxonomy_id: web::Path<i64>,
+    taxonomy_data: web::Json<TaxonomyUpdate>,
+) -> impl Responder {
+    let existing = match fetch_taxonomy(&pool, *taxonomy_id).await {
+        Ok(taxonomy) => taxonomy,
+        Err(response) => return response,
+    };
+
+    let name = taxonomy_data.name.as_deref().unwrap_or(&existing.name);
+    if let Err(response) = check_taxonomy_name(name) {
+        return response;
+    }
+    if let Err(response) = name_taken(&pool, name, existing.id).await {
+        return response;
+    }
+    let hierarchical = taxonomy_data.hierarchical.unwrap_or(existing.hierarchical);
+
+    let result: Result<Taxonomy, TaxonomyError> = async {
+        let mut tx = pool.begin().await?;
+        if !hierarchical {
+            let nested: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM terms WHERE taxonomy_id = ? AND parent_id IS NOT NULL)",
+            )
+            .bind(existing.id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if nested {
+                return Err(TaxonomyError::Conflict(format!(
+                    "'{}' has nested terms; move them to the top level first",
+                    existing.name
+                )));
+            }
+        }
+
+        let updated = sqlx::query_as::<_, Taxonomy>(
+            "UPDATE taxonomies SET name = ?, hierarchical = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(name)
+        .bind(hierarchical)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "taxonomy",
+            existing.id,
+            Some(&existing),
+            Some(&updated),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    match result {
+        Ok(taxonomy) => HttpResponse::Ok().json(taxonomy),
+        Err(TaxonomyError::Database(_)) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to update taxonomy"
+            }))
+        }
+        Err(e) => taxonomy_error(e, "update"),
+    }
+}
+
+/// Deletes a taxonomy with all of its terms and their assignments.
+pub async fn delete_taxonomy(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    taxonomy_id: web::Path<i64>,
+) -> impl Responder {
+    let existing = match fetch_taxonomy(&pool, *taxonomy_id).await {
+        Ok(taxonomy) => taxonomy,
+        Err(response) => return response,
+    };
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM taxonomies WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "taxonomy",
+            existing.id,
+            Some(&existing),
+            None::<&Taxonomy>,
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Taxonomy deleted successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete taxonomy"
+        })),
+    }
+}
+
+pub async fn create_term(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    taxonomy_id: web::Path<i64>,
+    term_data: web::Json<TermCreate>,
+) -> impl Responder {
+    let taxonomy = match fetch_taxonomy(&pool, *taxonomy_id).await {
+        Ok(taxonomy) => taxonomy,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = check_term_name(&term_data.name) {
+        return response;
+    }
+    let slug = match check_slug(&term_data.slug) {
+        Ok(slug) => slug,
+        Err(response) => return response,
+    };
+    let names = match &term_data.names {
+        Some(names) => match check_names(&pool, names).await {
+            Ok(names) => names,
+            Err(response) => return response,
+        },
+        None => BTreeMap::new(),
+    };
+
+    let result: Result<Term, TaxonomyError> = async {
+        let mut tx = pool.begin().await?;
+        taxonomies::check_parent(&mut tx, &taxonomy, term_data.parent_id, None).await?;
+        taxonomies::check_slug(&mut tx, taxonomy.id, &slug, None).await?;
+
+        let term = sqlx::query_as::<_, Term>(
+            "INSERT INTO terms (taxonomy_id, parent_id, slug, name, display_order)
+             VALUES (?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(taxonomy.id)
+        .bind(term_data.parent_id)
+        .bind(&slug)
+        .bind(term_data.name.trim())
+        .bind(term_data.display_order.unwrap_or(0))
+        .fetch_one(&mut *tx)
+        .await?;
+        taxonomies::set_names(&mut tx, term.id, &names).await?;
+
+        let after = snapshot(&mut tx, &term).await?;
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "term",
+            term.id,
+            None::<&Term>,
+            Some(&after),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(term)
+    }
+    .await;
+
+    match result {
+        Ok(term) => HttpResponse::Created().json(term),
+        Err(e) => taxonomy_error(e, "create"),
+    }
+}
+
+pub async fn update_term(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    term_data: web::Json<TermUpdate>,
+) -> impl Responder {
+    let (taxonomy_id, term_id) = path.into_inner();
+    let existing = match fetch_term(&pool, taxonomy_id, term_id).await {
+        Ok(term) => term,
+        Err(response) => return response,
+    };
+
+    let name = term_data.name.as_deref().unwrap_or(&existing.name);
+    if let Err(response) = check_term_name(name) {
+        return response;
+    }
+    let slug = match check_slug(term_data.slug.as_deref().unwrap_or(&existing.slug)) {
+        Ok(slug) => slug,
+        Err(response) => return response,
+    };
+    let names = match &term_data.names {
+        Some(names) => match check_names(&pool, names).await {
+            Ok(names) => Some(names),
+            Err(response) => return response,
+        },
+        None => None,
+    };
+
+    let result: Result<Term, TaxonomyError> = async {
+        let mut tx = pool.begin().await?;
+        taxonomies::check_slug(&mut tx, taxonomy_id, &slug, Some(existing.id)).await?;
+        let before = snapshot(&mut tx, &existing).await?;
+
+        let updated = sqlx::query_as::<_, Term>(
+            "UPDATE terms SET slug = ?, name = ?, display_order = ?,
+             updated_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING *",
+        )
+        .bind(&slug)
+        .bind(name.trim())
+        .bind(term_data.display_order.unwrap_or(existing.display_order))
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(names) = &names {
+            taxonomies::set_names(&mut tx, existing.id, names).await?;
+        }
+
+        let after = snapshot(&mut tx, &updated).await?;
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "term",
+            existing.id,
+            Some(&before),
+            Some(&after),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    .await;
+
+    match result {
+        Ok(term) => HttpResponse::Ok().json(term),
+        Err(e) => taxonomy_error(e, "update"),
+    }
+}
+
+pub async fn move_term(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+    move_data: web::Json<TermMove>,
+) -> impl Responder {
+    let (taxonomy_id, term_id) = path.into_inner();
+    let taxonomy = match fetch_taxonomy(&pool, taxonomy_id).await {
+        Ok(taxonomy) => taxonomy,
+        Err(response) => return response,
+    };
+    let existing = match fetch_term(&pool, taxonomy_id, term_id).await {
+        Ok(term) => term,
+        Err(response) => return response,
+    };
+
+    let result: Result<Term, TaxonomyError> = async {
+        let mut tx = pool.begin().await?;
+        taxonomies::check_parent(&mut tx, &taxonomy, move_data.parent_id, Some(existing.id))
+            .await?;
+
+        let moved = sqlx::query_as::<_, Term>(
+            "UPDATE terms SET parent_id = ?, display_order = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(move_data.parent_id)
+        .bind(move_data.display_order.unwrap_or(existing.display_order))
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "move",
+            "term",
+            existing.id,
+            Some(&existing),
+            Some(&moved),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(moved)
+    }
+    .await;
+
+    match result {
+        Ok(term) => HttpResponse::Ok().json(term),
+        Err(e) => taxonomy_error(e, "move"),
+    }
+}
+
+/// Deletes a term and removes it from every content and page. Terms with
+/// children must be emptied or moved first.
+pub async fn delete_term(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let (taxonomy_id, term_id) = path.into_inner();
+    let existing = match fetch_term(&pool, taxonomy_id, term_id).await {
+        Ok(term) => term,
+        Err(response) => return response,
+    };
+
+    let result: Result<(), TaxonomyError> = async {
+        let mut tx = pool.begin().await?;
+        let children: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM terms WHERE parent_id = ?")
+            .bind(existing.id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if children > 0 {
+            return Err(TaxonomyError::Conflict(format!(
+                "Term {} has {} child terms; delete or move them first",
+                existing.id, children
+            )));
+        }
+
+        let before = snapshot(&mut tx, &existing).await?;
+        sqlx::query("DELETE FROM terms WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "term",
+            existing.id,
+            Some(&before),
+            None::<&Term>,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Term deleted successfully"
+        })),
+        Err(e) => taxonomy_error(e, "delete"),
+    }
+}
+
+/// Fails with `404` unless the content or page `id` exists.
+async fn check_tagged(pool: &SqlitePool, tagged: Tagged, id: i64) -> Result<(), HttpResponse> {
+    let (query, missing) = match tagged {
+        Tagged::Content => ("SELECT id FROM contents WHERE id = ?", "Content not found"),
+        Tagged::Page => ("SELECT id FROM pages WHERE id = ?", "Page not found"),
+    };
+    let exists: Result<Option<i64>, sqlx::Error> = sqlx::query_scalar(query)
+        .bind(id)
+        .fetch_optional(pool)
+        .await;
+    match exists {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": missing
+        }))),
+        Err(_) => Err(database_error()),
+    }
+}
+
+async fn get_terms(pool: &SqlitePool, tagged: Tagged, id: i64) -> HttpResponse {
+    if let Err(response) = check_tagged(pool, tagged, id).await {
+        return response;
+    }
+
+    let terms: Result<Vec<Term>, sqlx::Error> = async {
+        let mut conn = pool.acquire().await?;
+        taxonomies::assigned(&mut conn, tagged, id).await
+    }
+    .await;
+
+    match terms {
+        Ok(terms) => HttpResponse::Ok().json(terms),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch terms"
+        })),
+    }
+}
+
+/// Replaces the terms of a content or page. Tagging does not change the
+/// tagged item's `version`; it is recorded in the change log on its own.
+async fn set_terms(
+    req: &HttpRequest,
+    pool: &SqlitePool,
+    tagged: Tagged,
+    id: i64,
+    term_ids: &[i64],
+) -> HttpResponse {
+    if let Err(response) = check_tagged(pool, tagged, id).await {
+        return response;
+    }
+    let entity = match tagged {
+        Tagged::Content => "content_terms",
+        Tagged::Page => "page_terms",
+    };
+
+    let result: Result<Vec<Term>, TaxonomyError> = async {
+        let mut tx = pool.begin().await?;
+        let before = taxonomies::assigned(&mut tx, tagged, id).await?;
+        taxonomies::assign(&mut tx, tagged, id, term_ids).await?;
+        let after = taxonomies::assigned(&mut tx, tagged, id).await?;
+
+        let context = AuditContext::from_request(req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            entity,
+            id,
+            Some(&before),
+            Some(&after),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(after)
+    }
+    .await;
+
+    match result {
+        Ok(terms) => HttpResponse::Ok().json(terms),
+        Err(e) => taxonomy_error(e, "assign"),
+    }
+}
+
+pub async fn get_content_terms(
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+) -> impl Responder {
+    get_terms(&pool, Tagged::Content, *content_id).await
+}
+
+pub async fn set_content_terms(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    content_id: web::Path<i64>,
+    assignment: web::Json<TermAssignment>,
+) -> impl Responder {
+    set_terms(
+        &req,
+        &pool,
+        Tagged::Content,
+        *content_id,
+        &assignment.term_ids,
+    )
+    .await
+}
+
+pub async fn get_page_terms(
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+) -> impl Responder {
+    get_terms(&pool, Tagged::Page, *page_id).await
+}
+
+pub async fn set_page_terms(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+    assignment: web::Json<TermAssignment>,
+) -> impl Responder {
+    set_terms(&req, &pool, Tagged::Page, *page_id, &assignment.term_ids).await
+}
@@ -0,0 +1,452 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::blocks;
+use crate::changes;
+use crate::config::AppConfig;
+use crate::etag;
+use crate::galleries;
+use crate::handlers::image::unique_filename;
+use crate::handlers::language;
+use crate::languages;
+use crate::page_tree::{self, TreeError};
+use crate::redirects;
+use crate::render;
+use crate::models::translation::{
+    PublicPageQuery, TranslationCreate, TranslationEntry, TranslationStatus,
+};
+use crate::models::gallery::ContentImage;
+use crate::models::{Content, Page};
+
+/// Creates a copy of a page in another language and links both through the
+/// source's translation group, assigning the source a group if it has none.
+/// Contents are copied along with their images and attachments, so deleting
+/// a content on one side never removes the other side's files.
+pub async fn create_translation(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    config: web::Data<AppConfig>,
+    page_id: web::Path<i64>,
+    translation: web::Json<TranslationCreate>,
+) -> impl Responder {
+    let source = match sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+        .bind(*page_id)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(page)) => page,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Page not found"
+            }))
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Database error"
+            }))
+        }
+    };
+
+    let lang = match languages::validate(pool.get_ref(), &translation.lang).await {
+        Ok(lang) => lang,
+        Err(e) => return language::error_response(e),
+    };
+    if lang == source.lang {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "lang must differ from the source page's language"
+        }));
+    }
+
+    if let Some(group) = &source.translation_group {
+        let existing: Result<Option<i64>, sqlx::Error> =
+            sqlx::query_scalar("SELECT id FROM pages WHERE translation_group = ? AND lang = ?")
+                .bind(group)
+                .bind(&lang)
+                .fetch_optional(pool.get_ref())
+                .await;
+        match existing {
+            Ok(Some(id)) => {
+                return HttpResponse::Conflict().json(serde_json::json!({
+                    "error": "A translation in this language already exists",
+                    "id": id
+                }))
+            }
+            Ok(None) => {}
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Database error"
+                }))
+            }
+        }
+    }
+
+    let contents = if translation.copy_contents.unwrap_or(true) {
+        let contents: Result<Vec<Content>, sqlx::Error> = async {
+            let mut contents = sqlx::query_as::<_, Content>(
+                "SELECT * FROM contents WHERE ref_id = ? ORDER BY display_order, id",
+            )
+            .bind(source.id)
+            .fetch_all(pool.get_ref())
+            .await?;
+            galleries::attach(&pool, &mut contents).await?;
+            Ok(contents)
+        }
+        .await;
+        match contents {
+            Ok(contents) => contents,
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Database error"
+                }))
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Copy images up front; a content whose image is missing on disk keeps
+    // its text but loses the dangling reference.
+    let upload_dir = Path::new(&config.upload_dir);
+    let mut copied = Vec::new();
+    let mut copies = Vec::with_capacity(contents.len());
+    for content in &contents {
+        match copy_images(upload_dir, content, &mut copied) {
+            Ok(copy) => copies.push(copy),
+            Err(_) => {
+                remove_images(upload_dir, &copied);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Failed to copy images"
+                }));
+            }
+        }
+    }
+
+    let context = AuditContext::from_request(&req);
+    let result: Result<Page, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+
+        let group = match &source.translation_group {
+            Some(group) => group.clone(),
+            None => {
+                let group = Uuid::new_v4().to_string();
+                let linked = sqlx::query_as::<_, Page>(
+                    "UPDATE pages SET translation_group = ?, version = version + 1
+                     WHERE id = ? RETURNING *",
+                )
+                .bind(&group)
+                .bind(source.id)
+                .fetch_one(&mut *tx)
+                .await?;
+                changes::record(
+                    &mut tx,
+                    &context,
+                    "update",
+                    "page",
+                    source.id,
+                    Some(&source),
+                    Some(&linked),
+                )
+                .await?;
+                group
+            }
+        };
+
+        // The copy goes under the parent's translation when there is one, or
+        // to the top level since a parent is in the same language as its
+        // children, and keeps the slug unless a sibling in `lang` has it.
+        let parent_id = match source.parent_id {
+            Some(parent_id) => sqlx::query_scalar::<_, i64>(
+                "SELECT t.id FROM pages p
+                 JOIN pages t ON t.translation_group = p.translation_group AND t.lang = ?
+                 WHERE p.id = ?",
+            )
+            .bind(&lang)
+            .bind(parent_id)
+            .fetch_optional(&mut *tx)
+            .await?,
+            None => None,
+        };
+        let slug =
+            match page_tree::check_slug(&mut tx, parent_id, &lang, source.slug.as_deref(), None)
+                .await
+            {
+                Ok(()) => source.slug.clone(),
+                Err(TreeError::Database(e)) => return Err(e),
+                Err(_) => None,
+            };
+
+        let page_id: i64 = sqlx::query_scalar(
+            "INSERT INTO pages (page_name, section_name, lang, content_type, visible, display_order,
+             attributes, translation_group, translation_of, parent_id, slug)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(&source.page_name)
+        .bind(&source.section_name)
+        .bind(&lang)
+        .bind(&source.content_type)
+        .bind(translation.visible.unwrap_or(false))
+        .bind(source.display_order)
+        .bind(&source.attributes)
+        .bind(&group)
+        .bind(source.id)
+        .bind(parent_id)
+        .bind(&slug)
+        .fetch_one(&mut *tx)
+        .await?;
+        page_tree::refresh_paths(&mut tx, page_id).await?;
+        let page = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+            .bind(page_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        changes::record(&mut tx, &context, "create", "page", page.id, None::<&Page>, Some(&page))
+            .await?;
+        redirects::clear_page_sources(&mut tx, &context, page.id).await?;
+
+        for (content, (image_path, gallery)) in contents.iter().zip(&copies) {
+            let copy = sqlx::query_as::<_, Content>(
+                "INSERT INTO contents (ref_id, short_desc, long_desc, format, long_desc_html,
+                 image_path, title, display_order, type_id, fields)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+            )
+            .bind(page.id)
+            .bind(&content.short_desc)
+            .bind(&content.long_desc)
+            .bind(&content.format)
+            .bind(&content.long_desc_html)
+            .bind(image_path)
+            .bind(&content.title)
+            .bind(content.display_order)
+            .bind(content.type_id)
+            .bind(&content.fields)
+            .fetch_one(&mut *tx)
+            .await?;
+            changes::record(
+                &mut tx,
+                &context,
+                "create",
+                "content",
+                copy.id,
+                None::<&Content>,
+                Some(&copy),
+            )
+            .await?;
+            for (image, filename) in gallery {
+                let attached = sqlx::query_as::<_, ContentImage>(
+                    "INSERT INTO content_images (content_id, filename, caption, alt, display_order)
+                     VALUES (?, ?, ?, ?, ?) RETURNING *",
+                )
+                .bind(copy.id)
+                .bind(filename)
+                .bind(&image.caption)
+                .bind(&image.alt)
+                .bind(image.display_order)
+                .fetch_one(&mut *tx)
+                .await?;
+                changes::record(
+                    &mut tx,
+                    &context,
+                    "create",
+                    "content_image",
+                    attached.id,
+                    None::<&ContentImage>,
+                    Some(&attached),
+                )
+                .await?;
+            }
+        }
+        if translation.copy_contents.unwrap_or(true) {
+            blocks::copy_placements(&mut tx, &context, source.id, page.id).await?;
+        }
+
+        tx.commit().await?;
+        Ok(page)
+    }
+    .await;
+
+    match result {
+        Ok(page) => HttpResponse::Created()
+            .insert_header((header::ETAG, etag::etag(page.version)))
+            .json(serde_json::json!({
+                "id": page.id,
+                "version": page.version,
+                "translation_group": page.translation_group,
+                "message": "Translation created successfully"
+            })),
+        Err(_) => {
+            remove_images(upload_dir, &copied);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to create translation"
+            }))
+        }
+    }
+}
+
+/// Copies one uploaded file to a fresh name, recorded in `copied`. A name
+/// that is not an uploaded file, or a file missing on disk, is skipped.
+fn copy_image(
+    upload_dir: &Path,
+    entry: &str,
+    copied: &mut Vec<String>,
+) -> std::io::Result<Option<String>> {
+    let Some(file) = render::stored_file(entry) else {
+        return Ok(None);
+    };
+    let filename = sanitize_filename::sanitize(unique_filename(file));
+    match std::fs::copy(upload_dir.join(file), upload_dir.join(&filename)) {
+        Ok(_) => {
+            copied.push(filename.clone());
+            Ok(Some(filename))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Copy of a content's `image_path`, and its attached images with the name
+/// of each copy.
+type ImageCopies<'a> = (Option<String>, Vec<(&'a ContentImage, String)>);
+
+/// Copies the files of a content's `image_path` and attached images. URLs
+/// in `image_path` are kept, and each copied entry keeps its `/images/`
+/// prefix if it had one.
+fn copy_images<'a>(
+    upload_dir: &Path,
+    content: &'a Content,
+    copied: &mut Vec<String>,
+) -> std::io::Result<ImageCopies<'a>> {
+    let mut entries = Vec::new();
+    for entry in render::image_paths(content.image_path.as_deref().unwrap_or_default()) {
+        if render::is_external(entry) {
+            entries.push(entry.to_string());
+        } else if let Some(filename) = copy_image(upload_dir, entry, copied)? {
+            let prefix = if entry.starts_with(render::IMAGE_PREFIX) {
+                render::IMAGE_PREFIX
+            } else {
+                ""
+            };
+            entries.push(format!("{}{}", prefix, filename));
+        }
+    }
+    let image_path = (!entries.is_empty()).then(|| entries.join(", "));
+    let mut gallery = Vec::with_capacity(content.gallery.len());
+    for image in &content.gallery {
+        if let Some(filename) = copy_image(upload_dir, &image.filename, copied)? {
+            gallery.push((image, filename));
+        }
+    }
+    Ok((image_path, gallery))
+}
+
+fn remove_images(upload_dir: &Path, filenames: &[String]) {
+    for filename in filenames {
+        let _ = std::fs::remove_file(upload_dir.join(filename));
+    }
+}
+
+/// Lists a page's translation group with the languages it lacks. A
+/// translation is stale when the page it was made from, or any of that
+/// page's contents, changed after the translation last did.
+pub async fn get_translations(
+    pool: web::Data<SqlitePool>,
+    page_id: web::Path<i64>,
+) -> impl Responder {
+    let result: Result<Option<TranslationStatus>, sqlx::Error> = async {
+        let group: Option<Option<String>> =
+            sqlx::query_scalar("SELECT translation_group FROM pages WHERE id = ?")
+                .bind(*page_id)
+                .fetch_optional(pool.get_ref())
+                .await?;
+        let Some(group) = group else {
+            return Ok(None);
+        };
+
+        let mut translations = sqlx::query_as::<_, TranslationEntry>(
+            "SELECT p.id, p.lang, p.visible, p.version, p.translation_of,
+             MAX(p.updated_at, COALESCE(
+                 (SELECT MAX(c.updated_at) FROM contents c WHERE c.ref_id = p.id),
+                 p.updated_at
+             )) AS last_modified
+             FROM pages p WHERE p.translation_group = ? OR p.id = ?
+             ORDER BY p.lang, p.id",
+        )
+        .bind(&group)
+        .bind(*page_id)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+        let last_modified: BTreeMap<i64, String> = translations
+            .iter()
+            .map(|entry| (entry.id, entry.last_modified.clone()))
+            .collect();
+        for entry in &mut translations {
+            entry.stale = entry
+                .translation_of
+                .and_then(|source_id| last_modified.get(&source_id))
+                .is_some_and(|source_modified| *source_modified > entry.last_modified);
+        }
+
+        let source_id = translations
+            .iter()
+            .filter(|entry| entry.translation_of.is_none())
+            .map(|entry| entry.id)
+            .min();
+
+        let site_langs: Vec<String> =
+            sqlx::query_scalar("SELECT code FROM languages WHERE enabled = 1 ORDER BY code")
+                .fetch_all(pool.get_ref())
+                .await?;
+        let group_langs: BTreeSet<&str> = translations
+            .iter()
+            .map(|entry| entry.lang.as_str())
+            .collect();
+        let missing = site_langs
+            .into_iter()
+            .filter(|lang| !group_langs.contains(lang.as_str()))
+            .collect();
+
+        Ok(Some(TranslationStatus {
+            translation_group: group,
+            source_id,
+            translations,
+            missing,
+        }))
+    }
+    .await;
+
+    match result {
+        Ok(Some(status)) => HttpResponse::Ok().json(status),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Page not found"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch translations"
+        })),
+    }
+}
+
+/// Visible pages of a slot with their contents, for unauthenticated readers,
+/// resolved through the language fallback chain by
+/// [`languages::resolve_slot`].
+pub async fn get_public_pages(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<PublicPageQuery>,
+) -> impl Responder {
+    let pages = languages::resolve_slot(
+        pool.get_ref(),
+        &query.page_name,
+        query.section_name.as_deref(),
+        query.lang.as_deref(),
+    )
+    .await;
+
+    match pages {
+        Ok(pages) => HttpResponse::Ok().json(pages),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch pages"
+        })),
+    }
+}
@@ -0,0 +1,322 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::models::webhook::{
+    DeliveryQuery, Webhook, WebhookCreate, WebhookDelivery, WebhookUpdate, WEBHOOK_EVENTS,
+};
+use crate::webhooks;
+
+/// Joins a subscription list into the stored form, rejecting unknown events.
+fn events_column(events: Option<&Vec<String>>) -> Result<String, HttpResponse> {
+    let Some(events) = events.filter(|events| !events.is_empty()) else {
+        return Ok("*".to_string());
+    };
+
+    if let Some(unknown) = events
+        .iter()
+        .find(|e| !WEBHOOK_EVENTS.contains(&e.as_str()))
+    {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown event '{}'", unknown),
+            "events": WEBHOOK_EVENTS
+        })));
+    }
+
+    Ok(events.join(","))
+}
+
+fn valid_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+pub async fn create_webhook(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    webhook_data: web::Json<WebhookCreate>,
+) -> impl Responder {
+    if !valid_url(&webhook_data.url) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "url must be an http or https URL"
+        }));
+    }
+
+    let events = match events_column(webhook_data.events.as_ref()) {
+        Ok(events) => events,
+        Err(response) => return response,
+    };
+    let secret = webhook_data
+        .secret
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+    let result: Result<Webhook, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let webhook = sqlx::query_as::<_, Webhook>(
+            "INSERT INTO webhooks (url, secret, events, is_active) VALUES (?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&webhook_data.url)
+        .bind(&secret)
+        .bind(&events)
+        .bind(webhook_data.is_active.unwrap_or(true))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "create",
+            "webhook",
+            webhook.id,
+            None::<&Webhook>,
+            Some(&webhook),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(webhook)
+    }
+    .await;
+
+    match result {
+        Ok(webhook) => HttpResponse::Created().json(serde_json::json!({
+            "id": webhook.id,
+            "secret": secret,
+            "message": "Webhook created successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to create webhook"
+        })),
+    }
+}
+
+pub async fn get_webhooks(pool: web::Data<SqlitePool>) -> impl Responder {
+    let webhooks = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks ORDER BY id DESC")
+        .fetch_all(pool.get_ref())
+        .await;
+
+    match webhooks {
+        Ok(webhooks) => HttpResponse::Ok().json(webhooks),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch webhooks"
+        })),
+    }
+}
+
+pub async fn get_webhook(
+    pool: web::Data<SqlitePool>,
+    webhook_id: web::Path<i64>,
+) -> impl Responder {
+    match fetch_webhook(&pool, *webhook_id).await {
+        Ok(webhook) => HttpResponse::Ok().json(webhook),
+        Err(response) => response,
+    }
+}
+
+pub async fn update_webhook(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    webhook_id: web::Path<i64>,
+    webhook_data: web::Json<WebhookUpdate>,
+) -> impl Responder {
+    let existing = match fetch_webhook(&pool, *webhook_id).await {
+        Ok(webhook) => webhook,
+        Err(response) => return response,
+    };
+
+    let url = webhook_data.url.as_ref().unwrap_or(&existing.url);
+    if !valid_url(url) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "url must be an http or https URL"
+        }));
+    }
+
+    let events = match &webhook_data.events {
+        Some(events) => match events_column(Some(events)) {
+            Ok(events) => events,
+            Err(response) => return response,
+        },
+        None => existing.events.clone(),
+    };
+    let secret = webhook_data.secret.as_ref().unwrap_or(&existing.secret);
+    let is_active = webhook_data.is_active.unwrap_or(existing.is_active);
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        let updated = sqlx::query_as::<_, Webhook>(
+            "UPDATE webhooks SET url = ?, secret = ?, events = ?, is_active = ?,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING *",
+        )
+        .bind(url)
+        .bind(secret)
+        .bind(&events)
+        .bind(is_active)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "update",
+            "webhook",
+            existing.id,
+            Some(&existing),
+            Some(&updated),
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Webhook updated successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to update webhook"
+        })),
+    }
+}
+
+pub async fn delete_webhook(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    webhook_id: web::Path<i64>,
+) -> impl Responder {
+    let existing = match fetch_webhook(&pool, *webhook_id).await {
+        Ok(webhook) => webhook,
+        Err(response) => return response,
+    };
+
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(existing.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "delete",
+            "webhook",
+            existing.id,
+            Some(&existing),
+            None::<&Webhook>,
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Webhook deleted successfully"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to delete webhook"
+        })),
+    }
+}
+
+/// Lists deliveries, newest first. `?status=dead` is the dead-letter view.
+pub async fn get_deliveries(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<DeliveryQuery>,
+) -> impl Responder {
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT d.*, e.event_type, e.payload
+         FROM webhook_deliveries d
+         JOIN webhook_events e ON e.id = d.event_id
+         WHERE (? IS NULL OR d.status = ?)
+         AND (? IS NULL OR d.webhook_id = ?)
+         ORDER BY d.id DESC
+         LIMIT ? OFFSET ?",
+    )
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(query.webhook_id)
+    .bind(query.webhook_id)
+    .bind(query.limit.unwrap_or(100).clamp(1, 1000))
+    .bind(query.offset.unwrap_or(0))
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match deliveries {
+        Ok(deliveries) => HttpResponse::Ok().json(deliveries),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to fetch deliveries"
+        })),
+    }
+}
+
+/// Puts a delivery back in the queue with a fresh retry budget.
+pub async fn redeliver(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    delivery_id: web::Path<i64>,
+) -> impl Responder {
+    let result: Result<Option<WebhookDelivery>, sqlx::Error> = async {
+        let mut tx = pool.begin().await?;
+        if !webhooks::requeue(&mut tx, *delivery_id).await? {
+            return Ok(None);
+        }
+
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT d.*, e.event_type, e.payload
+             FROM webhook_deliveries d
+             JOIN webhook_events e ON e.id = d.event_id
+             WHERE d.id = ?",
+        )
+        .bind(*delivery_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let context = AuditContext::from_request(&req);
+        changes::record(
+            &mut tx,
+            &context,
+            "redeliver",
+            "webhook_delivery",
+            delivery.id,
+            None::<&WebhookDelivery>,
+            Some(&delivery),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(Some(delivery))
+    }
+    .await;
+
+    match result {
+        Ok(Some(delivery)) => HttpResponse::Ok().json(delivery),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Delivery not found"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to queue redelivery"
+        })),
+    }
+}
+
+async fn fetch_webhook(pool: &SqlitePool, webhook_id: i64) -> Result<Webhook, HttpResponse> {
+    match sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = ?")
+        .bind(webhook_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(webhook)) => Ok(webhook),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Webhook not found"
+        }))),
+        Err(_) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Database error"
+        }))),
+    }
+}
@@ -0,0 +1,225 @@
+use sqlx::{Executor, Sqlite, SqlitePool};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::blocks;
+use crate::models::language::Language;
+use crate::models::translation::PublicPage;
+use crate::models::Page;
+
+/// Longest tag the `languages.code` column holds.
+const MAX_TAG_LEN: usize = 35;
+
+#[derive(Debug)]
+pub enum LanguageError {
+    /// The tag is malformed, unregistered or disabled.
+    Invalid(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for LanguageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LanguageError::Invalid(message) => write!(f, "{}", message),
+            LanguageError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LanguageError {}
+
+impl From<sqlx::Error> for LanguageError {
+    fn from(e: sqlx::Error) -> Self {
+        LanguageError::Database(e)
+    }
+}
+
+/// Canonical casing of a well-formed BCP 47 tag (`zh-hant` becomes `zh-Hant`,
+/// `my_mm` becomes `my-MM`), or `None` if `tag` is not one. Only the syntax is
+/// checked; whether the subtags exist is up to the registry.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+        return None;
+    }
+
+    let mut subtags = Vec::new();
+    // Set once a singleton starts an extension or private-use sequence,
+    // whose subtags are kept lowercase.
+    let mut in_extension = false;
+    for (index, subtag) in tag.split(['-', '_']).enumerate() {
+        if subtag.is_empty()
+            || subtag.len() > 8
+            || !subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return None;
+        }
+
+        let is_alpha = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        let is_digit = subtag.chars().all(|c| c.is_ascii_digit());
+        let normalized = if index == 0 {
+            if !is_alpha || subtag.len() < 2 {
+                return None;
+            }
+            subtag.to_ascii_lowercase()
+        } else if in_extension {
+            subtag.to_ascii_lowercase()
+        } else if subtag.len() == 1 {
+            in_extension = true;
+            subtag.to_ascii_lowercase()
+        } else if subtag.len() == 4 && is_alpha {
+            let lower = subtag.to_ascii_lowercase();
+            lower[..1].to_ascii_uppercase() + &lower[1..]
+        } else if (subtag.len() == 2 && is_alpha) || (subtag.len() == 3 && is_digit) {
+            subtag.to_ascii_uppercase()
+        } else {
+            subtag.to_ascii_lowercase()
+        };
+        subtags.push(normalized);
+    }
+
+    if in_extension && subtags.last().is_some_and(|subtag| subtag.len() == 1) {
+        return None;
+    }
+    Some(subtags.join("-"))
+}
+
+pub async fn fetch<'e, E>(executor: E, code: &str) -> Result<Option<Language>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Language>("SELECT * FROM languages WHERE code = ?")
+        .bind(code)
+        .fetch_optional(executor)
+        .await
+}
+
+/// Checks that `tag` names a registered, enabled language and returns its
+/// canonical code, the form pages store.
+pub async fn validate<'e, E>(executor: E, tag: &str) -> Result<String, LanguageError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let Some(code) = normalize_tag(tag) else {
+        return Err(LanguageError::Invalid(format!(
+            "'{}' is not a valid BCP 47 language tag",
+            tag
+        )));
+    };
+
+    match fetch(executor, &code).await? {
+        Some(language) if language.enabled => Ok(code),
+        Some(_) => Err(LanguageError::Invalid(format!(
+            "Language '{}' is disabled",
+            code
+        ))),
+        None => Err(LanguageError::Invalid(format!(
+            "Language '{}' is not registered",
+            code
+        ))),
+    }
+}
+
+pub async fn default_code<'e, E>(executor: E) -> Result<Option<String>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar("SELECT code FROM languages WHERE is_default = 1")
+        .fetch_optional(executor)
+        .await
+}
+
+/// Enabled languages to try for `tag`, in order: the language itself, its
+/// fallback chain, then the default language.
+pub async fn resolution_chain(pool: &SqlitePool, tag: &str) -> Result<Vec<String>, sqlx::Error> {
+    let code = normalize_tag(tag).unwrap_or_else(|| tag.to_string());
+    let languages = sqlx::query_as::<_, Language>("SELECT * FROM languages WHERE enabled = 1")
+        .fetch_all(pool)
+        .await?;
+
+    let mut candidates = vec![code.clone()];
+    if let Some(language) = languages.iter().find(|language| language.code == code) {
+        candidates.extend(language.fallback_codes().map(str::to_string));
+    }
+    if let Some(default) = languages.iter().find(|language| language.is_default) {
+        candidates.push(default.code.clone());
+    }
+
+    let mut chain: Vec<String> = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let enabled = languages.iter().any(|language| language.code == candidate);
+        if enabled && !chain.contains(&candidate) {
+            chain.push(candidate);
+        }
+    }
+    Ok(chain)
+}
+
+/// Visible pages of a `page_name` slot, optionally one section, with their
+/// contents, for `lang` or the default language when `None`. The language is
+/// resolved through [`resolution_chain`]: a page whose translation group has
+/// no visible page in an earlier language of the chain is served from the
+/// first later one that has it. If the requested language has no visible
+/// pages in the slot at all, the whole slot falls back.
+pub async fn resolve_slot(
+    pool: &SqlitePool,
+    page_name: &str,
+    section_name: Option<&str>,
+    lang: Option<&str>,
+) -> Result<Vec<PublicPage>, sqlx::Error> {
+    let lang = match lang {
+        Some(lang) => lang.to_string(),
+        None => default_code(pool).await?.unwrap_or_default(),
+    };
+    let chain = resolution_chain(pool, &lang).await?;
+
+    let slot = sqlx::query_as::<_, Page>(
+        "SELECT * FROM pages WHERE visible = 1 AND page_name = ?
+         AND (? IS NULL OR section_name = ?)",
+    )
+    .bind(page_name)
+    .bind(section_name)
+    .bind(section_name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_lang: BTreeMap<String, Vec<Page>> = BTreeMap::new();
+    for page in slot {
+        by_lang.entry(page.lang.clone()).or_default().push(page);
+    }
+
+    let mut pages: Vec<(Page, bool)> = Vec::new();
+    let mut covered_groups = BTreeSet::new();
+    for (position, code) in chain.iter().enumerate() {
+        let Some(candidates) = by_lang.remove(code) else {
+            continue;
+        };
+        let whole_slot = pages.is_empty();
+        for page in candidates {
+            let uncovered = page
+                .translation_group
+                .as_ref()
+                .is_some_and(|group| !covered_groups.contains(group));
+            if whole_slot || uncovered {
+                pages.push((page, position > 0));
+            }
+        }
+        covered_groups.extend(
+            pages
+                .iter()
+                .filter_map(|(page, _)| page.translation_group.clone()),
+        );
+    }
+    pages.sort_by_key(|(page, _)| (page.display_order, page.id));
+
+    let mut public_pages = Vec::with_capacity(pages.len());
+    for (page, fallback) in pages {
+        let contents = blocks::page_contents(pool, page.id).await?;
+        public_pages.push(PublicPage {
+            page,
+            contents,
+            fallback,
+        });
+    }
+    Ok(public_pages)
+}
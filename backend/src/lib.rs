@@ -0,0 +1,32 @@
+//! Shared by the API server (`main.rs`) and the `ccs-admin` CLI.
+
+pub mod audit;
+pub mod auth;
+pub mod backups;
+pub mod blocks;
+pub mod bundle;
+pub mod change_feed;
+pub mod changes;
+pub mod config;
+pub mod content_types;
+pub mod contents;
+pub mod db;
+pub mod etag;
+pub mod feeds;
+pub mod galleries;
+pub mod handlers;
+pub mod languages;
+pub mod menus;
+pub mod merge_patch;
+pub mod page_tree;
+pub mod pages;
+pub mod previews;
+pub mod redirects;
+pub mod relations;
+pub mod render;
+pub mod site;
+pub mod similarity;
+pub mod taxonomies;
+pub mod models;
+pub mod rich_text;
+pub mod webhooks;
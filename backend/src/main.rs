@@ -1,16 +1,16 @@
-mod auth;
-mod config;
-mod db;
-mod handlers;
-mod models;
-
 use actix_cors::Cors;
-use actix_web::{middleware::Logger, web, App, HttpServer};
+use actix_web::{http::header, middleware::Logger, web, App, HttpServer};
 use dotenv::dotenv;
 use std::env;
+use std::time::Duration;
+
+use backend::auth::middleware::AuthMiddleware;
+use backend::config::{AppConfig, BackupConfig, SiteConfig};
+use backend::render::Renderer;
+use backend::{backups, db, handlers, webhooks};
 
-use auth::middleware::AuthMiddleware;
-use config::AppConfig;
+/// Upper limit on an uploaded site bundle, images included.
+const BUNDLE_MAX_SIZE: usize = 512 * 1024 * 1024;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -26,6 +26,23 @@ async fn main() -> std::io::Result<()> {
             .parse()
             .expect("JWT_EXPIRATION must be a number"),
         upload_dir: env::var("UPLOAD_DIR").unwrap_or_else(|_| "../data/images".to_string()),
+        webhook_poll_interval: env::var("WEBHOOK_POLL_INTERVAL")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .expect("WEBHOOK_POLL_INTERVAL must be a number"),
+        webhook_max_attempts: env::var("WEBHOOK_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse()
+            .expect("WEBHOOK_MAX_ATTEMPTS must be a number"),
+        webhook_retention_days: env::var("WEBHOOK_RETENTION_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .expect("WEBHOOK_RETENTION_DAYS must be a number"),
+        backup: BackupConfig::from_env(),
+        public_base_url: env::var("PUBLIC_BASE_URL").unwrap_or_default(),
+        feed_author: env::var("FEED_AUTHOR").unwrap_or_default(),
+        template_dir: env::var("TEMPLATE_DIR").ok(),
+        site: SiteConfig::from_env(),
     };
 
     let server_host = env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
@@ -36,11 +53,29 @@ async fn main() -> std::io::Result<()> {
 
     let pool = db::init_db().await.expect("Failed to initialize database");
 
+    tokio::spawn(webhooks::run_worker(
+        pool.clone(),
+        Duration::from_secs(config.webhook_poll_interval),
+        config.webhook_max_attempts,
+        config.webhook_retention_days,
+    ));
+
+    if config.backup.interval_hours > 0 {
+        tokio::spawn(backups::run_scheduler(
+            pool.clone(),
+            config.backup.clone(),
+            config.upload_dir.clone(),
+        ));
+    }
+
+    let renderer = web::Data::new(Renderer::new(config.template_dir.clone().map(Into::into)));
+
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
             .allow_any_header()
+            .expose_headers([header::ETAG])
             .max_age(3600);
 
         App::new()
@@ -48,6 +83,7 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .app_data(web::Data::new(pool.clone()))
             .app_data(web::Data::new(config.clone()))
+            .app_data(renderer.clone())
             .service(
                 web::scope("/api")
                     .service(
@@ -64,6 +100,7 @@ async fn main() -> std::io::Result<()> {
                             .route("/me", web::get().to(handlers::agent::get_current_agent))
                             .route("/{id}", web::get().to(handlers::agent::get_agent))
                             .route("/{id}", web::put().to(handlers::agent::update_agent_status))
+                            .route("/{id}", web::patch().to(handlers::agent::patch_agent))
                             .route("/{id}", web::delete().to(handlers::agent::delete_agent)),
                     )
                     .service(
@@ -73,9 +110,190 @@ async fn main() -> std::io::Result<()> {
                             })
                             .route("", web::post().to(handlers::page::create_page))
                             .route("", web::get().to(handlers::page::get_pages))
+                            .route("/reorder", web::post().to(handlers::page::reorder_pages))
+                            .route("/tree", web::get().to(handlers::page::get_page_tree))
                             .route("/{id}", web::get().to(handlers::page::get_page))
                             .route("/{id}", web::put().to(handlers::page::update_page))
-                            .route("/{id}", web::delete().to(handlers::page::delete_page)),
+                            .route("/{id}", web::patch().to(handlers::page::patch_page))
+                            .route("/{id}", web::delete().to(handlers::page::delete_page))
+                            .route("/{id}/tree", web::get().to(handlers::page::get_subtree))
+                            .route("/{id}/move", web::post().to(handlers::page::move_page))
+                            .route(
+                                "/{id}/translations",
+                                web::post().to(handlers::translation::create_translation),
+                            )
+                            .route(
+                                "/{id}/translations",
+                                web::get().to(handlers::translation::get_translations),
+                            )
+                            .route(
+                                "/{id}/terms",
+                                web::get().to(handlers::taxonomy::get_page_terms),
+                            )
+                            .route(
+                                "/{id}/terms",
+                                web::put().to(handlers::taxonomy::set_page_terms),
+                            )
+                            .route(
+                                "/{id}/blocks",
+                                web::get().to(handlers::block::get_page_blocks),
+                            )
+                            .route(
+                                "/{id}/blocks/{block_id}",
+                                web::put().to(handlers::block::place_block),
+                            )
+                            .route(
+                                "/{id}/blocks/{block_id}",
+                                web::delete().to(handlers::block::remove_block),
+                            )
+                            .route(
+                                "/{id}/relations",
+                                web::post().to(handlers::relation::create_page_relation),
+                            )
+                            .route(
+                                "/{id}/relations/{relation_id}",
+                                web::delete().to(handlers::relation::delete_page_relation),
+                            )
+                            .route(
+                                "/{id}/related",
+                                web::get().to(handlers::relation::get_page_related),
+                            )
+                            .route(
+                                "/{id}/previews",
+                                web::post().to(handlers::preview::create_preview),
+                            )
+                            .route(
+                                "/{id}/previews",
+                                web::get().to(handlers::preview::get_previews),
+                            )
+                            .route(
+                                "/{id}/previews/{preview_id}",
+                                web::delete().to(handlers::preview::revoke_preview),
+                            ),
+                    )
+                    .service(
+                        web::scope("/languages")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::language::create_language))
+                            .route("", web::get().to(handlers::language::get_languages))
+                            .route("/{code}", web::get().to(handlers::language::get_language))
+                            .route("/{code}", web::put().to(handlers::language::update_language))
+                            .route(
+                                "/{code}",
+                                web::delete().to(handlers::language::delete_language),
+                            ),
+                    )
+                    .service(
+                        web::scope("/menus")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::menu::create_menu))
+                            .route("", web::get().to(handlers::menu::get_menus))
+                            .route("/{id}", web::get().to(handlers::menu::get_menu))
+                            .route("/{id}", web::put().to(handlers::menu::update_menu))
+                            .route("/{id}", web::delete().to(handlers::menu::delete_menu))
+                            .route(
+                                "/{id}/items",
+                                web::post().to(handlers::menu::create_menu_item),
+                            )
+                            .route(
+                                "/{id}/items/{item_id}",
+                                web::put().to(handlers::menu::update_menu_item),
+                            )
+                            .route(
+                                "/{id}/items/{item_id}",
+                                web::delete().to(handlers::menu::delete_menu_item),
+                            )
+                            .route(
+                                "/{id}/items/{item_id}/move",
+                                web::post().to(handlers::menu::move_menu_item),
+                            ),
+                    )
+                    .service(
+                        web::scope("/redirects")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::redirect::create_redirect))
+                            .route("", web::get().to(handlers::redirect::get_redirects))
+                            .route("/{id}", web::get().to(handlers::redirect::get_redirect))
+                            .route("/{id}", web::put().to(handlers::redirect::update_redirect))
+                            .route(
+                                "/{id}",
+                                web::delete().to(handlers::redirect::delete_redirect),
+                            ),
+                    )
+                    .service(
+                        web::scope("/taxonomies")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::taxonomy::create_taxonomy))
+                            .route("", web::get().to(handlers::taxonomy::get_taxonomies))
+                            .route("/{id}", web::get().to(handlers::taxonomy::get_taxonomy))
+                            .route("/{id}", web::put().to(handlers::taxonomy::update_taxonomy))
+                            .route(
+                                "/{id}",
+                                web::delete().to(handlers::taxonomy::delete_taxonomy),
+                            )
+                            .route(
+                                "/{id}/terms",
+                                web::post().to(handlers::taxonomy::create_term),
+                            )
+                            .route(
+                                "/{id}/terms/{term_id}",
+                                web::put().to(handlers::taxonomy::update_term),
+                            )
+                            .route(
+                                "/{id}/terms/{term_id}",
+                                web::delete().to(handlers::taxonomy::delete_term),
+                            )
+                            .route(
+                                "/{id}/terms/{term_id}/move",
+                                web::post().to(handlers::taxonomy::move_term),
+                            ),
+                    )
+                    .service(
+                        web::scope("/public")
+                            .route(
+                                "/pages",
+                                web::get().to(handlers::translation::get_public_pages),
+                            )
+                            .route(
+                                "/languages",
+                                web::get().to(handlers::language::get_public_languages),
+                            )
+                            .route(
+                                "/menus/{name}",
+                                web::get().to(handlers::menu::get_public_menu),
+                            )
+                            .route(
+                                "/redirects/lookup",
+                                web::get().to(handlers::redirect::lookup_redirect),
+                            )
+                            .route(
+                                "/render/{page_name}",
+                                web::get().to(handlers::render::render_page),
+                            )
+                            .route(
+                                "/preview",
+                                web::get().to(handlers::preview::get_public_preview),
+                            )
+                            .route(
+                                "/preview/render",
+                                web::get().to(handlers::preview::render_public_preview),
+                            )
+                            .route(
+                                "/sitemap.xml",
+                                web::get().to(handlers::feed::get_sitemap),
+                            )
+                            .route(
+                                "/feeds/{page_name}/{format}",
+                                web::get().to(handlers::feed::get_feed),
+                            ),
                     )
                     .service(
                         web::scope("/contents")
@@ -86,12 +304,170 @@ async fn main() -> std::io::Result<()> {
                             .route("", web::get().to(handlers::content::get_contents))
                             .route("/{id}", web::get().to(handlers::content::get_content))
                             .route("/{id}", web::put().to(handlers::content::update_content))
+                            .route("/{id}", web::patch().to(handlers::content::patch_content))
                             .route("/{id}", web::delete().to(handlers::content::delete_content))
                             .route(
                                 "/ref/{ref_id}",
                                 web::get().to(handlers::content::get_contents_by_ref),
+                            )
+                            .route(
+                                "/ref/{ref_id}/reorder",
+                                web::post().to(handlers::content::reorder_contents),
+                            )
+                            .route(
+                                "/{id}/terms",
+                                web::get().to(handlers::taxonomy::get_content_terms),
+                            )
+                            .route(
+                                "/{id}/terms",
+                                web::put().to(handlers::taxonomy::set_content_terms),
+                            )
+                            .route(
+                                "/{id}/images",
+                                web::get().to(handlers::gallery::get_content_images),
+                            )
+                            .route(
+                                "/{id}/images",
+                                web::post().to(handlers::gallery::add_content_image),
+                            )
+                            .route(
+                                "/{id}/images/reorder",
+                                web::post().to(handlers::gallery::reorder_content_images),
+                            )
+                            .route(
+                                "/{id}/images/{image_id}",
+                                web::put().to(handlers::gallery::update_content_image),
+                            )
+                            .route(
+                                "/{id}/images/{image_id}",
+                                web::delete().to(handlers::gallery::delete_content_image),
+                            )
+                            .route(
+                                "/{id}/relations",
+                                web::post().to(handlers::relation::create_content_relation),
+                            )
+                            .route(
+                                "/{id}/relations/{relation_id}",
+                                web::delete().to(handlers::relation::delete_content_relation),
+                            )
+                            .route(
+                                "/{id}/related",
+                                web::get().to(handlers::relation::get_content_related),
+                            )
+                            .route(
+                                "/{id}/similar",
+                                web::get().to(handlers::relation::get_similar_contents),
+                            ),
+                    )
+                    .service(
+                        web::scope("/blocks")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::block::create_block))
+                            .route("", web::get().to(handlers::block::get_blocks))
+                            .route("/{id}", web::get().to(handlers::block::get_block))
+                            .route("/{id}", web::put().to(handlers::block::update_block))
+                            .route("/{id}", web::delete().to(handlers::block::delete_block))
+                            .route(
+                                "/{id}/usage",
+                                web::get().to(handlers::block::get_block_usage),
                             ),
                     )
+                    .service(
+                        web::scope("/content-types")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::content_type::create_content_type))
+                            .route("", web::get().to(handlers::content_type::get_content_types))
+                            .route("/{id}", web::get().to(handlers::content_type::get_content_type))
+                            .route("/{id}", web::put().to(handlers::content_type::update_content_type))
+                            .route(
+                                "/{id}",
+                                web::delete().to(handlers::content_type::delete_content_type),
+                            ),
+                    )
+                    .service(
+                        web::scope("/batch")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::batch::run_batch)),
+                    )
+                    .service(
+                        web::scope("/bundle")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .app_data(web::PayloadConfig::new(BUNDLE_MAX_SIZE))
+                            .route("/export", web::get().to(handlers::bundle::export_bundle))
+                            .route("/import", web::post().to(handlers::bundle::import_bundle)),
+                    )
+                    .service(
+                        web::scope("/backups")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::backup::create_backup))
+                            .route("", web::get().to(handlers::backup::get_backups))
+                            .route("/{name}", web::get().to(handlers::backup::get_backup))
+                            .route("/{name}", web::delete().to(handlers::backup::delete_backup))
+                            .route(
+                                "/{name}/verify",
+                                web::post().to(handlers::backup::verify_backup),
+                            ),
+                    )
+                    .service(
+                        web::scope("/site")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("/build", web::post().to(handlers::site::build_site)),
+                    )
+                    .service(
+                        web::scope("/audit")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::get().to(handlers::audit::get_audit_log))
+                            .route("/export", web::get().to(handlers::audit::export_audit_log)),
+                    )
+                    // Checks its own token, which may come in the URL.
+                    .route(
+                        "/changes/stream",
+                        web::get().to(handlers::change::stream_changes),
+                    )
+                    .service(
+                        web::scope("/changes")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::get().to(handlers::change::get_changes))
+                            .route(
+                                "/stream-token",
+                                web::post().to(handlers::change::create_stream_token),
+                            ),
+                    )
+                    .service(
+                        web::scope("/webhooks")
+                            .wrap(AuthMiddleware {
+                                jwt_secret: config.jwt_secret.clone(),
+                            })
+                            .route("", web::post().to(handlers::webhook::create_webhook))
+                            .route("", web::get().to(handlers::webhook::get_webhooks))
+                            .route(
+                                "/deliveries",
+                                web::get().to(handlers::webhook::get_deliveries),
+                            )
+                            .route(
+                                "/deliveries/{id}/redeliver",
+                                web::post().to(handlers::webhook::redeliver),
+                            )
+                            .route("/{id}", web::get().to(handlers::webhook::get_webhook))
+                            .route("/{id}", web::put().to(handlers::webhook::update_webhook))
+                            .route("/{id}", web::delete().to(handlers::webhook::delete_webhook)),
+                    )
                     .service(
                         web::scope("/images")
                             .wrap(AuthMiddleware {
@@ -114,7 +490,8 @@ async fn main() -> std::io::Result<()> {
                             .route(
                                 "/contents",
                                 web::get().to(handlers::search::search_contents),
-                            ),
+                            )
+                            .route("/facets", web::get().to(handlers::search::search_facets)),
                     )
                     .route(
                         "/pre-view/images/{filename}",
@@ -0,0 +1,287 @@
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::languages;
+use crate::models::menu::{Menu, MenuItem, MenuItemNode, ResolvedMenu, ResolvedMenuItem};
+use crate::models::Page;
+
+#[derive(Debug)]
+pub enum MenuError {
+    /// Malformed item or unknown parent.
+    Invalid(String),
+    /// A move would put an item below itself.
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for MenuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MenuError::Invalid(message) | MenuError::Conflict(message) => {
+                write!(f, "{}", message)
+            }
+            MenuError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MenuError {}
+
+impl From<sqlx::Error> for MenuError {
+    fn from(e: sqlx::Error) -> Self {
+        MenuError::Database(e)
+    }
+}
+
+/// Labels of every item in `menu_id`, by item id and then language code.
+pub async fn labels(
+    conn: &mut SqliteConnection,
+    menu_id: i64,
+) -> Result<HashMap<i64, BTreeMap<String, String>>, sqlx::Error> {
+    let rows: Vec<(i64, String, String)> = sqlx::query_as(
+        "SELECT l.item_id, l.lang, l.label FROM menu_item_labels l
+         JOIN menu_items i ON i.id = l.item_id WHERE i.menu_id = ?",
+    )
+    .bind(menu_id)
+    .fetch_all(conn)
+    .await?;
+
+    let mut labels: HashMap<i64, BTreeMap<String, String>> = HashMap::new();
+    for (item_id, lang, label) in rows {
+        labels.entry(item_id).or_default().insert(lang, label);
+    }
+    Ok(labels)
+}
+
+/// Replaces the labels of `item_id`.
+pub async fn set_labels(
+    conn: &mut SqliteConnection,
+    item_id: i64,
+    labels: &BTreeMap<String, String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM menu_item_labels WHERE item_id = ?")
+        .bind(item_id)
+        .execute(&mut *conn)
+        .await?;
+    for (lang, label) in labels {
+        sqlx::query("INSERT INTO menu_item_labels (item_id, lang, label) VALUES (?, ?, ?)")
+            .bind(item_id)
+            .bind(lang)
+            .bind(label)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn items(conn: &mut SqliteConnection, menu_id: i64) -> Result<Vec<MenuItem>, sqlx::Error> {
+    sqlx::query_as::<_, MenuItem>(
+        "SELECT * FROM menu_items WHERE menu_id = ? ORDER BY display_order, id",
+    )
+    .bind(menu_id)
+    .fetch_all(conn)
+    .await
+}
+
+fn by_parent(items: Vec<MenuItem>) -> HashMap<Option<i64>, Vec<MenuItem>> {
+    let mut children: HashMap<Option<i64>, Vec<MenuItem>> = HashMap::new();
+    for item in items {
+        children.entry(item.parent_id).or_default().push(item);
+    }
+    children
+}
+
+/// Every item of `menu_id` as a tree, hidden items included.
+pub async fn tree(
+    conn: &mut SqliteConnection,
+    menu_id: i64,
+) -> Result<Vec<MenuItemNode>, sqlx::Error> {
+    fn build(
+        parent_id: Option<i64>,
+        children: &mut HashMap<Option<i64>, Vec<MenuItem>>,
+        labels: &mut HashMap<i64, BTreeMap<String, String>>,
+    ) -> Vec<MenuItemNode> {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| MenuItemNode {
+                labels: labels.remove(&item.id).unwrap_or_default(),
+                children: build(Some(item.id), children, labels),
+                item,
+            })
+            .collect()
+    }
+
+    let mut labels = labels(&mut *conn, menu_id).await?;
+    let mut children = by_parent(items(conn, menu_id).await?);
+    Ok(build(None, &mut children, &mut labels))
+}
+
+/// Checks that `parent_id` is an item of `menu_id` and, when `item_id` is
+/// being moved under it, that the parent is neither the item itself nor one
+/// of its descendants.
+pub async fn check_parent(
+    conn: &mut SqliteConnection,
+    menu_id: i64,
+    parent_id: Option<i64>,
+    item_id: Option<i64>,
+) -> Result<(), MenuError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+
+    let parent_menu: Option<i64> =
+        sqlx::query_scalar("SELECT menu_id FROM menu_items WHERE id = ?")
+            .bind(parent_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    if parent_menu != Some(menu_id) {
+        return Err(MenuError::Invalid(format!(
+            "Parent item {} not found in menu {}",
+            parent_id, menu_id
+        )));
+    }
+
+    if let Some(item_id) = item_id {
+        let cycle: bool = sqlx::query_scalar(
+            "WITH RECURSIVE ancestors(id) AS (
+                 SELECT ?
+                 UNION
+                 SELECT i.parent_id FROM menu_items i JOIN ancestors a ON i.id = a.id
+                 WHERE i.parent_id IS NOT NULL
+             )
+             SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?)",
+        )
+        .bind(parent_id)
+        .bind(item_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if cycle {
+            return Err(MenuError::Conflict(format!(
+                "Item {} cannot be moved under itself or one of its descendants",
+                item_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The visible page `page_id` stands for in a language: the page itself or
+/// one of its translations, whichever comes first in `chain`.
+async fn resolve_page(
+    pool: &SqlitePool,
+    page_id: i64,
+    chain: &[String],
+) -> Result<Option<Page>, sqlx::Error> {
+    let mut candidates = sqlx::query_as::<_, Page>(
+        "SELECT * FROM pages WHERE visible = 1 AND (id = ? OR translation_group =
+             (SELECT translation_group FROM pages WHERE id = ?))",
+    )
+    .bind(page_id)
+    .bind(page_id)
+    .fetch_all(pool)
+    .await?;
+
+    let position = chain
+        .iter()
+        .find_map(|code| candidates.iter().position(|page| page.lang == *code));
+    Ok(position.map(|position| candidates.swap_remove(position)))
+}
+
+/// `menu` as shown in `lang`, or the default language when `None`. Items
+/// that are hidden, limited to other languages, or link to a page with no
+/// visible version in the language's resolution chain are left out with
+/// everything below them. Labels fall back along the same chain and then
+/// to the item's own `label`.
+pub async fn resolve(
+    pool: &SqlitePool,
+    menu: Menu,
+    lang: Option<&str>,
+) -> Result<ResolvedMenu, sqlx::Error> {
+    let lang = match lang {
+        Some(lang) => languages::normalize_tag(lang).unwrap_or_else(|| lang.to_string()),
+        None => languages::default_code(pool).await?.unwrap_or_default(),
+    };
+    let chain = languages::resolution_chain(pool, &lang).await?;
+
+    let mut conn = pool.acquire().await?;
+    let labels = labels(&mut conn, menu.id).await?;
+    let items = items(&mut conn, menu.id).await?;
+    drop(conn);
+
+    let mut resolved: HashMap<i64, ResolvedMenuItem> = HashMap::new();
+    let mut order: Vec<(Option<i64>, i64)> = Vec::new();
+    for item in items {
+        let shown =
+            item.visible && (item.langs.is_empty() || item.lang_codes().any(|code| code == lang));
+        if !shown {
+            continue;
+        }
+
+        let (href, page) = match item.link_type.as_str() {
+            "page" => {
+                let page = match item.page_id {
+                    Some(page_id) => resolve_page(pool, page_id, &chain).await?,
+                    None => None,
+                };
+                let Some(page) = page else {
+                    continue;
+                };
+                let href = page.path.clone().map(|path| match &item.anchor {
+                    Some(anchor) => format!("{}#{}", path, anchor),
+                    None => path,
+                });
+                (href, Some(page))
+            }
+            "anchor" => (item.anchor.as_ref().map(|a| format!("#{}", a)), None),
+            _ => (item.url.clone(), None),
+        };
+
+        let item_labels = labels.get(&item.id);
+        let label = chain
+            .iter()
+            .find_map(|code| item_labels.and_then(|labels| labels.get(code)))
+            .cloned()
+            .unwrap_or_else(|| item.label.clone());
+
+        order.push((item.parent_id, item.id));
+        resolved.insert(
+            item.id,
+            ResolvedMenuItem {
+                id: item.id,
+                label,
+                link_type: item.link_type,
+                href,
+                page_id: page.as_ref().map(|page| page.id),
+                page_name: page.as_ref().map(|page| page.page_name.clone()),
+                page_lang: page.map(|page| page.lang),
+                children: Vec::new(),
+            },
+        );
+    }
+
+    fn build(
+        parent_id: Option<i64>,
+        order: &[(Option<i64>, i64)],
+        resolved: &mut HashMap<i64, ResolvedMenuItem>,
+    ) -> Vec<ResolvedMenuItem> {
+        order
+            .iter()
+            .filter(|(parent, _)| *parent == parent_id)
+            .filter_map(|(_, id)| {
+                let mut item = resolved.remove(id)?;
+                item.children = build(Some(*id), order, resolved);
+                Some(item)
+            })
+            .collect()
+    }
+
+    Ok(ResolvedMenu {
+        items: build(None, &order, &mut resolved),
+        name: menu.name,
+        location: menu.location,
+        lang,
+    })
+}
@@ -0,0 +1,42 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Applies an RFC 7396 JSON Merge Patch to `target`: absent members are left
+/// unchanged, `null` removes a member and objects are merged recursively.
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge(target.entry(key.as_str()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Merges `patch` into the serialized `current` fields and parses the result
+/// back, so nullable fields can be cleared and required ones cannot. The error
+/// describes why the patch was rejected.
+pub fn apply<T>(current: &T, patch: &Value) -> Result<T, String>
+where
+    T: Serialize + DeserializeOwned,
+{
+    if !patch.is_object() {
+        return Err("Merge patch must be a JSON object".to_string());
+    }
+
+    let mut document = serde_json::to_value(current).map_err(|e| e.to_string())?;
+    merge(&mut document, patch);
+
+    serde_json::from_value(document).map_err(|e| format!("Invalid merge patch: {}", e))
+}
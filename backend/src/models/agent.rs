@@ -37,3 +37,20 @@ pub struct AgentInfo {
     pub agent_number: String,
     pub is_active: bool,
 }
+
+/// Writable agent fields; the document a merge patch is applied to.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentFields {
+    pub agent_number: String,
+    pub is_active: bool,
+}
+
+impl From<&Agent> for AgentFields {
+    fn from(agent: &Agent) -> Self {
+        AgentFields {
+            agent_number: agent.agent_number.clone(),
+            is_active: agent.is_active,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub agent_id: Option<i64>,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<String>,
+    pub before_state: Option<String>,
+    pub after_state: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub agent_id: Option<i64>,
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    /// Inclusive lower bound on `created_at`, e.g. `2024-01-31` or `2024-01-31 08:00:00`.
+    pub from: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
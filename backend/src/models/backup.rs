@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Written as `manifest.json` next to each snapshot's `database.db`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub name: String,
+    pub created_at: String,
+    /// Latest applied schema migration when the snapshot was taken.
+    pub schema_version: Option<i64>,
+    pub database: BackupFile,
+    /// Image files in the upload directory at snapshot time. The files
+    /// themselves are not copied.
+    pub images: Vec<BackupFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupSummary {
+    pub name: String,
+    pub created_at: String,
+    pub schema_version: Option<i64>,
+    pub database_size: u64,
+    pub image_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupVerification {
+    pub name: String,
+    /// The database file matches its manifest checksum and passes
+    /// `PRAGMA integrity_check`.
+    pub valid: bool,
+    pub integrity: String,
+    /// Manifest images no longer in the upload directory.
+    pub missing_images: Vec<String>,
+    /// Manifest images whose contents have changed since the snapshot.
+    pub changed_images: Vec<String>,
+}
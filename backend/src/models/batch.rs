@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use super::PageCreate;
+
+#[derive(Debug, Deserialize)]
+pub struct BatchRequest {
+    pub operations: Vec<BatchOperation>,
+}
+
+/// One operation in a batch. Updates take a merge patch like the PATCH
+/// endpoints; updates and deletes carry the version an `If-Match` would.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    CreatePage {
+        /// Name later operations use in `page_ref` to point at this page.
+        #[serde(rename = "ref")]
+        client_ref: Option<String>,
+        data: PageCreate,
+    },
+    UpdatePage {
+        id: i64,
+        version: i64,
+        data: serde_json::Value,
+    },
+    DeletePage {
+        id: i64,
+        version: i64,
+    },
+    CreateContent {
+        data: BatchContentCreate,
+    },
+    UpdateContent {
+        id: i64,
+        version: i64,
+        data: serde_json::Value,
+    },
+    DeleteContent {
+        id: i64,
+        version: i64,
+    },
+}
+
+/// `ContentCreate` whose page is either an existing `ref_id` or the `ref` of
+/// a page created earlier in the same batch.
+#[derive(Debug, Deserialize)]
+pub struct BatchContentCreate {
+    pub ref_id: Option<i64>,
+    pub page_ref: Option<String>,
+    pub short_desc: Option<String>,
+    pub long_desc: Option<String>,
+    pub format: Option<String>,
+    pub image_path: Option<String>,
+    pub title: Option<String>,
+    pub display_order: Option<i32>,
+    pub type_id: Option<i64>,
+    pub fields: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
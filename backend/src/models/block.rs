@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Block {
+    pub id: i64,
+    /// Handle such as `contact-card`.
+    pub name: String,
+    pub short_desc: Option<String>,
+    pub long_desc: Option<String>,
+    /// How `long_desc` is written: `plain`, `markdown` or `html`.
+    pub format: String,
+    /// `long_desc` rendered to sanitized HTML.
+    pub long_desc_html: Option<String>,
+    pub image_path: Option<String>,
+    pub title: Option<String>,
+    pub version: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockCreate {
+    pub name: String,
+    pub short_desc: Option<String>,
+    pub long_desc: Option<String>,
+    /// Defaults to `plain`.
+    pub format: Option<String>,
+    pub image_path: Option<String>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockUpdate {
+    pub name: Option<String>,
+    pub short_desc: Option<String>,
+    pub long_desc: Option<String>,
+    pub format: Option<String>,
+    pub image_path: Option<String>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct BlockPlacement {
+    pub id: i64,
+    pub block_id: i64,
+    pub page_id: i64,
+    /// Position among the page's contents.
+    pub display_order: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Places a block on a page, or moves it if it is already there.
+#[derive(Debug, Deserialize)]
+pub struct PlacementSave {
+    pub display_order: Option<i32>,
+}
+
+/// A page a block is placed on.
+#[derive(Debug, Serialize, FromRow)]
+pub struct BlockUsage {
+    pub placement_id: i64,
+    pub page_id: i64,
+    pub page_name: String,
+    pub section_name: String,
+    pub lang: String,
+    pub path: Option<String>,
+    pub visible: bool,
+    pub display_order: i32,
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::content_type::FieldSpec;
+
+/// Portable copy of the site's settings, pages, contents and images. Stored
+/// gzipped.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    /// Absent from version 1 bundles.
+    #[serde(default)]
+    pub settings: BundleSettings,
+    pub pages: Vec<BundlePage>,
+    pub images: Vec<BundleImage>,
+}
+
+/// Definitions pages and contents refer to by name, imported before them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BundleSettings {
+    pub languages: Vec<BundleLanguage>,
+    pub content_types: Vec<BundleContentType>,
+    pub taxonomies: Vec<BundleTaxonomy>,
+    pub menus: Vec<BundleMenu>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleLanguage {
+    pub code: String,
+    pub name: String,
+    pub direction: String,
+    pub is_default: bool,
+    pub enabled: bool,
+    pub fallback: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleContentType {
+    pub name: String,
+    pub label: String,
+    pub fields: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleTaxonomy {
+    pub name: String,
+    pub hierarchical: bool,
+    pub terms: Vec<BundleTerm>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleTerm {
+    /// Id in the exporting database, which `parent_id` refers to.
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub slug: String,
+    pub name: String,
+    pub display_order: i32,
+    /// Names by language code.
+    pub names: BTreeMap<String, String>,
+}
+
+/// A term a page or content is tagged with.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleTermRef {
+    pub taxonomy: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleMenu {
+    pub name: String,
+    pub location: String,
+    pub items: Vec<BundleMenuItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleMenuItem {
+    /// Id in the exporting database, which `parent_id` refers to.
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub display_order: i32,
+    pub link_type: String,
+    /// Bundle id of the linked page.
+    pub page_id: Option<i64>,
+    pub url: Option<String>,
+    pub anchor: Option<String>,
+    pub label: String,
+    pub visible: bool,
+    pub langs: String,
+    /// Labels by language code.
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundlePage {
+    /// Id in the exporting database, reported back in the import's id map.
+    pub id: i64,
+    pub page_name: String,
+    pub section_name: String,
+    pub lang: String,
+    pub content_type: String,
+    pub visible: bool,
+    pub display_order: i32,
+    pub attributes: Option<String>,
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    /// Bundle id of the page this one translates.
+    #[serde(default)]
+    pub translation_of: Option<i64>,
+    /// Bundle id of the parent page.
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub slug: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terms: Vec<BundleTermRef>,
+    pub contents: Vec<BundleContent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleContent {
+    pub short_desc: Option<String>,
+    pub long_desc: Option<String>,
+    /// Bundles from before content formats hold plain text.
+    #[serde(default = "default_format")]
+    pub format: String,
+    pub image_path: Option<String>,
+    pub title: Option<String>,
+    pub display_order: i32,
+    /// Name of the content type, which must exist where the bundle is
+    /// imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terms: Vec<BundleTermRef>,
+    /// Attached images in order; their files travel in `images`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gallery: Vec<BundleGalleryImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleGalleryImage {
+    pub filename: String,
+    pub caption: Option<String>,
+    pub alt: Option<String>,
+}
+
+fn default_format() -> String {
+    crate::rich_text::DEFAULT_FORMAT.to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleImage {
+    pub filename: String,
+    /// Base64-encoded file contents.
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Keep existing pages and add the bundle's.
+    Merge,
+    /// Delete every existing page first, and the menus the bundle brings.
+    Replace,
+}
+
+/// What a merge does with a bundle page whose `page_name`, `section_name`
+/// and `lang` already exist.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    Fail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BundleImportQuery {
+    pub mode: Option<ImportMode>,
+    pub on_conflict: Option<ConflictPolicy>,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleConflict {
+    pub page_name: String,
+    pub section_name: String,
+    pub lang: String,
+    pub existing_ids: Vec<i64>,
+    pub bundle_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub mode: ImportMode,
+    pub pages_created: usize,
+    pub pages_deleted: usize,
+    pub contents_created: usize,
+    pub languages_created: usize,
+    /// Languages of imported pages that are disabled here.
+    pub languages_disabled: Vec<String>,
+    pub content_types_created: usize,
+    pub taxonomies_created: usize,
+    pub terms_created: usize,
+    pub menus_created: usize,
+    /// Pages left here whose parent was deleted, by id, with the imported
+    /// page they were moved under, or `None` at the top level.
+    pub pages_reparented: BTreeMap<i64, Option<i64>>,
+    pub images_written: usize,
+    /// Files of deleted contents that nothing refers to any more; removed
+    /// once the import commits.
+    pub images_removed: Vec<String>,
+    /// Bundle images renamed because a different file had the same name.
+    pub images_renamed: BTreeMap<String, String>,
+    pub conflicts: Vec<BundleConflict>,
+    /// Bundle page id to the id it was imported as.
+    pub page_ids: BTreeMap<i64, i64>,
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ChangeEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub page_name: Option<String>,
+    pub section_name: Option<String>,
+    pub lang: Option<String>,
+    pub payload: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeQuery {
+    pub page_name: Option<String>,
+    pub section_name: Option<String>,
+    pub lang: Option<String>,
+    /// Only return events after this id; the stream also honours `Last-Event-ID`.
+    pub since: Option<i64>,
+    pub limit: Option<i64>,
+    /// Change stream token, for clients such as `EventSource` that cannot
+    /// send an `Authorization` header.
+    pub token: Option<String>,
+}
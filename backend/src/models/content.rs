@@ -1,16 +1,54 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use super::gallery::ContentImage;
+
+/// Serializes the stored `fields` text as the JSON object it holds.
+mod fields_json {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(fields: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+        fields
+            .as_deref()
+            .and_then(|fields| serde_json::from_str::<serde_json::Value>(fields).ok())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+        Ok(Option::<serde_json::Value>::deserialize(deserializer)?.map(|fields| fields.to_string()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Content {
     pub id: i64,
     pub ref_id: i64,
     pub short_desc: Option<String>,
     pub long_desc: Option<String>,
+    /// How `long_desc` is written: `plain`, `markdown` or `html`.
+    pub format: String,
+    /// `long_desc` rendered to sanitized HTML.
+    pub long_desc_html: Option<String>,
     pub image_path: Option<String>,
     pub title: Option<String>,
+    pub display_order: i32,
+    pub version: i64,
     pub created_at: String,
     pub updated_at: String,
+    /// Content type the custom `fields` follow; `None` for plain contents.
+    pub type_id: Option<i64>,
+    /// Custom field values by field name.
+    #[serde(default, with = "fields_json")]
+    pub fields: Option<String>,
+    /// Set on a shared block placed on the page, whose `id` is then the
+    /// block's and whose `display_order` is the placement's.
+    #[sqlx(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<i64>,
+    /// Image attachments in `display_order`, where they were loaded.
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gallery: Vec<ContentImage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -18,8 +56,14 @@ pub struct ContentCreate {
     pub ref_id: i64,
     pub short_desc: Option<String>,
     pub long_desc: Option<String>,
+    /// Defaults to `plain`.
+    pub format: Option<String>,
     pub image_path: Option<String>,
     pub title: Option<String>,
+    pub display_order: Option<i32>,
+    pub type_id: Option<i64>,
+    /// Values for the custom fields of `type_id`.
+    pub fields: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +71,62 @@ pub struct ContentUpdate {
     pub ref_id: Option<i64>,
     pub short_desc: Option<String>,
     pub long_desc: Option<String>,
+    pub format: Option<String>,
+    pub image_path: Option<String>,
+    pub title: Option<String>,
+    pub display_order: Option<i32>,
+    pub type_id: Option<i64>,
+    /// Replaces every custom field value.
+    pub fields: Option<serde_json::Value>,
+}
+
+/// Writable content fields; the document a merge patch is applied to.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContentFields {
+    pub ref_id: i64,
+    pub short_desc: Option<String>,
+    pub long_desc: Option<String>,
+    pub format: String,
     pub image_path: Option<String>,
     pub title: Option<String>,
+    pub display_order: i32,
+    #[serde(default)]
+    pub type_id: Option<i64>,
+    #[serde(default)]
+    pub fields: Option<serde_json::Value>,
+}
+
+impl From<&Content> for ContentFields {
+    fn from(content: &Content) -> Self {
+        ContentFields {
+            ref_id: content.ref_id,
+            short_desc: content.short_desc.clone(),
+            long_desc: content.long_desc.clone(),
+            format: content.format.clone(),
+            image_path: content.image_path.clone(),
+            title: content.title.clone(),
+            display_order: content.display_order,
+            type_id: content.type_id,
+            fields: content
+                .fields
+                .as_deref()
+                .and_then(|fields| serde_json::from_str(fields).ok()),
+        }
+    }
+}
+
+/// New order of every content under a page, first to last.
+#[derive(Debug, Deserialize)]
+pub struct ContentReorder {
+    pub ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContentQuery {
+    /// Comma-separated term ids; only contents tagged with each of them, or
+    /// with one of its descendants, are listed.
+    pub terms: Option<String>,
+    /// Only contents of this content type.
+    pub type_id: Option<i64>,
 }
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Types a custom field can have.
+pub const FIELD_TYPES: &[&str] = &[
+    "text",
+    "rich_text",
+    "number",
+    "date",
+    "boolean",
+    "image",
+    "page",
+    "list",
+];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ContentType {
+    pub id: i64,
+    /// Handle such as `event` or `team_member`.
+    pub name: String,
+    pub label: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ContentTypeField {
+    pub id: i64,
+    pub type_id: i64,
+    /// Key of the value in a content's `fields`.
+    pub name: String,
+    pub label: String,
+    /// One of [`FIELD_TYPES`].
+    pub field_type: String,
+    /// Type of each element of a `list` field.
+    pub item_type: Option<String>,
+    pub required: bool,
+    pub display_order: i32,
+}
+
+/// A field as an admin defines it; its position in the list is its
+/// `display_order`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    /// Defaults to `name`.
+    pub label: Option<String>,
+    pub field_type: String,
+    pub item_type: Option<String>,
+    pub required: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContentTypeCreate {
+    pub name: String,
+    /// Defaults to `name`.
+    pub label: Option<String>,
+    pub fields: Vec<FieldSpec>,
+}
+
+/// `fields` replaces the whole field list.
+#[derive(Debug, Deserialize)]
+pub struct ContentTypeUpdate {
+    pub name: Option<String>,
+    pub label: Option<String>,
+    pub fields: Option<Vec<FieldSpec>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentTypeDetail {
+    #[serde(flatten)]
+    pub content_type: ContentType,
+    pub fields: Vec<ContentTypeField>,
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ContentImage {
+    pub id: i64,
+    pub content_id: i64,
+    /// Uploaded file in `UPLOAD_DIR`, removed along with the attachment.
+    pub filename: String,
+    pub caption: Option<String>,
+    pub alt: Option<String>,
+    pub display_order: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContentImageCreate {
+    pub filename: String,
+    pub caption: Option<String>,
+    pub alt: Option<String>,
+    /// Defaults to after the last attachment.
+    pub display_order: Option<i32>,
+}
+
+/// Replaces the caption and alt text; a missing one is cleared.
+#[derive(Debug, Deserialize)]
+pub struct ContentImageUpdate {
+    pub caption: Option<String>,
+    pub alt: Option<String>,
+}
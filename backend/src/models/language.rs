@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const DIRECTIONS: &[&str] = &["ltr", "rtl"];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Language {
+    /// Canonical BCP 47 tag, e.g. `en`, `my-MM` or `zh-Hant`.
+    pub code: String,
+    pub name: String,
+    /// `ltr` or `rtl`.
+    pub direction: String,
+    pub is_default: bool,
+    pub enabled: bool,
+    /// Comma-separated codes tried in order before the default language.
+    pub fallback: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Language {
+    pub fn fallback_codes(&self) -> impl Iterator<Item = &str> {
+        self.fallback.split(',').filter(|code| !code.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LanguageCreate {
+    pub code: String,
+    pub name: String,
+    pub direction: Option<String>,
+    /// Makes this the default language, replacing the current one.
+    pub is_default: Option<bool>,
+    pub enabled: Option<bool>,
+    pub fallback: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LanguageUpdate {
+    pub name: Option<String>,
+    pub direction: Option<String>,
+    pub is_default: Option<bool>,
+    pub enabled: Option<bool>,
+    pub fallback: Option<Vec<String>>,
+}
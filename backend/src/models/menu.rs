@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+
+pub const LOCATIONS: &[&str] = &["header", "footer", "sidebar"];
+
+pub const LINK_TYPES: &[&str] = &["page", "url", "anchor"];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Menu {
+    pub id: i64,
+    /// Handle the public endpoint looks the menu up by, e.g. `main`.
+    pub name: String,
+    /// `header`, `footer` or `sidebar`.
+    pub location: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MenuCreate {
+    pub name: String,
+    pub location: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MenuUpdate {
+    pub name: Option<String>,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MenuItem {
+    pub id: i64,
+    pub menu_id: i64,
+    pub parent_id: Option<i64>,
+    pub display_order: i32,
+    /// `page`, `url` or `anchor`.
+    pub link_type: String,
+    /// Target of a `page` item; cleared when the page is deleted.
+    pub page_id: Option<i64>,
+    /// Target of a `url` item.
+    pub url: Option<String>,
+    /// Fragment of an `anchor` item, or of a `page` item's link.
+    pub anchor: Option<String>,
+    /// Label for languages without one of their own.
+    pub label: String,
+    pub visible: bool,
+    /// Comma-separated codes the item is shown in; empty for all.
+    pub langs: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl MenuItem {
+    pub fn lang_codes(&self) -> impl Iterator<Item = &str> {
+        self.langs.split(',').filter(|code| !code.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MenuItemCreate {
+    pub parent_id: Option<i64>,
+    pub display_order: Option<i32>,
+    pub link_type: String,
+    pub page_id: Option<i64>,
+    pub url: Option<String>,
+    pub anchor: Option<String>,
+    pub label: String,
+    /// Labels by language code.
+    pub labels: Option<BTreeMap<String, String>>,
+    pub visible: Option<bool>,
+    pub langs: Option<Vec<String>>,
+}
+
+/// Changes an item in place. Setting `link_type` clears the targets the new
+/// type does not use; `labels` and `langs` replace the stored ones.
+#[derive(Debug, Deserialize)]
+pub struct MenuItemUpdate {
+    pub link_type: Option<String>,
+    pub page_id: Option<i64>,
+    pub url: Option<String>,
+    pub anchor: Option<String>,
+    pub label: Option<String>,
+    pub labels: Option<BTreeMap<String, String>>,
+    pub visible: Option<bool>,
+    pub langs: Option<Vec<String>>,
+}
+
+/// Moves an item under `parent_id` in the same menu, or to the top level
+/// when it is `None`.
+#[derive(Debug, Deserialize)]
+pub struct MenuItemMove {
+    pub parent_id: Option<i64>,
+    /// Position among the new siblings; keeps the current one if absent.
+    pub display_order: Option<i32>,
+}
+
+/// An item as editors see it, with every label and all children, hidden
+/// ones included.
+#[derive(Debug, Serialize)]
+pub struct MenuItemNode {
+    #[serde(flatten)]
+    pub item: MenuItem,
+    pub labels: BTreeMap<String, String>,
+    pub children: Vec<MenuItemNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MenuDetail {
+    #[serde(flatten)]
+    pub menu: Menu,
+    pub items: Vec<MenuItemNode>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MenuQuery {
+    pub lang: Option<String>,
+}
+
+/// An item as a site shows it in one language.
+#[derive(Debug, Serialize)]
+pub struct ResolvedMenuItem {
+    pub id: i64,
+    pub label: String,
+    pub link_type: String,
+    /// Where the item links to; `None` for a page without a `path`.
+    pub href: Option<String>,
+    /// The page linked to, in the language it was resolved to.
+    pub page_id: Option<i64>,
+    pub page_name: Option<String>,
+    pub page_lang: Option<String>,
+    pub children: Vec<ResolvedMenuItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedMenu {
+    pub name: String,
+    pub location: String,
+    pub lang: String,
+    pub items: Vec<ResolvedMenuItem>,
+}
@@ -1,7 +1,26 @@
 pub mod agent;
 pub mod page;
 pub mod content;
+pub mod content_type;
+pub mod gallery;
+pub mod relation;
+pub mod preview;
+pub mod block;
+pub mod batch;
+pub mod audit;
+pub mod webhook;
+pub mod change;
+pub mod language;
+pub mod menu;
+pub mod redirect;
+pub mod translation;
+pub mod bundle;
+pub mod backup;
+pub mod site;
+pub mod taxonomy;
 
-pub use agent::{Agent, AgentLogin, AgentCreate};
-pub use page::{Page, PageCreate, PageUpdate};
-pub use content::{Content, ContentCreate, ContentUpdate};
+pub use agent::{Agent, AgentLogin, AgentCreate, AgentFields};
+pub use page::{Page, PageCreate, PageUpdate, PageFields, PageReorder, PageMove, PageTreeQuery, PageNode};
+pub use content::{Content, ContentCreate, ContentUpdate, ContentFields, ContentReorder, ContentQuery};
+pub use audit::{AuditEntry, AuditQuery};
+pub use change::{ChangeEvent, ChangeQuery};
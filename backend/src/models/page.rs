@@ -11,6 +11,17 @@ pub struct Page {
     pub visible: bool,
     pub display_order: i32,
     pub attributes: Option<String>,
+    pub version: i64,
+    /// Shared by a page and its translations in other languages.
+    pub translation_group: Option<String>,
+    /// Page this translation was created from; `None` for originals.
+    pub translation_of: Option<i64>,
+    /// Parent in the page tree; `None` for top-level pages.
+    pub parent_id: Option<i64>,
+    pub slug: Option<String>,
+    /// Language and `/`-joined slugs from the root, e.g. `/en/about/team`;
+    /// `None` unless the page and all of its ancestors have a slug.
+    pub path: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -24,6 +35,8 @@ pub struct PageCreate {
     pub visible: Option<bool>,
     pub display_order: Option<i32>,
     pub attributes: Option<String>,
+    pub parent_id: Option<i64>,
+    pub slug: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,4 +48,69 @@ pub struct PageUpdate {
     pub visible: Option<bool>,
     pub display_order: Option<i32>,
     pub attributes: Option<String>,
+    pub slug: Option<String>,
+}
+
+/// Writable page fields; the document a merge patch is applied to.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageFields {
+    pub page_name: String,
+    pub section_name: String,
+    pub lang: String,
+    pub content_type: String,
+    pub visible: bool,
+    pub display_order: i32,
+    pub attributes: Option<String>,
+    pub slug: Option<String>,
+}
+
+impl From<&Page> for PageFields {
+    fn from(page: &Page) -> Self {
+        PageFields {
+            page_name: page.page_name.clone(),
+            section_name: page.section_name.clone(),
+            lang: page.lang.clone(),
+            content_type: page.content_type.clone(),
+            visible: page.visible,
+            display_order: page.display_order,
+            attributes: page.attributes.clone(),
+            slug: page.slug.clone(),
+        }
+    }
+}
+
+/// New order of every page in a `page_name`/`section_name` scope (optionally
+/// narrowed to one `lang`), first to last.
+#[derive(Debug, Deserialize)]
+pub struct PageReorder {
+    pub page_name: String,
+    pub section_name: String,
+    pub lang: Option<String>,
+    pub ids: Vec<i64>,
+}
+
+/// Moves a page under `parent_id`, or to the top level when it is `None`.
+#[derive(Debug, Deserialize)]
+pub struct PageMove {
+    pub parent_id: Option<i64>,
+    /// Position among the new siblings; keeps the current one if absent.
+    pub display_order: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageTreeQuery {
+    /// Only pages in this language; a page whose parent is filtered out is
+    /// left out with its subtree.
+    pub lang: Option<String>,
+    /// Levels below the root to include; all when absent.
+    pub depth: Option<u32>,
+}
+
+/// A page with its children, in `display_order`.
+#[derive(Debug, Serialize)]
+pub struct PageNode {
+    #[serde(flatten)]
+    pub page: Page,
+    pub children: Vec<PageNode>,
 }
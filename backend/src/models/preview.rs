@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Preview {
+    pub id: i64,
+    pub page_id: i64,
+    /// Language shown: the page's own, or that of one of its translations.
+    pub lang: String,
+    /// Who or what the link is for.
+    pub note: Option<String>,
+    pub created_by: Option<i64>,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewCreate {
+    /// Defaults to the page's language.
+    pub lang: Option<String>,
+    /// Lifetime in seconds; defaults to 7 days, at most 30.
+    pub expires_in: Option<i64>,
+    pub note: Option<String>,
+}
+
+/// A preview with its link, which is only given while the preview is active.
+#[derive(Debug, Serialize)]
+pub struct PreviewLink {
+    #[serde(flatten)]
+    pub preview: Preview,
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewQuery {
+    pub token: String,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const STATUS_CODES: &[i32] = &[301, 302];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Redirect {
+    pub id: i64,
+    /// Path requests arrive at, e.g. `/en/about/team`.
+    pub source_path: String,
+    /// Site path or absolute URL to send them to.
+    pub target_path: Option<String>,
+    /// Page whose current `path` to send them to, instead of `target_path`.
+    pub target_page_id: Option<i64>,
+    /// `301` or `302`.
+    pub status_code: i32,
+    pub hits: i64,
+    pub last_hit_at: Option<String>,
+    /// Created by the server when a page's URL changed.
+    pub automatic: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Exactly one of `target_path` and `target_page_id` must be given.
+#[derive(Debug, Deserialize)]
+pub struct RedirectCreate {
+    pub source_path: String,
+    pub target_path: Option<String>,
+    pub target_page_id: Option<i64>,
+    pub status_code: Option<i32>,
+}
+
+/// Giving either target replaces the stored one.
+#[derive(Debug, Deserialize)]
+pub struct RedirectUpdate {
+    pub source_path: Option<String>,
+    pub target_path: Option<String>,
+    pub target_page_id: Option<i64>,
+    pub status_code: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedirectQuery {
+    /// Only redirects to this page.
+    pub page_id: Option<i64>,
+    pub automatic: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedirectLookupQuery {
+    pub path: String,
+}
+
+/// Where a request for `source_path` should go.
+#[derive(Debug, Serialize)]
+pub struct RedirectLookup {
+    pub redirect_id: i64,
+    pub source_path: String,
+    pub location: String,
+    pub status_code: i32,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::{Content, Page};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Relation {
+    pub id: i64,
+    /// `content` or `page`.
+    pub source_kind: String,
+    pub source_id: i64,
+    /// Handle such as `see-also` or `part-of`, read from source to target.
+    pub relation_type: String,
+    pub target_kind: String,
+    pub target_id: i64,
+    pub display_order: i32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelationCreate {
+    pub relation_type: String,
+    pub target_kind: String,
+    pub target_id: i64,
+    /// Defaults to after the last relation of the same type.
+    pub display_order: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelatedQuery {
+    /// Only relations of this type.
+    pub relation_type: Option<String>,
+    /// `outgoing`, `incoming` or, by default, both.
+    pub direction: Option<String>,
+}
+
+/// A relation seen from one of its ends, with the item at the other end.
+#[derive(Debug, Serialize)]
+pub struct RelatedItem {
+    /// `outgoing` when the item is the relation's target, `incoming` when it
+    /// is the source.
+    pub direction: &'static str,
+    pub relation: Relation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<Page>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SimilarQuery {
+    /// Defaults to 5, at most 50.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarContent {
+    /// Cosine similarity of the TF-IDF weighted terms, from 0 to 1.
+    pub score: f64,
+    #[serde(flatten)]
+    pub content: Content,
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Written as `.build.json` in the site directory; the next build compares
+/// against it to skip unchanged pages.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SiteManifest {
+    pub built_at: String,
+    /// Hash of the templates, base URL and language registry. Any change
+    /// re-renders every page.
+    pub settings: String,
+    /// Keyed by `page_name`.
+    pub pages: BTreeMap<String, SitePage>,
+    /// Image files copied into `images/`.
+    pub images: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SitePage {
+    /// Hash of the ids, versions and timestamps of every page and content
+    /// with this `page_name`, visible or not.
+    pub signature: String,
+    /// Latest `updated_at` among those rows.
+    pub lastmod: String,
+    /// Document title per rendered language.
+    pub titles: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SiteBuildQuery {
+    /// Re-render every page even if nothing changed.
+    pub full: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SiteBuildReport {
+    pub full: bool,
+    /// Files written, relative to the site directory.
+    pub rendered: Vec<String>,
+    /// Page files kept from the previous build.
+    pub unchanged: usize,
+    /// Files of pages or images that no longer exist.
+    pub removed: Vec<String>,
+    pub images_copied: usize,
+    /// Referenced images missing from the upload directory.
+    pub missing_images: Vec<String>,
+    /// `page_name`s that cannot be used as file names.
+    pub skipped: Vec<String>,
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Taxonomy {
+    pub id: i64,
+    /// Handle such as `categories` or `tags`.
+    pub name: String,
+    /// Whether terms may have a parent.
+    pub hierarchical: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxonomyCreate {
+    pub name: String,
+    pub hierarchical: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxonomyUpdate {
+    pub name: Option<String>,
+    /// Cannot be turned off while terms are nested.
+    pub hierarchical: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Term {
+    pub id: i64,
+    pub taxonomy_id: i64,
+    pub parent_id: Option<i64>,
+    /// Unique within the taxonomy.
+    pub slug: String,
+    /// Name for languages without one of their own.
+    pub name: String,
+    pub display_order: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TermCreate {
+    pub parent_id: Option<i64>,
+    pub slug: String,
+    pub name: String,
+    /// Names by language code.
+    pub names: Option<BTreeMap<String, String>>,
+    pub display_order: Option<i32>,
+}
+
+/// `names` replaces the stored names.
+#[derive(Debug, Deserialize)]
+pub struct TermUpdate {
+    pub slug: Option<String>,
+    pub name: Option<String>,
+    pub names: Option<BTreeMap<String, String>>,
+    pub display_order: Option<i32>,
+}
+
+/// Moves a term under `parent_id` in the same taxonomy, or to the top level
+/// when it is `None`.
+#[derive(Debug, Deserialize)]
+pub struct TermMove {
+    pub parent_id: Option<i64>,
+    /// Position among the new siblings; keeps the current one if absent.
+    pub display_order: Option<i32>,
+}
+
+/// A term with every name and its children, in `display_order`.
+#[derive(Debug, Serialize)]
+pub struct TermNode {
+    #[serde(flatten)]
+    pub term: Term,
+    pub names: BTreeMap<String, String>,
+    pub children: Vec<TermNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaxonomyDetail {
+    #[serde(flatten)]
+    pub taxonomy: Taxonomy,
+    pub terms: Vec<TermNode>,
+}
+
+/// The full set of terms a content or page is tagged with.
+#[derive(Debug, Deserialize)]
+pub struct TermAssignment {
+    pub term_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FacetQuery {
+    /// Search text, matched like `/api/search`; everything when absent.
+    pub q: Option<String>,
+    /// Comma-separated term ids every counted item must carry.
+    pub terms: Option<String>,
+    /// Only terms of this taxonomy.
+    pub taxonomy_id: Option<i64>,
+    /// Language to show term names in; the default language when absent.
+    pub lang: Option<String>,
+}
+
+/// How many matching contents and pages carry a term or one of its
+/// descendants.
+#[derive(Debug, Serialize)]
+pub struct Facet {
+    pub taxonomy_id: i64,
+    pub term_id: i64,
+    pub parent_id: Option<i64>,
+    pub slug: String,
+    pub name: String,
+    pub contents: usize,
+    pub pages: usize,
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::{Content, Page};
+
+#[derive(Debug, Deserialize)]
+pub struct TranslationCreate {
+    pub lang: String,
+    /// Defaults to `false` so untranslated copies stay off the site.
+    pub visible: Option<bool>,
+    /// Copy the source page's contents (and their images); defaults to `true`.
+    pub copy_contents: Option<bool>,
+}
+
+/// One page of a translation group with the time it or any of its contents
+/// last changed.
+#[derive(Debug, Serialize, FromRow)]
+pub struct TranslationEntry {
+    pub id: i64,
+    pub lang: String,
+    pub visible: bool,
+    pub version: i64,
+    pub translation_of: Option<i64>,
+    pub last_modified: String,
+    /// Set when the group's source changed after this translation did.
+    #[sqlx(default)]
+    pub stale: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranslationStatus {
+    pub translation_group: Option<String>,
+    /// The original the group's translations were made from, if it still exists.
+    pub source_id: Option<i64>,
+    pub translations: Vec<TranslationEntry>,
+    /// Enabled languages this group lacks.
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PublicPageQuery {
+    pub page_name: String,
+    pub section_name: Option<String>,
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicPage {
+    #[serde(flatten)]
+    pub page: Page,
+    pub contents: Vec<Content>,
+    /// The page is shown in a fallback language because the requested one
+    /// has no visible translation.
+    pub fallback: bool,
+}
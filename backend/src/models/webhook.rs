@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Lifecycle events a webhook can subscribe to.
+pub const WEBHOOK_EVENTS: &[&str] = &[
+    "page.created",
+    "page.updated",
+    "page.published",
+    "page.deleted",
+    "content.created",
+    "content.updated",
+    "content.deleted",
+    "image.uploaded",
+    "image.deleted",
+];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// Comma-separated event names, or `*` for every event.
+    pub events: String,
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookCreate {
+    pub url: String,
+    /// Generated when omitted; only ever returned by the create response.
+    pub secret: Option<String>,
+    /// Subscribed events; omitted or empty means every event.
+    pub events: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookUpdate {
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_id: i64,
+    pub event_type: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: Option<String>,
+    pub last_status_code: Option<i64>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub status: Option<String>,
+    pub webhook_id: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
@@ -0,0 +1,271 @@
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::models::{Page, PageNode};
+
+/// Longest slug the `pages.slug` column holds.
+const MAX_SLUG_LEN: usize = 100;
+
+#[derive(Debug)]
+pub enum TreeError {
+    /// Malformed slug or unknown parent.
+    Invalid(String),
+    /// The slug is taken among the siblings, or a move would create a cycle.
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::Invalid(message) | TreeError::Conflict(message) => {
+                write!(f, "{}", message)
+            }
+            TreeError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+impl From<sqlx::Error> for TreeError {
+    fn from(e: sqlx::Error) -> Self {
+        TreeError::Database(e)
+    }
+}
+
+/// Lowercased `slug`, which must be ASCII letters and digits in groups
+/// joined by single hyphens, such as `team-2024`.
+pub fn normalize_slug(slug: &str) -> Result<String, TreeError> {
+    let slug = slug.trim().to_ascii_lowercase();
+    let valid = slug.len() <= MAX_SLUG_LEN
+        && slug.split('-').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        });
+    if !valid {
+        return Err(TreeError::Invalid(format!(
+            "'{}' is not a valid slug: use up to {} lowercase letters, digits and single hyphens",
+            slug, MAX_SLUG_LEN
+        )));
+    }
+    Ok(slug)
+}
+
+/// Checks that `parent_id` names an existing page in `lang` and, when
+/// `page_id` is being moved under it, that the parent is neither the page
+/// itself nor one of its descendants.
+pub async fn check_parent(
+    conn: &mut SqliteConnection,
+    parent_id: Option<i64>,
+    lang: &str,
+    page_id: Option<i64>,
+) -> Result<(), TreeError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+
+    let parent_lang: Option<String> = sqlx::query_scalar("SELECT lang FROM pages WHERE id = ?")
+        .bind(parent_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    match parent_lang {
+        None => {
+            return Err(TreeError::Invalid(format!(
+                "Parent page {} not found",
+                parent_id
+            )))
+        }
+        Some(parent_lang) if parent_lang != lang => {
+            return Err(TreeError::Invalid(format!(
+                "Parent page {} is in '{}', not '{}'",
+                parent_id, parent_lang, lang
+            )))
+        }
+        Some(_) => {}
+    }
+
+    if let Some(page_id) = page_id {
+        let cycle: bool = sqlx::query_scalar(
+            "WITH RECURSIVE ancestors(id) AS (
+                 SELECT ?
+                 UNION
+                 SELECT p.parent_id FROM pages p JOIN ancestors a ON p.id = a.id
+                 WHERE p.parent_id IS NOT NULL
+             )
+             SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?)",
+        )
+        .bind(parent_id)
+        .bind(page_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if cycle {
+            return Err(TreeError::Conflict(format!(
+                "Page {} cannot be moved under itself or one of its descendants",
+                page_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fails if `page_id` has child pages in a language other than `lang`, which
+/// it cannot then be switched to.
+pub async fn check_children(
+    conn: &mut SqliteConnection,
+    page_id: i64,
+    lang: &str,
+) -> Result<(), TreeError> {
+    let other: Option<String> =
+        sqlx::query_scalar("SELECT lang FROM pages WHERE parent_id = ? AND lang != ? LIMIT 1")
+            .bind(page_id)
+            .bind(lang)
+            .fetch_optional(&mut *conn)
+            .await?;
+    match other {
+        Some(other) => Err(TreeError::Conflict(format!(
+            "Page {} has child pages in '{}': move them before changing its language to '{}'",
+            page_id, other, lang
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Fails if a page other than `page_id` under `parent_id` already uses
+/// `slug` in `lang`.
+pub async fn check_slug(
+    conn: &mut SqliteConnection,
+    parent_id: Option<i64>,
+    lang: &str,
+    slug: Option<&str>,
+    page_id: Option<i64>,
+) -> Result<(), TreeError> {
+    let Some(slug) = slug else {
+        return Ok(());
+    };
+
+    let taken: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM pages WHERE parent_id IS ? AND lang = ? AND slug = ? AND id != ?",
+    )
+    .bind(parent_id)
+    .bind(lang)
+    .bind(slug)
+    .bind(page_id.unwrap_or(0))
+    .fetch_optional(&mut *conn)
+    .await?;
+    match taken {
+        Some(id) => Err(TreeError::Conflict(format!(
+            "Slug '{}' is already used by page {} under the same parent in '{}'",
+            slug, id, lang
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Recomputes `path` for `page_id` from its parent's and then for every
+/// page below it. Call after a page's slug, language or parent changes.
+///
+/// A path starts with the language, which parent and children share, so
+/// `/en/about` and `/my-MM/about` address different pages.
+pub async fn refresh_paths(conn: &mut SqliteConnection, page_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "WITH RECURSIVE subtree(id, path) AS (
+             SELECT p.id, CASE WHEN p.parent_id IS NULL THEN '/' || p.lang || '/' || p.slug
+                 ELSE (SELECT path FROM pages WHERE id = p.parent_id) || '/' || p.slug END
+             FROM pages p WHERE p.id = ?
+             UNION ALL
+             SELECT c.id, s.path || '/' || c.slug FROM pages c JOIN subtree s ON c.parent_id = s.id
+         )
+         UPDATE pages SET path = (SELECT path FROM subtree WHERE subtree.id = pages.id)
+         WHERE id IN (SELECT id FROM subtree)",
+    )
+    .bind(page_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Recomputes every page's `path`, for bulk changes such as imports that
+/// delete parents or link many pages at once.
+pub async fn rebuild_paths(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "WITH RECURSIVE tree(id, path) AS (
+             SELECT id, '/' || lang || '/' || slug FROM pages WHERE parent_id IS NULL
+             UNION ALL
+             SELECT p.id, t.path || '/' || p.slug FROM pages p JOIN tree t ON p.parent_id = t.id
+         )
+         UPDATE pages SET path = (SELECT path FROM tree WHERE tree.id = pages.id)",
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn child_ids(conn: &mut SqliteConnection, page_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM pages WHERE parent_id = ? ORDER BY display_order, id")
+        .bind(page_id)
+        .fetch_all(conn)
+        .await
+}
+
+/// The tree below `root`, or below the top level when `None`, down to
+/// `depth` levels and optionally limited to one language. Returns the root
+/// node, or every top-level node.
+pub async fn subtree(
+    pool: &SqlitePool,
+    root: Option<i64>,
+    lang: Option<&str>,
+    depth: Option<u32>,
+) -> Result<Vec<PageNode>, sqlx::Error> {
+    let pages = sqlx::query_as::<_, Page>(
+        "WITH RECURSIVE tree(id, depth) AS (
+             SELECT id, 0 FROM pages
+             WHERE (? IS NULL AND parent_id IS NULL) OR id = ?
+             UNION ALL
+             SELECT p.id, t.depth + 1 FROM pages p JOIN tree t ON p.parent_id = t.id
+             WHERE ? IS NULL OR t.depth < ?
+         )
+         SELECT pages.* FROM pages JOIN tree ON tree.id = pages.id
+         WHERE ? IS NULL OR pages.lang = ?
+         ORDER BY pages.display_order, pages.id",
+    )
+    .bind(root)
+    .bind(root)
+    .bind(depth)
+    .bind(depth)
+    .bind(lang)
+    .bind(lang)
+    .fetch_all(pool)
+    .await?;
+
+    let mut roots = Vec::new();
+    let mut children: HashMap<i64, Vec<Page>> = HashMap::new();
+    for page in pages {
+        let is_root = match root {
+            Some(root) => page.id == root,
+            None => page.parent_id.is_none(),
+        };
+        match page.parent_id {
+            Some(parent_id) if !is_root => children.entry(parent_id).or_default().push(page),
+            _ => roots.push(page),
+        }
+    }
+
+    fn attach(page: Page, children: &mut HashMap<i64, Vec<Page>>) -> PageNode {
+        let below = children.remove(&page.id).unwrap_or_default();
+        PageNode {
+            page,
+            children: below
+                .into_iter()
+                .map(|child| attach(child, children))
+                .collect(),
+        }
+    }
+    Ok(roots
+        .into_iter()
+        .map(|page| attach(page, &mut children))
+        .collect())
+}
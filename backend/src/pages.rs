@@ -0,0 +1,244 @@
+use sqlx::SqliteConnection;
+use std::fmt;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::languages::{self, LanguageError};
+use crate::models::{Page, PageCreate, PageFields};
+use crate::page_tree::{self, TreeError};
+use crate::redirects;
+
+#[derive(Debug)]
+pub enum PageError {
+    /// Unknown or disabled language, malformed slug, or unusable parent.
+    Invalid(String),
+    /// The slug is taken among the siblings, or children are in another
+    /// language.
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageError::Invalid(message) | PageError::Conflict(message) => {
+                write!(f, "{}", message)
+            }
+            PageError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PageError {}
+
+impl From<sqlx::Error> for PageError {
+    fn from(e: sqlx::Error) -> Self {
+        PageError::Database(e)
+    }
+}
+
+impl From<TreeError> for PageError {
+    fn from(e: TreeError) -> Self {
+        match e {
+            TreeError::Invalid(message) => PageError::Invalid(message),
+            TreeError::Conflict(message) => PageError::Conflict(message),
+            TreeError::Database(e) => PageError::Database(e),
+        }
+    }
+}
+
+impl From<LanguageError> for PageError {
+    fn from(e: LanguageError) -> Self {
+        match e {
+            LanguageError::Invalid(message) => PageError::Invalid(message),
+            LanguageError::Database(e) => PageError::Database(e),
+        }
+    }
+}
+
+/// Creates a page in the tree and records it. Redirects from the new page's
+/// addresses are removed so they reach the page again.
+pub async fn create(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    data: &PageCreate,
+) -> Result<Page, PageError> {
+    let lang = languages::validate(&mut *conn, &data.lang).await?;
+    let slug = data
+        .slug
+        .as_deref()
+        .map(page_tree::normalize_slug)
+        .transpose()?;
+    page_tree::check_parent(conn, data.parent_id, &lang, None).await?;
+    page_tree::check_slug(conn, data.parent_id, &lang, slug.as_deref(), None).await?;
+
+    let page = sqlx::query_as::<_, Page>(
+        "INSERT INTO pages (page_name, section_name, lang, content_type, visible, display_order, attributes,
+         parent_id, slug)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(&data.page_name)
+    .bind(&data.section_name)
+    .bind(&lang)
+    .bind(&data.content_type)
+    .bind(data.visible.unwrap_or(true))
+    .bind(data.display_order.unwrap_or(0))
+    .bind(&data.attributes)
+    .bind(data.parent_id)
+    .bind(&slug)
+    .fetch_one(&mut *conn)
+    .await?;
+    let page = refresh_paths(conn, page.id).await?;
+
+    changes::record(
+        &mut *conn,
+        context,
+        "create",
+        "page",
+        page.id,
+        None::<&Page>,
+        Some(&page),
+    )
+    .await?;
+    redirects::clear_page_sources(conn, context, page.id).await?;
+    Ok(page)
+}
+
+/// Writes `fields` over `existing` if its version is still current, and
+/// records the change. `None` when the page changed or went away since it
+/// was read. Paths below the page follow a new slug or language, and the
+/// old addresses redirect to the new ones.
+pub async fn update(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    existing: &Page,
+    fields: &PageFields,
+) -> Result<Option<Page>, PageError> {
+    // Pages keep a language that was disabled after they were written.
+    let lang = if fields.lang == existing.lang {
+        existing.lang.clone()
+    } else {
+        languages::validate(&mut *conn, &fields.lang).await?
+    };
+    // Like the language, a stored slug is kept as it is unless it changes.
+    let slug = if fields.slug == existing.slug {
+        existing.slug.clone()
+    } else {
+        fields
+            .slug
+            .as_deref()
+            .map(page_tree::normalize_slug)
+            .transpose()?
+    };
+
+    if lang != existing.lang {
+        page_tree::check_parent(conn, existing.parent_id, &lang, Some(existing.id)).await?;
+        page_tree::check_children(conn, existing.id, &lang).await?;
+    }
+    let path_changes = slug != existing.slug || lang != existing.lang;
+    let old_paths = if path_changes {
+        page_tree::check_slug(
+            conn,
+            existing.parent_id,
+            &lang,
+            slug.as_deref(),
+            Some(existing.id),
+        )
+        .await?;
+        redirects::subtree_paths(conn, existing.id).await?
+    } else {
+        Vec::new()
+    };
+
+    let updated = sqlx::query_as::<_, Page>(
+        "UPDATE pages SET page_name = ?, section_name = ?, lang = ?, content_type = ?,
+         visible = ?, display_order = ?, attributes = ?, slug = ?, version = version + 1,
+         updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND version = ? RETURNING *",
+    )
+    .bind(&fields.page_name)
+    .bind(&fields.section_name)
+    .bind(&lang)
+    .bind(&fields.content_type)
+    .bind(fields.visible)
+    .bind(fields.display_order)
+    .bind(&fields.attributes)
+    .bind(&slug)
+    .bind(existing.id)
+    .bind(existing.version)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(mut updated) = updated else {
+        return Ok(None);
+    };
+    if path_changes {
+        updated = refresh_paths(conn, updated.id).await?;
+    }
+
+    changes::record(
+        &mut *conn,
+        context,
+        "update",
+        "page",
+        existing.id,
+        Some(existing),
+        Some(&updated),
+    )
+    .await?;
+    redirects::follow_path_changes(conn, context, &old_paths).await?;
+    redirects::follow_rename(
+        conn,
+        context,
+        (&existing.lang, &existing.page_name),
+        (&updated.lang, &updated.page_name),
+    )
+    .await?;
+    Ok(Some(updated))
+}
+
+/// Deletes `existing` if its version is still current, and records it.
+/// `false` when the page changed or went away since it was read. A page
+/// with children fails with a conflict.
+pub async fn delete(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    existing: &Page,
+) -> Result<bool, PageError> {
+    if !page_tree::child_ids(conn, existing.id).await?.is_empty() {
+        return Err(PageError::Conflict(
+            "Page has child pages; move or delete them first".to_string(),
+        ));
+    }
+
+    let deleted = sqlx::query("DELETE FROM pages WHERE id = ? AND version = ?")
+        .bind(existing.id)
+        .bind(existing.version)
+        .execute(&mut *conn)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    changes::record(
+        conn,
+        context,
+        "delete",
+        "page",
+        existing.id,
+        Some(existing),
+        None::<&Page>,
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Recomputes the paths at and below `page_id` and returns the page as it
+/// now is.
+async fn refresh_paths(conn: &mut SqliteConnection, page_id: i64) -> Result<Page, sqlx::Error> {
+    page_tree::refresh_paths(conn, page_id).await?;
+    sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+        .bind(page_id)
+        .fetch_one(&mut *conn)
+        .await
+}
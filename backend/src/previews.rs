@@ -0,0 +1,167 @@
+//! Preview links: signed, expiring tokens that show one page in one language
+//! as it currently stands, visible or not, without an agent login.
+//!
+//! A token is an HS256 JWT signed with the server's `JWT_SECRET` and the
+//! `preview` audience, so it cannot be used as an agent token or the other
+//! way round. It carries the id of its `previews` row and is derived from
+//! that row alone, which lets the admin API hand the same link out again and
+//! makes revoking or deleting the row disable it.
+
+use chrono::{NaiveDateTime, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite, SqlitePool};
+use std::fmt;
+
+use crate::blocks;
+use crate::models::preview::Preview;
+use crate::models::translation::PublicPage;
+use crate::models::Page;
+
+const AUDIENCE: &str = "preview";
+
+/// Lifetime of a preview when none is given.
+pub const DEFAULT_TTL: i64 = 7 * 24 * 60 * 60;
+/// Longest lifetime a preview can be given.
+pub const MAX_TTL: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PreviewClaims {
+    aud: String,
+    /// Id of the `previews` row.
+    jti: String,
+    page_id: i64,
+    lang: String,
+    exp: usize,
+}
+
+#[derive(Debug)]
+pub enum PreviewError {
+    /// The token is malformed, forged, expired or revoked.
+    Invalid,
+    /// The previewed page, or its translation, no longer exists.
+    NotFound,
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for PreviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreviewError::Invalid => write!(f, "Invalid or expired preview token"),
+            PreviewError::NotFound => write!(f, "Previewed page not found"),
+            PreviewError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PreviewError {}
+
+impl From<sqlx::Error> for PreviewError {
+    fn from(e: sqlx::Error) -> Self {
+        PreviewError::Database(e)
+    }
+}
+
+fn expires_at(preview: &Preview) -> Option<i64> {
+    NaiveDateTime::parse_from_str(&preview.expires_at, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|expires_at| expires_at.and_utc().timestamp())
+}
+
+/// Neither revoked nor expired.
+pub fn is_active(preview: &Preview) -> bool {
+    preview.revoked_at.is_none()
+        && expires_at(preview).is_some_and(|expires_at| expires_at > Utc::now().timestamp())
+}
+
+pub fn sign(preview: &Preview, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = PreviewClaims {
+        aud: AUDIENCE.to_string(),
+        jti: preview.id.to_string(),
+        page_id: preview.page_id,
+        lang: preview.lang.clone(),
+        exp: expires_at(preview).unwrap_or_default() as usize,
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+}
+
+/// URL of the rendered preview, absolute when `PUBLIC_BASE_URL` is set.
+pub fn url(base: &str, token: &str) -> String {
+    format!("{}/api/public/preview/render?token={}", base, token)
+}
+
+/// The page shown for `page` in `lang`: the page itself if it is in that
+/// language, otherwise its translation into it.
+pub async fn target_page<'e, E>(
+    executor: E,
+    page: Page,
+    lang: &str,
+) -> Result<Option<Page>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if page.lang == lang {
+        return Ok(Some(page));
+    }
+    let Some(group) = page.translation_group else {
+        return Ok(None);
+    };
+    sqlx::query_as::<_, Page>(
+        "SELECT * FROM pages WHERE translation_group = ? AND lang = ? ORDER BY id LIMIT 1",
+    )
+    .bind(group)
+    .bind(lang)
+    .fetch_optional(executor)
+    .await
+}
+
+/// Checks `token` and returns the preview it grants with the page it shows,
+/// contents included, whether the page is visible or not.
+pub async fn resolve(
+    pool: &SqlitePool,
+    secret: &str,
+    token: &str,
+) -> Result<(Preview, PublicPage), PreviewError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[AUDIENCE]);
+    let claims = decode::<PreviewClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .map_err(|_| PreviewError::Invalid)?
+    .claims;
+    let id: i64 = claims.jti.parse().map_err(|_| PreviewError::Invalid)?;
+
+    let preview = sqlx::query_as::<_, Preview>("SELECT * FROM previews WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(PreviewError::Invalid)?;
+    if preview.page_id != claims.page_id || preview.lang != claims.lang || !is_active(&preview) {
+        return Err(PreviewError::Invalid);
+    }
+
+    let page = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+        .bind(preview.page_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(PreviewError::NotFound)?;
+    let page = target_page(pool, page, &preview.lang)
+        .await?
+        .ok_or(PreviewError::NotFound)?;
+    let contents = blocks::page_contents(pool, page.id).await?;
+
+    Ok((
+        preview,
+        PublicPage {
+            page,
+            contents,
+            fallback: false,
+        },
+    ))
+}
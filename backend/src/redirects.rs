@@ -0,0 +1,558 @@
+use sqlx::{SqliteConnection, SqlitePool};
+use std::fmt;
+
+use crate::audit::AuditContext;
+use crate::changes;
+use crate::models::redirect::{Redirect, RedirectLookup};
+use crate::site;
+
+/// Redirects a lookup follows before it gives up on a chain.
+const MAX_HOPS: usize = 10;
+
+const MAX_PATH_LEN: usize = 500;
+
+#[derive(Debug)]
+pub enum RedirectError {
+    /// Malformed path or target.
+    Invalid(String),
+    /// The source is taken, belongs to a page, or the redirect would loop.
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedirectError::Invalid(message) | RedirectError::Conflict(message) => {
+                write!(f, "{}", message)
+            }
+            RedirectError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RedirectError {}
+
+impl From<sqlx::Error> for RedirectError {
+    fn from(e: sqlx::Error) -> Self {
+        RedirectError::Database(e)
+    }
+}
+
+/// Where a redirect sends requests.
+#[derive(Debug, Clone)]
+pub enum Target {
+    Path(String),
+    /// The page's current `path`, so later moves need no new redirect.
+    Page(i64),
+}
+
+impl Target {
+    /// The target given by a request's `target_path` and `target_page_id`, of
+    /// which exactly one must be set.
+    pub fn from_fields(
+        target_path: Option<&str>,
+        target_page_id: Option<i64>,
+    ) -> Result<Target, RedirectError> {
+        match (target_path, target_page_id) {
+            (Some(path), None) => Ok(Target::Path(normalize_target(path)?)),
+            (None, Some(page_id)) => Ok(Target::Page(page_id)),
+            _ => Err(RedirectError::Invalid(
+                "Give exactly one of target_path and target_page_id".to_string(),
+            )),
+        }
+    }
+
+    pub fn of(redirect: &Redirect) -> Target {
+        match (&redirect.target_path, redirect.target_page_id) {
+            (_, Some(page_id)) => Target::Page(page_id),
+            (path, None) => Target::Path(path.clone().unwrap_or_default()),
+        }
+    }
+
+    fn columns(&self) -> (Option<&str>, Option<i64>) {
+        match self {
+            Target::Path(path) => (Some(path), None),
+            Target::Page(page_id) => (None, Some(*page_id)),
+        }
+    }
+}
+
+fn trim_trailing_slash(path: &str) -> String {
+    match path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// A site path starting with `/`, without a query, fragment or trailing
+/// slash.
+pub fn normalize_source(path: &str) -> Result<String, RedirectError> {
+    let path = path.trim();
+    let valid = path.starts_with('/')
+        && path.len() <= MAX_PATH_LEN
+        && !path.contains("//")
+        && !path
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '?' || c == '#');
+    if !valid {
+        return Err(RedirectError::Invalid(format!(
+            "'{}' is not a valid source path: use a site path starting with '/' \
+             without a query or fragment",
+            path
+        )));
+    }
+    Ok(trim_trailing_slash(path))
+}
+
+/// A site path starting with `/` or an absolute `http(s)` URL.
+pub fn normalize_target(path: &str) -> Result<String, RedirectError> {
+    let path = path.trim();
+    let absolute = ["http://", "https://"]
+        .iter()
+        .any(|scheme| path.starts_with(scheme) && path.len() > scheme.len());
+    let valid = (absolute || path.starts_with('/'))
+        && path.len() <= MAX_PATH_LEN
+        && !path.chars().any(|c| c.is_whitespace() || c.is_control());
+    if !valid {
+        return Err(RedirectError::Invalid(format!(
+            "'{}' is not a valid target: use a site path starting with '/' or an http(s) URL",
+            path
+        )));
+    }
+    Ok(path.to_string())
+}
+
+/// The source a request for `path` matches: its query, fragment and
+/// trailing slash are ignored.
+pub fn lookup_key(path: &str) -> String {
+    let path = path.trim().split(['?', '#']).next().unwrap_or_default();
+    trim_trailing_slash(path)
+}
+
+pub async fn fetch(
+    conn: &mut SqliteConnection,
+    redirect_id: i64,
+) -> Result<Option<Redirect>, sqlx::Error> {
+    sqlx::query_as::<_, Redirect>("SELECT * FROM redirects WHERE id = ?")
+        .bind(redirect_id)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn by_source(
+    conn: &mut SqliteConnection,
+    source: &str,
+) -> Result<Option<Redirect>, sqlx::Error> {
+    sqlx::query_as::<_, Redirect>("SELECT * FROM redirects WHERE source_path = ?")
+        .bind(source)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn page_path(
+    conn: &mut SqliteConnection,
+    page_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let path: Option<Option<String>> = sqlx::query_scalar("SELECT path FROM pages WHERE id = ?")
+        .bind(page_id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(path.flatten())
+}
+
+/// The page served at `path`, either as its tree `path` or as its static
+/// site URL (see [`site::page_file`]). Both start with the page's language,
+/// so redirect sources from either are compared in one scheme.
+async fn live_page(conn: &mut SqliteConnection, path: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT id FROM pages WHERE path = ? OR '/' || lang || '/' || page_name || '.html' = ?
+         ORDER BY id LIMIT 1",
+    )
+    .bind(path)
+    .bind(path)
+    .fetch_optional(conn)
+    .await
+}
+
+/// Where `target` currently leads; `None` for a page without a `path`.
+pub async fn location(
+    conn: &mut SqliteConnection,
+    target: &Target,
+) -> Result<Option<String>, sqlx::Error> {
+    match target {
+        Target::Path(path) => Ok(Some(path.clone())),
+        Target::Page(page_id) => page_path(conn, *page_id).await,
+    }
+}
+
+/// Follows `target` through redirects whose source it is, so a stored
+/// redirect always points at the end of its chain. Fails if the chain leads
+/// back to `source`.
+async fn collapse(
+    conn: &mut SqliteConnection,
+    source: &str,
+    mut target: Target,
+) -> Result<Target, RedirectError> {
+    let mut seen = vec![source.to_string()];
+    loop {
+        if let Some(location) = location(conn, &target).await? {
+            if seen.contains(&location) {
+                return Err(RedirectError::Conflict(format!(
+                    "A redirect from '{}' would loop back to '{}'",
+                    source, location
+                )));
+            }
+        }
+        let Target::Path(path) = &target else {
+            return Ok(target);
+        };
+        match by_source(conn, path).await? {
+            Some(next) => {
+                seen.push(path.clone());
+                target = Target::of(&next);
+            }
+            None => return Ok(target),
+        }
+    }
+}
+
+pub async fn remove(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    redirect: &Redirect,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM redirects WHERE id = ?")
+        .bind(redirect.id)
+        .execute(&mut *conn)
+        .await?;
+    changes::record(
+        conn,
+        context,
+        "delete",
+        "redirect",
+        redirect.id,
+        Some(redirect),
+        None::<&Redirect>,
+    )
+    .await
+}
+
+/// Points redirects that led to `redirect`'s source at its target instead,
+/// removing those that would then lead back to themselves.
+async fn retarget_inbound(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    redirect: &Redirect,
+) -> Result<(), sqlx::Error> {
+    let inbound =
+        sqlx::query_as::<_, Redirect>("SELECT * FROM redirects WHERE target_path = ? AND id != ?")
+            .bind(&redirect.source_path)
+            .bind(redirect.id)
+            .fetch_all(&mut *conn)
+            .await?;
+    let location = location(conn, &Target::of(redirect)).await?;
+
+    for before in inbound {
+        if location.as_deref() == Some(before.source_path.as_str()) {
+            remove(conn, context, &before).await?;
+            continue;
+        }
+        let after = sqlx::query_as::<_, Redirect>(
+            "UPDATE redirects SET target_path = ?, target_page_id = ?,
+             updated_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING *",
+        )
+        .bind(&redirect.target_path)
+        .bind(redirect.target_page_id)
+        .bind(before.id)
+        .fetch_one(&mut *conn)
+        .await?;
+        changes::record(
+            conn,
+            context,
+            "update",
+            "redirect",
+            before.id,
+            Some(&before),
+            Some(&after),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Creates a redirect from `source` or, given `redirect_id`, updates that
+/// one. Chains are collapsed so the stored target is final, and redirects
+/// that led to `source` are pointed at the new target. Fails if `source`
+/// is a page's path or already redirected elsewhere, or if the redirect
+/// would loop.
+pub async fn save(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    redirect_id: Option<i64>,
+    source: &str,
+    target: Target,
+    status_code: i32,
+    automatic: bool,
+) -> Result<Redirect, RedirectError> {
+    if let Some(page_id) = live_page(conn, source).await? {
+        return Err(RedirectError::Conflict(format!(
+            "'{}' is the path or site URL of page {}",
+            source, page_id
+        )));
+    }
+    if let Some(existing) = by_source(conn, source).await? {
+        if Some(existing.id) != redirect_id {
+            return Err(RedirectError::Conflict(format!(
+                "Redirect {} already starts at '{}'",
+                existing.id, source
+            )));
+        }
+    }
+
+    let target = collapse(conn, source, target).await?;
+    let (target_path, target_page_id) = target.columns();
+
+    let before = match redirect_id {
+        Some(redirect_id) => fetch(conn, redirect_id).await?,
+        None => None,
+    };
+    let redirect = match &before {
+        Some(before) => {
+            sqlx::query_as::<_, Redirect>(
+                "UPDATE redirects SET source_path = ?, target_path = ?, target_page_id = ?,
+                 status_code = ?, automatic = ?, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ? RETURNING *",
+            )
+            .bind(source)
+            .bind(target_path)
+            .bind(target_page_id)
+            .bind(status_code)
+            .bind(automatic)
+            .bind(before.id)
+            .fetch_one(&mut *conn)
+            .await?
+        }
+        None => {
+            sqlx::query_as::<_, Redirect>(
+                "INSERT INTO redirects (source_path, target_path, target_page_id, status_code,
+                 automatic)
+                 VALUES (?, ?, ?, ?, ?) RETURNING *",
+            )
+            .bind(source)
+            .bind(target_path)
+            .bind(target_page_id)
+            .bind(status_code)
+            .bind(automatic)
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    changes::record(
+        conn,
+        context,
+        if before.is_some() { "update" } else { "create" },
+        "redirect",
+        redirect.id,
+        before.as_ref(),
+        Some(&redirect),
+    )
+    .await?;
+    retarget_inbound(conn, context, &redirect).await?;
+    Ok(redirect)
+}
+
+/// A redirect from a path a page now lives at would hide the page.
+async fn clear_source(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    path: &str,
+) -> Result<(), sqlx::Error> {
+    match by_source(conn, path).await? {
+        Some(redirect) => remove(conn, context, &redirect).await,
+        None => Ok(()),
+    }
+}
+
+/// Removes redirects from the addresses `page_id` is now served at, its
+/// `path` and its static site URL. Call in the transaction of every write
+/// that creates a page or gives it a new address.
+pub async fn clear_page_sources(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    page_id: i64,
+) -> Result<(), sqlx::Error> {
+    let page: Option<(String, String, Option<String>)> =
+        sqlx::query_as("SELECT lang, page_name, path FROM pages WHERE id = ?")
+            .bind(page_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    let Some((lang, page_name, path)) = page else {
+        return Ok(());
+    };
+    if let Some(path) = path {
+        clear_source(conn, context, &path).await?;
+    }
+    if site::is_file_name(&page_name) {
+        clear_source(conn, context, &site_url(&lang, &page_name)).await?;
+    }
+    Ok(())
+}
+
+/// A `301` created by the server, replacing an earlier automatic redirect
+/// from `source`. Skipped when an editor's redirect already starts there,
+/// `source` is still a page's path, or the redirect would loop.
+async fn redirect_automatically(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    source: &str,
+    target: Target,
+) -> Result<(), sqlx::Error> {
+    let existing = match by_source(conn, source).await? {
+        Some(redirect) if !redirect.automatic => return Ok(()),
+        Some(redirect) => Some(redirect.id),
+        None => None,
+    };
+    match save(conn, context, existing, source, target, 301, true).await {
+        Ok(_) | Err(RedirectError::Invalid(_)) | Err(RedirectError::Conflict(_)) => Ok(()),
+        Err(RedirectError::Database(e)) => Err(e),
+    }
+}
+
+/// Paths of `page_id` and every page below it, taken before a change that
+/// may move them so [`follow_path_changes`] can redirect the old ones.
+pub async fn subtree_paths(
+    conn: &mut SqliteConnection,
+    page_id: i64,
+) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    sqlx::query_as(
+        "WITH RECURSIVE subtree(id) AS (
+             SELECT ?
+             UNION ALL
+             SELECT p.id FROM pages p JOIN subtree s ON p.parent_id = s.id
+         )
+         SELECT pages.id, pages.path FROM pages JOIN subtree ON subtree.id = pages.id
+         WHERE pages.path IS NOT NULL",
+    )
+    .bind(page_id)
+    .fetch_all(conn)
+    .await
+}
+
+/// Redirects each path in `before` that changed to its page. A page whose
+/// path was cleared gets no redirect.
+pub async fn follow_path_changes(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    before: &[(i64, String)],
+) -> Result<(), sqlx::Error> {
+    let mut moved = Vec::new();
+    for (page_id, old_path) in before {
+        let Some(new_path) = page_path(conn, *page_id).await? else {
+            continue;
+        };
+        if new_path != *old_path {
+            clear_source(conn, context, &new_path).await?;
+            moved.push((*page_id, old_path));
+        }
+    }
+    for (page_id, old_path) in moved {
+        redirect_automatically(conn, context, old_path, Target::Page(page_id)).await?;
+    }
+    Ok(())
+}
+
+fn site_url(lang: &str, page_name: &str) -> String {
+    format!("/{}", site::page_file(lang, page_name))
+}
+
+/// Redirects the static site URL of a page's `page_name` in its language
+/// after either changed, once no page is left at the old one.
+pub async fn follow_rename(
+    conn: &mut SqliteConnection,
+    context: &AuditContext,
+    (old_lang, old_name): (&str, &str),
+    (new_lang, new_name): (&str, &str),
+) -> Result<(), sqlx::Error> {
+    if (old_lang, old_name) == (new_lang, new_name)
+        || !site::is_file_name(old_name)
+        || !site::is_file_name(new_name)
+    {
+        return Ok(());
+    }
+    let remaining: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pages WHERE lang = ? AND page_name = ?")
+            .bind(old_lang)
+            .bind(old_name)
+            .fetch_one(&mut *conn)
+            .await?;
+    if remaining > 0 {
+        return Ok(());
+    }
+
+    let target = site_url(new_lang, new_name);
+    clear_source(conn, context, &target).await?;
+    redirect_automatically(
+        conn,
+        context,
+        &site_url(old_lang, old_name),
+        Target::Path(target),
+    )
+    .await
+}
+
+/// Where a request for `path` should go, following any chain left by manual
+/// edits, and counts the hit on the redirect that matched. `None` when no
+/// redirect matches, a page is served at `path` anyway, or the redirect's
+/// page has no path. Fails with `Conflict` on a loop
+/// or a chain longer than [`MAX_HOPS`].
+pub async fn lookup(
+    pool: &SqlitePool,
+    path: &str,
+) -> Result<Option<RedirectLookup>, RedirectError> {
+    let key = lookup_key(path);
+    let mut conn = pool.acquire().await?;
+    let Some(first) = by_source(&mut conn, &key).await? else {
+        return Ok(None);
+    };
+    // Writes clear such redirects, but rows from raw SQL may remain.
+    if live_page(&mut conn, &key).await?.is_some() {
+        return Ok(None);
+    }
+
+    let mut seen = vec![key];
+    let mut current = Target::of(&first);
+    let location = loop {
+        let Some(location) = location(&mut conn, &current).await? else {
+            return Ok(None);
+        };
+        if seen.contains(&location) || seen.len() > MAX_HOPS {
+            return Err(RedirectError::Conflict(format!(
+                "Redirects from '{}' loop or chain more than {} times",
+                first.source_path, MAX_HOPS
+            )));
+        }
+        match by_source(&mut conn, &location).await? {
+            Some(next) => {
+                seen.push(location);
+                current = Target::of(&next);
+            }
+            None => break location,
+        }
+    };
+
+    sqlx::query(
+        "UPDATE redirects SET hits = hits + 1, last_hit_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(first.id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Some(RedirectLookup {
+        redirect_id: first.id,
+        source_path: first.source_path,
+        location,
+        status_code: first.status_code,
+    }))
+}
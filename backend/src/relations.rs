@@ -0,0 +1,174 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::galleries;
+use crate::models::relation::{RelatedItem, Relation, SimilarContent};
+use crate::models::{Content, Page};
+use crate::similarity;
+
+/// What either end of a relation is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Content,
+    Page,
+}
+
+impl Kind {
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "content" => Some(Kind::Content),
+            "page" => Some(Kind::Page),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Content => "content",
+            Kind::Page => "page",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            Kind::Content => "contents",
+            Kind::Page => "pages",
+        }
+    }
+}
+
+pub async fn exists(conn: &mut SqliteConnection, kind: Kind, id: i64) -> Result<bool, sqlx::Error> {
+    let found: Option<i64> =
+        sqlx::query_scalar(&format!("SELECT id FROM {} WHERE id = ?", kind.table()))
+            .bind(id)
+            .fetch_optional(conn)
+            .await?;
+    Ok(found.is_some())
+}
+
+/// Relations of `kind` `id` in one direction: those it is the source of
+/// when `outgoing`, otherwise those it is the target of.
+async fn relations(
+    pool: &SqlitePool,
+    kind: Kind,
+    id: i64,
+    relation_type: Option<&str>,
+    outgoing: bool,
+) -> Result<Vec<Relation>, sqlx::Error> {
+    let end = if outgoing { "source" } else { "target" };
+    sqlx::query_as::<_, Relation>(&format!(
+        "SELECT * FROM relations
+         WHERE {end}_kind = ? AND {end}_id = ? AND (? IS NULL OR relation_type = ?)
+         ORDER BY relation_type, display_order, id",
+    ))
+    .bind(kind.as_str())
+    .bind(id)
+    .bind(relation_type)
+    .bind(relation_type)
+    .fetch_all(pool)
+    .await
+}
+
+/// The items related to `kind` `id`, each with the relation that links
+/// them: targets of its own relations first if `outgoing`, then sources of
+/// relations to it if `incoming`.
+pub async fn related(
+    pool: &SqlitePool,
+    kind: Kind,
+    id: i64,
+    relation_type: Option<&str>,
+    outgoing: bool,
+    incoming: bool,
+) -> Result<Vec<RelatedItem>, sqlx::Error> {
+    let mut ends = Vec::new();
+    if outgoing {
+        for relation in relations(pool, kind, id, relation_type, true).await? {
+            ends.push(("outgoing", relation));
+        }
+    }
+    if incoming {
+        for relation in relations(pool, kind, id, relation_type, false).await? {
+            ends.push(("incoming", relation));
+        }
+    }
+
+    let mut items = Vec::with_capacity(ends.len());
+    for (direction, relation) in ends {
+        let (other_kind, other_id) = if direction == "outgoing" {
+            (relation.target_kind.as_str(), relation.target_id)
+        } else {
+            (relation.source_kind.as_str(), relation.source_id)
+        };
+        let (content, page) = match Kind::parse(other_kind) {
+            Some(Kind::Content) => {
+                let mut contents = Vec::from_iter(
+                    sqlx::query_as::<_, Content>("SELECT * FROM contents WHERE id = ?")
+                        .bind(other_id)
+                        .fetch_optional(pool)
+                        .await?,
+                );
+                galleries::attach(pool, &mut contents).await?;
+                (contents.pop(), None)
+            }
+            Some(Kind::Page) => {
+                let page = sqlx::query_as::<_, Page>("SELECT * FROM pages WHERE id = ?")
+                    .bind(other_id)
+                    .fetch_optional(pool)
+                    .await?;
+                (None, page)
+            }
+            None => (None, None),
+        };
+        items.push(RelatedItem {
+            direction,
+            relation,
+            content,
+            page,
+        });
+    }
+    Ok(items)
+}
+
+/// Contents most like `content`, from pages in the same language as its
+/// own. Placed blocks are not considered.
+pub async fn similar(
+    pool: &SqlitePool,
+    content: &Content,
+    limit: usize,
+) -> Result<Vec<SimilarContent>, sqlx::Error> {
+    let mut candidates = sqlx::query_as::<_, Content>(
+        "SELECT c.* FROM contents c JOIN pages p ON p.id = c.ref_id
+         WHERE c.id != ? AND p.lang = (SELECT lang FROM pages WHERE id = ?)",
+    )
+    .bind(content.id)
+    .bind(content.ref_id)
+    .fetch_all(pool)
+    .await?;
+
+    let counts = |content: &Content| {
+        similarity::term_counts(
+            content.title.as_deref(),
+            content.short_desc.as_deref(),
+            content.long_desc_html.as_deref(),
+        )
+    };
+    let documents: Vec<(i64, _)> = candidates
+        .iter()
+        .map(|candidate| (candidate.id, counts(candidate)))
+        .collect();
+    let ranked = similarity::rank(&counts(content), &documents, limit);
+
+    let mut contents = Vec::with_capacity(ranked.len());
+    let mut scores = Vec::with_capacity(ranked.len());
+    for (id, score) in ranked {
+        if let Some(position) = candidates.iter().position(|candidate| candidate.id == id) {
+            contents.push(candidates.swap_remove(position));
+            scores.push((score * 10_000.0).round() / 10_000.0);
+        }
+    }
+    galleries::attach(pool, &mut contents).await?;
+    Ok(scores
+        .into_iter()
+        .zip(contents)
+        .map(|(score, content)| SimilarContent { score, content })
+        .collect())
+}
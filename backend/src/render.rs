@@ -0,0 +1,436 @@
+use minijinja::{context, Environment, Value};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::languages;
+use crate::models::translation::PublicPage;
+use crate::{rich_text, site};
+
+/// Templates compiled into the binary. Each can be replaced by a file with
+/// the same relative path in the renderer's template directory.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("layout.html", include_str!("../templates/layout.html")),
+    ("macros.html", include_str!("../templates/macros.html")),
+    ("index.html", include_str!("../templates/index.html")),
+    (
+        "content_types/default.html",
+        include_str!("../templates/content_types/default.html"),
+    ),
+    (
+        "content_types/h1.html",
+        include_str!("../templates/content_types/h1.html"),
+    ),
+    (
+        "content_types/h2.html",
+        include_str!("../templates/content_types/h2.html"),
+    ),
+    (
+        "content_types/h3.html",
+        include_str!("../templates/content_types/h3.html"),
+    ),
+    (
+        "content_types/p.html",
+        include_str!("../templates/content_types/p.html"),
+    ),
+    (
+        "content_types/img.html",
+        include_str!("../templates/content_types/img.html"),
+    ),
+    (
+        "content_types/vid.html",
+        include_str!("../templates/content_types/vid.html"),
+    ),
+];
+
+#[derive(Debug)]
+pub enum RenderError {
+    Template(minijinja::Error),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Template(e) => write!(f, "Template error: {}", e),
+            RenderError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<minijinja::Error> for RenderError {
+    fn from(e: minijinja::Error) -> Self {
+        RenderError::Template(e)
+    }
+}
+
+impl From<sqlx::Error> for RenderError {
+    fn from(e: sqlx::Error) -> Self {
+        RenderError::Database(e)
+    }
+}
+
+/// Where rendered markup points: image files and other language versions of
+/// a page. The API and a static export link to different places.
+pub struct Links<'a> {
+    /// Prefix for stored image filenames, without a trailing slash.
+    pub image_base: String,
+    /// URL of `page_name` in `lang`.
+    pub page_href: &'a (dyn Fn(&str, &str) -> String + Sync),
+}
+
+/// Prefix the upload endpoint returns, and the admin UI stores, in front of
+/// an uploaded filename.
+pub const IMAGE_PREFIX: &str = "/images/";
+
+/// Entries of a content's `image_path`, which holds one filename or a
+/// comma-separated list.
+pub fn image_paths(image_path: &str) -> impl Iterator<Item = &str> {
+    image_path
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+}
+
+/// Whether an `image_path` entry is a URL rather than a stored filename:
+/// an `http(s)` URL or a root-relative path outside [`IMAGE_PREFIX`].
+pub fn is_external(path: &str) -> bool {
+    path.starts_with("http://")
+        || path.starts_with("https://")
+        || (path.starts_with('/') && !path.starts_with(IMAGE_PREFIX))
+}
+
+/// The uploaded file an `image_path` entry or attachment names, with any
+/// [`IMAGE_PREFIX`] removed. `None` for URLs and for names that could reach
+/// outside the upload directory.
+pub fn stored_file(entry: &str) -> Option<&str> {
+    let name = entry.strip_prefix(IMAGE_PREFIX).unwrap_or(entry);
+    site::is_file_name(name).then_some(name)
+}
+
+/// Uploaded files a content's or block's `image_path` refers to.
+pub fn stored_files(image_path: &str) -> impl Iterator<Item = &str> {
+    image_paths(image_path).filter_map(stored_file)
+}
+
+/// `image_path` with every uploaded file it refers to replaced by `rename`,
+/// keeping URLs, the [`IMAGE_PREFIX`] of entries that had one, and the list.
+pub fn rename_stored_files(image_path: &str, mut rename: impl FnMut(&str) -> String) -> String {
+    image_paths(image_path)
+        .map(|entry| match stored_file(entry) {
+            Some(file) => {
+                let prefix = &entry[..entry.len() - file.len()];
+                format!("{}{}", prefix, rename(file))
+            }
+            None => entry.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Links<'_> {
+    /// Image URLs of a content's `image_path`. Absolute URLs are kept as
+    /// they are.
+    pub fn images(&self, image_path: Option<&str>) -> Vec<String> {
+        image_paths(image_path.unwrap_or_default())
+            .map(|path| self.image(path))
+            .collect()
+    }
+
+    /// URL of one image entry or attachment.
+    pub fn image(&self, path: &str) -> String {
+        if is_external(path) {
+            path.to_string()
+        } else {
+            let file = path.strip_prefix(IMAGE_PREFIX).unwrap_or(path);
+            format!("{}/{}", self.image_base, file)
+        }
+    }
+}
+
+/// Title of a `page_name`: the first content title of its first `h1` page,
+/// or the page_name itself.
+pub fn document_title(pages: &[PublicPage], page_name: &str) -> String {
+    pages
+        .iter()
+        .find(|page| page.page.content_type == "h1")
+        .and_then(|page| page.contents.iter().find_map(|c| c.title.clone()))
+        .unwrap_or_else(|| page_name.to_string())
+}
+
+/// A rendered HTML document and the title in its `<title>`.
+pub struct Document {
+    pub title: String,
+    pub html: String,
+}
+
+/// Entry of a language index: a page of the site and where it lives.
+#[derive(Serialize)]
+pub struct IndexEntry {
+    pub page_name: String,
+    pub title: String,
+    pub href: String,
+}
+
+#[derive(Serialize)]
+pub struct Alternate {
+    pub lang: String,
+    pub href: String,
+}
+
+#[derive(Serialize)]
+struct Section {
+    name: String,
+    html: Value,
+}
+
+/// Turns pages into HTML with one template per `content_type`, falling back
+/// to `content_types/default.html`, inside `layout.html`.
+pub struct Renderer {
+    env: Environment<'static>,
+    template_dir: Option<PathBuf>,
+}
+
+impl Renderer {
+    /// Uses the built-in templates, each overridden by the file of the same
+    /// relative path in `template_dir` when there is one.
+    pub fn new(template_dir: Option<PathBuf>) -> Self {
+        let overrides = template_dir.clone().map(minijinja::path_loader);
+        let mut env = Environment::new();
+        env.set_loader(move |name| {
+            if let Some(load) = &overrides {
+                if let Some(source) = load(name)? {
+                    return Ok(Some(source));
+                }
+            }
+            Ok(BUILTIN_TEMPLATES
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(_, source)| source.to_string()))
+        });
+        Renderer { env, template_dir }
+    }
+
+    /// Hash of every template source the renderer can use, built-in and
+    /// override, so callers caching rendered output notice template edits.
+    pub fn fingerprint(&self) -> Result<String, std::io::Error> {
+        let mut hasher = Sha256::new();
+        for (name, source) in BUILTIN_TEMPLATES {
+            hasher.update(name.as_bytes());
+            hasher.update(source.as_bytes());
+        }
+        if let Some(dir) = &self.template_dir {
+            let mut files = Vec::new();
+            collect_files(dir, &mut files)?;
+            files.sort();
+            for file in files {
+                hasher.update(file.to_string_lossy().as_bytes());
+                hasher.update(std::fs::read(&file)?);
+            }
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    fn element_template(
+        &self,
+        content_type: &str,
+    ) -> Result<minijinja::Template<'_, '_>, RenderError> {
+        let safe_name = !content_type.is_empty()
+            && content_type
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if safe_name {
+            if let Ok(template) = self
+                .env
+                .get_template(&format!("content_types/{}.html", content_type))
+            {
+                return Ok(template);
+            }
+        }
+        Ok(self.env.get_template("content_types/default.html")?)
+    }
+
+    fn render_element(&self, page: &PublicPage, links: &Links) -> Result<String, RenderError> {
+        let attributes: serde_json::Value = page
+            .page
+            .attributes
+            .as_deref()
+            .and_then(|attributes| serde_json::from_str(attributes).ok())
+            .filter(serde_json::Value::is_object)
+            .unwrap_or_else(|| serde_json::json!({}));
+
+        let contents: Vec<serde_json::Value> = page
+            .contents
+            .iter()
+            .map(|content| {
+                let mut value = serde_json::to_value(content).unwrap_or_default();
+                // Sanitized again here: rows written before migration 009, by
+                // bundle import or by hand never went through `prepare`.
+                value["long_desc_html"] = serde_json::json!(content
+                    .long_desc_html
+                    .as_deref()
+                    .map(rich_text::sanitize));
+                value["images"] = serde_json::json!(links.images(content.image_path.as_deref()));
+                value["gallery"] = content
+                    .gallery
+                    .iter()
+                    .map(|image| {
+                        let mut item = serde_json::to_value(image).unwrap_or_default();
+                        item["url"] = serde_json::json!(links.image(&image.filename));
+                        item
+                    })
+                    .collect();
+                value
+            })
+            .collect();
+
+        let template = self.element_template(&page.page.content_type)?;
+        Ok(template.render(context! {
+            page => &page.page,
+            attributes => attributes,
+            contents => contents,
+            fallback => page.fallback,
+        })?)
+    }
+
+    /// A complete HTML document for the resolved pages of one `page_name`,
+    /// grouping consecutive pages of a section into one `<section>`.
+    fn render_document(
+        &self,
+        page_name: &str,
+        lang: &str,
+        dir: &str,
+        pages: &[PublicPage],
+        alternates: &[Alternate],
+        links: &Links,
+    ) -> Result<Document, RenderError> {
+        let mut sections: Vec<(String, String)> = Vec::new();
+        for page in pages {
+            let html = self.render_element(page, links)?;
+            match sections.last_mut() {
+                Some((name, section)) if *name == page.page.section_name => {
+                    section.push('\n');
+                    section.push_str(&html);
+                }
+                _ => sections.push((page.page.section_name.clone(), html)),
+            }
+        }
+        let sections: Vec<Section> = sections
+            .into_iter()
+            .map(|(name, html)| Section {
+                name,
+                html: Value::from_safe_string(html),
+            })
+            .collect();
+
+        let title = document_title(pages, page_name);
+
+        let layout = self.env.get_template("layout.html")?;
+        let html = layout.render(context! {
+            lang => lang,
+            dir => dir,
+            title => &title,
+            page_name => page_name,
+            sections => sections,
+            alternates => alternates,
+        })?;
+        Ok(Document { title, html })
+    }
+
+    /// A language's table of contents, rendered with `index.html`.
+    pub fn render_index(
+        &self,
+        lang: &str,
+        dir: &str,
+        title: &str,
+        pages: &[IndexEntry],
+        alternates: &[Alternate],
+    ) -> Result<String, RenderError> {
+        let index = self.env.get_template("index.html")?;
+        Ok(index.render(context! {
+            lang => lang,
+            dir => dir,
+            title => title,
+            pages => pages,
+            alternates => alternates,
+        })?)
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Renders every visible page of `page_name` in `lang`, or the default
+/// language, resolving missing translations like the public pages API.
+/// Returns `None` when nothing of `page_name` is visible.
+pub async fn render_page(
+    pool: &SqlitePool,
+    renderer: &Renderer,
+    links: &Links<'_>,
+    page_name: &str,
+    lang: Option<&str>,
+) -> Result<Option<Document>, RenderError> {
+    let lang = match lang {
+        Some(lang) => languages::normalize_tag(lang).unwrap_or_else(|| lang.to_string()),
+        None => languages::default_code(pool).await?.unwrap_or_default(),
+    };
+
+    let pages = languages::resolve_slot(pool, page_name, None, Some(&lang)).await?;
+    if pages.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = languages::fetch(pool, &lang)
+        .await?
+        .map(|language| language.direction)
+        .unwrap_or_else(|| "ltr".to_string());
+
+    let alternate_langs: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT p.lang FROM pages p JOIN languages l ON l.code = p.lang
+         WHERE p.page_name = ? AND p.visible = 1 AND l.enabled = 1 ORDER BY p.lang",
+    )
+    .bind(page_name)
+    .fetch_all(pool)
+    .await?;
+    let alternates: Vec<Alternate> = alternate_langs
+        .into_iter()
+        .map(|alternate| Alternate {
+            href: (links.page_href)(page_name, &alternate),
+            lang: alternate,
+        })
+        .collect();
+
+    renderer
+        .render_document(page_name, &lang, &dir, &pages, &alternates, links)
+        .map(Some)
+}
+
+/// A single page as a complete document, whatever its visibility, for
+/// preview links. No alternate languages are listed.
+pub async fn render_preview(
+    pool: &SqlitePool,
+    renderer: &Renderer,
+    links: &Links<'_>,
+    page: PublicPage,
+) -> Result<Document, RenderError> {
+    let dir = languages::fetch(pool, &page.page.lang)
+        .await?
+        .map(|language| language.direction)
+        .unwrap_or_else(|| "ltr".to_string());
+    let page_name = page.page.page_name.clone();
+    let lang = page.page.lang.clone();
+    renderer.render_document(&page_name, &lang, &dir, &[page], &[], links)
+}
@@ -0,0 +1,83 @@
+use ammonia::Builder;
+use pulldown_cmark::{html, Options, Parser};
+use std::collections::HashSet;
+
+/// Formats `contents.long_desc` may be written in.
+pub const FORMATS: &[&str] = &["plain", "markdown", "html"];
+
+pub const DEFAULT_FORMAT: &str = "plain";
+
+pub fn check_format(format: &str) -> Result<(), String> {
+    if FORMATS.contains(&format) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown format '{}', expected one of: {}",
+            format,
+            FORMATS.join(", ")
+        ))
+    }
+}
+
+/// Removes everything outside the allowlist: ammonia's default set of
+/// formatting, list, table, link and image tags, with links limited to
+/// http, https and mailto and given `rel="noopener noreferrer nofollow"`.
+/// Scripts, styles, event handlers and `javascript:` URLs never survive.
+pub fn sanitize(html: &str) -> String {
+    Builder::default()
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .clean(html)
+        .to_string()
+}
+
+/// Plain text as a single paragraph with escaped markup and `<br>` line
+/// breaks. Migration 009 renders existing rows with the same rules in SQL.
+fn render_plain(text: &str) -> String {
+    let escaped = text
+        .replace('\r', "")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "<br>");
+    format!("<p>{}</p>", escaped)
+}
+
+fn render_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+    output
+}
+
+/// Safe HTML for `source` in `format`. Markdown and HTML output is always
+/// passed through [`sanitize`], whatever was stored.
+pub fn render(format: &str, source: &str) -> String {
+    match format {
+        "markdown" => sanitize(&render_markdown(source)),
+        "html" => sanitize(source),
+        _ => render_plain(source),
+    }
+}
+
+/// Prepares `long_desc` for storage: HTML sources are sanitized before they
+/// are saved, and the rendered HTML is returned alongside. Fails on an
+/// unknown format.
+pub fn prepare(
+    format: &str,
+    long_desc: Option<&str>,
+) -> Result<(Option<String>, Option<String>), String> {
+    check_format(format)?;
+    let Some(source) = long_desc else {
+        return Ok((None, None));
+    };
+
+    let source = if format == "html" {
+        sanitize(source)
+    } else {
+        source.to_string()
+    };
+    let rendered = render(format, &source);
+    Ok((Some(source), Some(rendered)))
+}
@@ -0,0 +1,123 @@
+//! "More like this" for contents: term frequencies over title, short
+//! description and text, weighted by inverse document frequency and compared
+//! by cosine similarity. Everything is computed in memory per request.
+
+use std::collections::HashMap;
+
+/// Words too common to say anything about a text.
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "do", "for", "from", "has", "have", "he", "her", "his", "how", "if", "in",
+    "into", "is", "it", "its", "more", "no", "not", "of", "on", "or", "our", "she", "so", "than",
+    "that", "the", "their", "them", "then", "there", "these", "they", "this", "to", "up", "us",
+    "was", "we", "were", "what", "when", "which", "who", "will", "with", "you", "your",
+];
+
+/// Text of sanitized HTML without its tags and character references.
+fn strip_markup(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut in_reference = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            '&' if !in_tag => in_reference = true,
+            ';' if in_reference => {
+                in_reference = false;
+                text.push(' ');
+            }
+            _ if in_tag => {}
+            _ if in_reference && (c.is_alphanumeric() || c == '#') => {}
+            _ => {
+                in_reference = false;
+                text.push(c);
+            }
+        }
+    }
+    text
+}
+
+/// Lowercased words of `text` of at least two characters, stop words left
+/// out.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+/// Term counts of a content. Title words count twice.
+pub fn term_counts(
+    title: Option<&str>,
+    short_desc: Option<&str>,
+    long_desc_html: Option<&str>,
+) -> HashMap<String, f64> {
+    let mut counts = HashMap::new();
+    let title = title.unwrap_or_default();
+    let body = strip_markup(long_desc_html.unwrap_or_default());
+    let texts = [title, title, short_desc.unwrap_or_default(), &body];
+    for word in texts.into_iter().flat_map(words) {
+        *counts.entry(word).or_insert(0.0) += 1.0;
+    }
+    counts
+}
+
+fn weigh(counts: &HashMap<String, f64>, idf: &HashMap<&str, f64>) -> HashMap<String, f64> {
+    counts
+        .iter()
+        .map(|(term, count)| {
+            let weight = (1.0 + count.ln()) * idf.get(term.as_str()).copied().unwrap_or(0.0);
+            (term.clone(), weight)
+        })
+        .collect()
+}
+
+fn norm(vector: &HashMap<String, f64>) -> f64 {
+    vector.values().map(|w| w * w).sum::<f64>().sqrt()
+}
+
+/// Candidates ranked by similarity to `target`, best first, at most `limit`
+/// of them. Candidates sharing no term with `target` are left out.
+pub fn rank(
+    target: &HashMap<String, f64>,
+    candidates: &[(i64, HashMap<String, f64>)],
+    limit: usize,
+) -> Vec<(i64, f64)> {
+    let documents = candidates.len() as f64 + 1.0;
+    let mut frequencies: HashMap<&str, f64> = HashMap::new();
+    for counts in std::iter::once(target).chain(candidates.iter().map(|(_, counts)| counts)) {
+        for term in counts.keys() {
+            *frequencies.entry(term.as_str()).or_insert(0.0) += 1.0;
+        }
+    }
+    let idf: HashMap<&str, f64> = frequencies
+        .into_iter()
+        .map(|(term, frequency)| (term, ((documents + 1.0) / (frequency + 1.0)).ln() + 1.0))
+        .collect();
+
+    let target = weigh(target, &idf);
+    let target_norm = norm(&target);
+    if target_norm == 0.0 {
+        return Vec::new();
+    }
+
+    let mut ranked: Vec<(i64, f64)> = candidates
+        .iter()
+        .filter_map(|(id, counts)| {
+            let candidate = weigh(counts, &idf);
+            let dot: f64 = candidate
+                .iter()
+                .filter_map(|(term, weight)| target.get(term).map(|w| w * weight))
+                .sum();
+            let candidate_norm = norm(&candidate);
+            (dot > 0.0 && candidate_norm > 0.0).then(|| (*id, dot / (target_norm * candidate_norm)))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked.truncate(limit);
+    ranked
+}
@@ -0,0 +1,438 @@
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use tokio::sync::Mutex;
+
+use crate::config::SiteConfig;
+use crate::feeds::{sitemap_xml, SitemapUrl};
+use crate::models::language::Language;
+use crate::models::site::{SiteBuildReport, SiteManifest, SitePage};
+use crate::render::{self, Alternate, IndexEntry, Links, RenderError, Renderer};
+
+const MANIFEST_FILE: &str = ".build.json";
+const IMAGE_DIR: &str = "images";
+
+/// Held for the length of a build so two builds never write the same
+/// directory at once.
+static BUILD_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug)]
+pub enum SiteError {
+    /// Another build is running.
+    Busy,
+    Render(RenderError),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for SiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SiteError::Busy => write!(f, "A site build is already running"),
+            SiteError::Render(e) => write!(f, "{}", e),
+            SiteError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SiteError {}
+
+impl From<RenderError> for SiteError {
+    fn from(e: RenderError) -> Self {
+        SiteError::Render(e)
+    }
+}
+
+impl From<sqlx::Error> for SiteError {
+    fn from(e: sqlx::Error) -> Self {
+        SiteError::Render(RenderError::Database(e))
+    }
+}
+
+impl From<std::io::Error> for SiteError {
+    fn from(e: std::io::Error) -> Self {
+        SiteError::Io(e)
+    }
+}
+
+/// Whether `name` can be used as a single file name in the site directory.
+pub fn is_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Where a `page_name` in `lang` is written, relative to the site directory.
+pub fn page_file(lang: &str, page_name: &str) -> String {
+    format!("{}/{}.html", lang, page_name)
+}
+
+/// Every file a build described by `manifest` leaves in the site directory,
+/// apart from the manifest, the root index and the sitemap.
+fn files_of(manifest: &SiteManifest) -> BTreeSet<String> {
+    let mut files = BTreeSet::new();
+    for (page_name, page) in &manifest.pages {
+        for lang in page.titles.keys() {
+            files.insert(page_file(lang, page_name));
+            files.insert(format!("{}/index.html", lang));
+        }
+    }
+    for image in &manifest.images {
+        files.insert(format!("{}/{}", IMAGE_DIR, image));
+    }
+    files
+}
+
+fn write_file(site_dir: &Path, file: &str, contents: &str) -> Result<(), std::io::Error> {
+    let path = site_dir.join(file);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)
+}
+
+/// Copies `source` over `target` unless `target` already has its size and
+/// is at least as new. Returns whether it copied.
+fn copy_if_changed(source: &Path, target: &Path) -> Result<bool, std::io::Error> {
+    let source_metadata = std::fs::metadata(source)?;
+    if let Ok(target_metadata) = std::fs::metadata(target) {
+        let current = target_metadata.len() == source_metadata.len()
+            && matches!(
+                (target_metadata.modified(), source_metadata.modified()),
+                (Ok(target), Ok(source)) if target >= source
+            );
+        if current {
+            return Ok(false);
+        }
+    }
+    std::fs::copy(source, target)?;
+    Ok(true)
+}
+
+fn read_manifest(site_dir: &Path) -> SiteManifest {
+    std::fs::read(site_dir.join(MANIFEST_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Hash of everything besides pages and contents that shapes the output.
+fn settings_hash(
+    renderer: &Renderer,
+    config: &SiteConfig,
+    languages: &[Language],
+) -> Result<String, std::io::Error> {
+    let mut hasher = Sha256::new();
+    hasher.update(renderer.fingerprint()?.as_bytes());
+    hasher.update(config.base_url.as_bytes());
+    for language in languages {
+        hasher.update(
+            format!(
+                "{}:{}:{}:{}:{}:{};",
+                language.code,
+                language.name,
+                language.direction,
+                language.is_default,
+                language.fallback,
+                language.updated_at
+            )
+            .as_bytes(),
+        );
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Signature and latest `updated_at` of every `page_name`, over all of its
+/// pages and their contents. A page_name whose signature is unchanged
+/// renders the same as last time.
+async fn signatures(pool: &SqlitePool) -> Result<BTreeMap<String, (String, String)>, sqlx::Error> {
+    let pages: Vec<(String, i64, i64, String)> = sqlx::query_as(
+        "SELECT page_name, id, version, updated_at FROM pages ORDER BY page_name, id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let contents: Vec<(String, i64, i64, String)> = sqlx::query_as(
+        "SELECT p.page_name, c.id, c.version, c.updated_at
+         FROM contents c JOIN pages p ON p.id = c.ref_id
+         ORDER BY p.page_name, c.id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let placements: Vec<(String, i64, i64, String)> = sqlx::query_as(
+        "SELECT p.page_name, bp.id, b.version, MAX(b.updated_at, bp.updated_at)
+         FROM block_placements bp JOIN blocks b ON b.id = bp.block_id
+         JOIN pages p ON p.id = bp.page_id
+         ORDER BY p.page_name, bp.id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let gallery: Vec<(String, i64, i64, String)> = sqlx::query_as(
+        "SELECT p.page_name, ci.id, ci.display_order, ci.updated_at
+         FROM content_images ci JOIN contents c ON c.id = ci.content_id
+         JOIN pages p ON p.id = c.ref_id
+         ORDER BY p.page_name, ci.id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut hashers: BTreeMap<String, (Sha256, String)> = BTreeMap::new();
+    let rows = pages
+        .into_iter()
+        .map(|row| ("p", row))
+        .chain(contents.into_iter().map(|row| ("c", row)))
+        .chain(placements.into_iter().map(|row| ("b", row)))
+        .chain(gallery.into_iter().map(|row| ("i", row)));
+    for (kind, (page_name, id, version, updated_at)) in rows {
+        let (hasher, lastmod) = hashers.entry(page_name).or_default();
+        hasher.update(format!("{}{}:{}:{};", kind, id, version, updated_at).as_bytes());
+        if updated_at > *lastmod {
+            *lastmod = updated_at;
+        }
+    }
+
+    Ok(hashers
+        .into_iter()
+        .map(|(page_name, (hasher, lastmod))| {
+            (page_name, (hex::encode(hasher.finalize()), lastmod))
+        })
+        .collect())
+}
+
+/// Renders every visible `page_name` in every enabled language it has
+/// visible pages in to `<lang>/<page_name>.html` under `config.dir`, with a
+/// `<lang>/index.html` per language, a root `index.html` for the default
+/// language, `sitemap.xml` and the referenced images in `images/`.
+///
+/// Unless `full` is set, a page_name whose pages and contents have not
+/// changed since the last build keeps its files. Changing templates,
+/// `SITE_BASE_URL` or the language registry rebuilds everything. Files of
+/// pages and images that are gone are removed.
+pub async fn build(
+    pool: &SqlitePool,
+    renderer: &Renderer,
+    config: &SiteConfig,
+    upload_dir: &Path,
+    full: bool,
+) -> Result<SiteBuildReport, SiteError> {
+    let Ok(_guard) = BUILD_LOCK.try_lock() else {
+        return Err(SiteError::Busy);
+    };
+
+    let site_dir = Path::new(&config.dir);
+    std::fs::create_dir_all(site_dir.join(IMAGE_DIR))?;
+    let mut previous = read_manifest(site_dir);
+    let previous_files = files_of(&previous);
+
+    let languages = sqlx::query_as::<_, Language>(
+        "SELECT * FROM languages WHERE enabled = 1 ORDER BY is_default DESC, code",
+    )
+    .fetch_all(pool)
+    .await?;
+    let settings = settings_hash(renderer, config, &languages)?;
+    let full = full || previous.settings != settings;
+
+    let base = config.base_url.trim_end_matches('/');
+    let page_href =
+        |page_name: &str, lang: &str| format!("{}/{}", base, page_file(lang, page_name));
+    let links = Links {
+        image_base: format!("{}/{}", base, IMAGE_DIR),
+        page_href: &page_href,
+    };
+
+    let published: Vec<(String, String)> = sqlx::query_as(
+        "SELECT DISTINCT p.page_name, p.lang FROM pages p JOIN languages l ON l.code = p.lang
+         WHERE p.visible = 1 AND l.enabled = 1 ORDER BY p.page_name, p.lang",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut published_langs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (page_name, lang) in published {
+        published_langs.entry(page_name).or_default().push(lang);
+    }
+
+    let mut signatures = signatures(pool).await?;
+    let mut report = SiteBuildReport {
+        full,
+        ..Default::default()
+    };
+    let mut manifest = SiteManifest {
+        built_at: chrono::Utc::now().to_rfc3339(),
+        settings,
+        ..Default::default()
+    };
+
+    for (page_name, langs) in published_langs {
+        if !is_file_name(&page_name) {
+            report.skipped.push(page_name);
+            continue;
+        }
+        let (signature, lastmod) = signatures.remove(&page_name).unwrap_or_default();
+
+        if let Some(entry) = previous.pages.remove(&page_name) {
+            let unchanged = !full
+                && entry.signature == signature
+                && entry.titles.keys().eq(langs.iter())
+                && langs
+                    .iter()
+                    .all(|lang| site_dir.join(page_file(lang, &page_name)).exists());
+            if unchanged {
+                report.unchanged += entry.titles.len();
+                manifest.pages.insert(page_name, entry);
+                continue;
+            }
+        }
+
+        let mut titles = BTreeMap::new();
+        for lang in langs {
+            let document =
+                render::render_page(pool, renderer, &links, &page_name, Some(&lang)).await?;
+            let Some(document) = document else {
+                continue;
+            };
+            let file = page_file(&lang, &page_name);
+            write_file(site_dir, &file, &document.html)?;
+            report.rendered.push(file);
+            titles.insert(lang, document.title);
+        }
+        manifest.pages.insert(
+            page_name,
+            SitePage {
+                signature,
+                lastmod,
+                titles,
+            },
+        );
+    }
+
+    let mut entries: BTreeMap<&str, Vec<IndexEntry>> = BTreeMap::new();
+    for (page_name, page) in &manifest.pages {
+        for (lang, title) in &page.titles {
+            entries.entry(lang).or_default().push(IndexEntry {
+                page_name: page_name.clone(),
+                title: title.clone(),
+                href: page_href(page_name, lang),
+            });
+        }
+    }
+    let index_alternates: Vec<Alternate> = languages
+        .iter()
+        .filter(|language| entries.contains_key(language.code.as_str()))
+        .map(|language| Alternate {
+            lang: language.code.clone(),
+            href: format!("{}/{}/index.html", base, language.code),
+        })
+        .collect();
+    let mut root_written = false;
+    for language in &languages {
+        let Some(pages) = entries.get(language.code.as_str()) else {
+            continue;
+        };
+        let html = renderer.render_index(
+            &language.code,
+            &language.direction,
+            &language.name,
+            pages,
+            &index_alternates,
+        )?;
+        let file = format!("{}/index.html", language.code);
+        write_file(site_dir, &file, &html)?;
+        report.rendered.push(file);
+        // Languages come default first, so the root index is the default
+        // language's, or the first language with pages.
+        if !root_written {
+            write_file(site_dir, "index.html", &html)?;
+            report.rendered.push("index.html".to_string());
+            root_written = true;
+        }
+    }
+
+    let mut urls = Vec::new();
+    for (page_name, page) in &manifest.pages {
+        for lang in page.titles.keys() {
+            urls.push(SitemapUrl {
+                loc: page_href(page_name, lang),
+                lastmod: page.lastmod.get(..10).map(str::to_string),
+                alternates: page
+                    .titles
+                    .keys()
+                    .map(|alternate| Alternate {
+                        lang: alternate.clone(),
+                        href: page_href(page_name, alternate),
+                    })
+                    .collect(),
+            });
+        }
+    }
+    write_file(site_dir, "sitemap.xml", &sitemap_xml(&urls))?;
+    report.rendered.push("sitemap.xml".to_string());
+
+    let image_paths: Vec<String> = sqlx::query_scalar(
+        "SELECT c.image_path FROM contents c
+         JOIN pages p ON p.id = c.ref_id
+         JOIN languages l ON l.code = p.lang
+         WHERE p.visible = 1 AND l.enabled = 1 AND c.image_path IS NOT NULL
+         UNION
+         SELECT b.image_path FROM blocks b
+         JOIN block_placements bp ON bp.block_id = b.id
+         JOIN pages p ON p.id = bp.page_id
+         JOIN languages l ON l.code = p.lang
+         WHERE p.visible = 1 AND l.enabled = 1 AND b.image_path IS NOT NULL
+         UNION
+         SELECT ci.filename FROM content_images ci
+         JOIN contents c ON c.id = ci.content_id
+         JOIN pages p ON p.id = c.ref_id
+         JOIN languages l ON l.code = p.lang
+         WHERE p.visible = 1 AND l.enabled = 1",
+    )
+    .fetch_all(pool)
+    .await?;
+    for image_path in &image_paths {
+        for entry in render::image_paths(image_path) {
+            if render::is_external(entry) {
+                continue;
+            }
+            let Some(image) = render::stored_file(entry) else {
+                report.missing_images.push(entry.to_string());
+                continue;
+            };
+            if manifest.images.contains(image) {
+                continue;
+            }
+            let target = site_dir.join(IMAGE_DIR).join(image);
+            match copy_if_changed(&upload_dir.join(image), &target) {
+                Ok(copied) => {
+                    if copied {
+                        report.images_copied += 1;
+                    }
+                    manifest.images.insert(image.to_string());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    report.missing_images.push(image.to_string());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    for file in previous_files.difference(&files_of(&manifest)) {
+        match std::fs::remove_file(site_dir.join(file)) {
+            Ok(()) => {
+                // Only succeeds once a language directory is empty.
+                if let Some(parent) = Path::new(file).parent() {
+                    let _ = std::fs::remove_dir(site_dir.join(parent));
+                }
+                report.removed.push(file.clone());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| SiteError::Io(std::io::Error::other(e)))?;
+    std::fs::write(site_dir.join(MANIFEST_FILE), json)?;
+    Ok(report)
+}
@@ -0,0 +1,400 @@
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::languages;
+use crate::models::taxonomy::{Facet, Taxonomy, Term, TermNode};
+
+#[derive(Debug)]
+pub enum TaxonomyError {
+    /// Malformed term or unknown parent.
+    Invalid(String),
+    /// The slug is taken, or a move would put a term below itself.
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for TaxonomyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaxonomyError::Invalid(message) | TaxonomyError::Conflict(message) => {
+                write!(f, "{}", message)
+            }
+            TaxonomyError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TaxonomyError {}
+
+impl From<sqlx::Error> for TaxonomyError {
+    fn from(e: sqlx::Error) -> Self {
+        TaxonomyError::Database(e)
+    }
+}
+
+/// What terms are assigned to.
+#[derive(Debug, Clone, Copy)]
+pub enum Tagged {
+    Content,
+    Page,
+}
+
+impl Tagged {
+    fn table(self) -> &'static str {
+        match self {
+            Tagged::Content => "content_terms",
+            Tagged::Page => "page_terms",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Tagged::Content => "content_id",
+            Tagged::Page => "page_id",
+        }
+    }
+}
+
+/// Term ids from a comma-separated query parameter.
+pub fn parse_terms(terms: &str) -> Result<Vec<i64>, String> {
+    terms
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(|_| format!("'{}' is not a term id", id)))
+        .collect()
+}
+
+/// Names of every term in `taxonomy_id`, by term id and then language code.
+pub async fn names(
+    conn: &mut SqliteConnection,
+    taxonomy_id: Option<i64>,
+) -> Result<HashMap<i64, BTreeMap<String, String>>, sqlx::Error> {
+    let rows: Vec<(i64, String, String)> = sqlx::query_as(
+        "SELECT n.term_id, n.lang, n.name FROM term_names n
+         JOIN terms t ON t.id = n.term_id WHERE ? IS NULL OR t.taxonomy_id = ?",
+    )
+    .bind(taxonomy_id)
+    .bind(taxonomy_id)
+    .fetch_all(conn)
+    .await?;
+
+    let mut names: HashMap<i64, BTreeMap<String, String>> = HashMap::new();
+    for (term_id, lang, name) in rows {
+        names.entry(term_id).or_default().insert(lang, name);
+    }
+    Ok(names)
+}
+
+/// Replaces the names of `term_id`.
+pub async fn set_names(
+    conn: &mut SqliteConnection,
+    term_id: i64,
+    names: &BTreeMap<String, String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM term_names WHERE term_id = ?")
+        .bind(term_id)
+        .execute(&mut *conn)
+        .await?;
+    for (lang, name) in names {
+        sqlx::query("INSERT INTO term_names (term_id, lang, name) VALUES (?, ?, ?)")
+            .bind(term_id)
+            .bind(lang)
+            .bind(name)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Every term of `taxonomy_id` as a tree.
+pub async fn tree(
+    conn: &mut SqliteConnection,
+    taxonomy_id: i64,
+) -> Result<Vec<TermNode>, sqlx::Error> {
+    fn build(
+        parent_id: Option<i64>,
+        children: &mut HashMap<Option<i64>, Vec<Term>>,
+        names: &mut HashMap<i64, BTreeMap<String, String>>,
+    ) -> Vec<TermNode> {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|term| TermNode {
+                names: names.remove(&term.id).unwrap_or_default(),
+                children: build(Some(term.id), children, names),
+                term,
+            })
+            .collect()
+    }
+
+    let mut names = names(&mut *conn, Some(taxonomy_id)).await?;
+    let terms = sqlx::query_as::<_, Term>(
+        "SELECT * FROM terms WHERE taxonomy_id = ? ORDER BY display_order, id",
+    )
+    .bind(taxonomy_id)
+    .fetch_all(conn)
+    .await?;
+
+    let mut children: HashMap<Option<i64>, Vec<Term>> = HashMap::new();
+    for term in terms {
+        children.entry(term.parent_id).or_default().push(term);
+    }
+    Ok(build(None, &mut children, &mut names))
+}
+
+/// Checks that `parent_id` is a term of `taxonomy`, which must be
+/// hierarchical, and, when `term_id` is being moved under it, that the
+/// parent is neither the term itself nor one of its descendants.
+pub async fn check_parent(
+    conn: &mut SqliteConnection,
+    taxonomy: &Taxonomy,
+    parent_id: Option<i64>,
+    term_id: Option<i64>,
+) -> Result<(), TaxonomyError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    if !taxonomy.hierarchical {
+        return Err(TaxonomyError::Invalid(format!(
+            "Terms of '{}' cannot be nested",
+            taxonomy.name
+        )));
+    }
+
+    let parent_taxonomy: Option<i64> =
+        sqlx::query_scalar("SELECT taxonomy_id FROM terms WHERE id = ?")
+            .bind(parent_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    if parent_taxonomy != Some(taxonomy.id) {
+        return Err(TaxonomyError::Invalid(format!(
+            "Parent term {} not found in '{}'",
+            parent_id, taxonomy.name
+        )));
+    }
+
+    if let Some(term_id) = term_id {
+        let cycle: bool = sqlx::query_scalar(
+            "WITH RECURSIVE ancestors(id) AS (
+                 SELECT ?
+                 UNION
+                 SELECT t.parent_id FROM terms t JOIN ancestors a ON t.id = a.id
+                 WHERE t.parent_id IS NOT NULL
+             )
+             SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?)",
+        )
+        .bind(parent_id)
+        .bind(term_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if cycle {
+            return Err(TaxonomyError::Conflict(format!(
+                "Term {} cannot be moved under itself or one of its descendants",
+                term_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fails if a term other than `term_id` in `taxonomy_id` uses `slug`.
+pub async fn check_slug(
+    conn: &mut SqliteConnection,
+    taxonomy_id: i64,
+    slug: &str,
+    term_id: Option<i64>,
+) -> Result<(), TaxonomyError> {
+    let taken: Option<i64> =
+        sqlx::query_scalar("SELECT id FROM terms WHERE taxonomy_id = ? AND slug = ? AND id != ?")
+            .bind(taxonomy_id)
+            .bind(slug)
+            .bind(term_id.unwrap_or(0))
+            .fetch_optional(conn)
+            .await?;
+    match taken {
+        Some(id) => Err(TaxonomyError::Conflict(format!(
+            "Slug '{}' is already used by term {}",
+            slug, id
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Terms a content or page is tagged with.
+pub async fn assigned(
+    conn: &mut SqliteConnection,
+    tagged: Tagged,
+    id: i64,
+) -> Result<Vec<Term>, sqlx::Error> {
+    sqlx::query_as::<_, Term>(&format!(
+        "SELECT t.* FROM terms t JOIN {} a ON a.term_id = t.id WHERE a.{} = ?
+         ORDER BY t.taxonomy_id, t.display_order, t.id",
+        tagged.table(),
+        tagged.column()
+    ))
+    .bind(id)
+    .fetch_all(conn)
+    .await
+}
+
+/// Replaces the terms a content or page is tagged with. Fails on an unknown
+/// term id.
+pub async fn assign(
+    conn: &mut SqliteConnection,
+    tagged: Tagged,
+    id: i64,
+    term_ids: &[i64],
+) -> Result<(), TaxonomyError> {
+    for term_id in term_ids {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM terms WHERE id = ?")
+            .bind(term_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if exists.is_none() {
+            return Err(TaxonomyError::Invalid(format!(
+                "Term {} not found",
+                term_id
+            )));
+        }
+    }
+
+    sqlx::query(&format!(
+        "DELETE FROM {} WHERE {} = ?",
+        tagged.table(),
+        tagged.column()
+    ))
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+    for term_id in term_ids {
+        sqlx::query(&format!(
+            "INSERT OR IGNORE INTO {} ({}, term_id) VALUES (?, ?)",
+            tagged.table(),
+            tagged.column()
+        ))
+        .bind(id)
+        .bind(term_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Pairs of a term and each term at or below it, so a filter or count on a
+/// category includes its subcategories.
+const CLOSURE: &str = "WITH RECURSIVE closure(ancestor, descendant) AS (
+         SELECT id, id FROM terms
+         UNION ALL
+         SELECT c.ancestor, t.id FROM terms t JOIN closure c ON t.parent_id = c.descendant
+     )";
+
+/// Ids of the contents or pages tagged, for every id in `term_ids`, with
+/// that term or one of its descendants.
+pub async fn matching(
+    pool: &SqlitePool,
+    tagged: Tagged,
+    term_ids: &[i64],
+) -> Result<HashSet<i64>, sqlx::Error> {
+    let mut matches: Option<HashSet<i64>> = None;
+    for term_id in term_ids {
+        let ids: Vec<i64> = sqlx::query_scalar(&format!(
+            "{} SELECT DISTINCT a.{} FROM {} a JOIN closure c ON c.descendant = a.term_id
+             WHERE c.ancestor = ?",
+            CLOSURE,
+            tagged.column(),
+            tagged.table()
+        ))
+        .bind(term_id)
+        .fetch_all(pool)
+        .await?;
+        let ids: HashSet<i64> = ids.into_iter().collect();
+        matches = Some(match matches {
+            Some(matches) => matches.intersection(&ids).copied().collect(),
+            None => ids,
+        });
+    }
+    Ok(matches.unwrap_or_default())
+}
+
+async fn term_counts(
+    pool: &SqlitePool,
+    tagged: Tagged,
+    among: &HashSet<i64>,
+) -> Result<HashMap<i64, usize>, sqlx::Error> {
+    let pairs: Vec<(i64, i64)> = sqlx::query_as(&format!(
+        "{} SELECT DISTINCT c.ancestor, a.{} FROM {} a JOIN closure c ON c.descendant = a.term_id",
+        CLOSURE,
+        tagged.column(),
+        tagged.table()
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for (term_id, id) in pairs {
+        if among.contains(&id) {
+            *counts.entry(term_id).or_default() += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Counts of `contents` and `pages` per term, optionally of one taxonomy,
+/// with names in `lang` or along its fallback chain. Terms carried by none
+/// of them are left out.
+pub async fn facets(
+    pool: &SqlitePool,
+    taxonomy_id: Option<i64>,
+    lang: Option<&str>,
+    contents: &HashSet<i64>,
+    pages: &HashSet<i64>,
+) -> Result<Vec<Facet>, sqlx::Error> {
+    let lang = match lang {
+        Some(lang) => languages::normalize_tag(lang).unwrap_or_else(|| lang.to_string()),
+        None => languages::default_code(pool).await?.unwrap_or_default(),
+    };
+    let chain = languages::resolution_chain(pool, &lang).await?;
+
+    let content_counts = term_counts(pool, Tagged::Content, contents).await?;
+    let page_counts = term_counts(pool, Tagged::Page, pages).await?;
+
+    let mut conn = pool.acquire().await?;
+    let names = names(&mut conn, taxonomy_id).await?;
+    let terms = sqlx::query_as::<_, Term>(
+        "SELECT * FROM terms WHERE ? IS NULL OR taxonomy_id = ?
+         ORDER BY taxonomy_id, display_order, id",
+    )
+    .bind(taxonomy_id)
+    .bind(taxonomy_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(terms
+        .into_iter()
+        .filter_map(|term| {
+            let contents = content_counts.get(&term.id).copied().unwrap_or(0);
+            let pages = page_counts.get(&term.id).copied().unwrap_or(0);
+            if contents == 0 && pages == 0 {
+                return None;
+            }
+            let term_names = names.get(&term.id);
+            let name = chain
+                .iter()
+                .find_map(|code| term_names.and_then(|names| names.get(code)))
+                .cloned()
+                .unwrap_or(term.name);
+            Some(Facet {
+                taxonomy_id: term.taxonomy_id,
+                term_id: term.id,
+                parent_id: term.parent_id,
+                slug: term.slug,
+                name,
+                contents,
+                pages,
+            })
+        })
+        .collect())
+}
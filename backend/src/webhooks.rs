@@ -0,0 +1,461 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
+use std::time::{Duration, Instant};
+
+/// Delay before the first retry; doubled after every further failure.
+const RETRY_BASE_SECONDS: i64 = 30;
+const RETRY_MAX_SECONDS: i64 = 6 * 60 * 60;
+const BATCH_SIZE: i64 = 20;
+/// How often the worker prunes the outbox.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Writes an event to the outbox and queues a delivery for every active
+/// webhook subscribed to it. Runs on the caller's connection so the event
+/// commits or rolls back together with the change that produced it.
+pub async fn enqueue(
+    conn: &mut SqliteConnection,
+    event_type: &str,
+    payload: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    let event_id = sqlx::query("INSERT INTO webhook_events (event_type, payload) VALUES (?, ?)")
+        .bind(event_type)
+        .bind(payload.to_string())
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+
+    sqlx::query(
+        "INSERT INTO webhook_deliveries (webhook_id, event_id)
+         SELECT id, ? FROM webhooks
+         WHERE is_active = 1
+         AND (events = '*' OR ',' || events || ',' LIKE '%,' || ? || ',%')",
+    )
+    .bind(event_id)
+    .bind(event_type)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// `sha256=<hex>` HMAC of the request body, sent as `X-Webhook-Signature`.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(FromRow)]
+struct DueDelivery {
+    id: i64,
+    attempts: i64,
+    event_type: String,
+    payload: String,
+    url: String,
+    secret: String,
+}
+
+/// Puts a delivery back in the queue with a fresh set of attempts, whatever
+/// its status. Returns `false` if there is no such delivery.
+pub async fn requeue(conn: &mut SqliteConnection, delivery_id: i64) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query_scalar::<_, i64>(
+        "UPDATE webhook_deliveries SET status = 'pending', attempts = 0,
+         next_attempt_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? RETURNING id",
+    )
+    .bind(delivery_id)
+    .fetch_optional(conn)
+    .await?;
+    Ok(updated.is_some())
+}
+
+/// Deletes events older than `retention_days` that have nothing left to
+/// send: every delivery is `delivered` or `dead`, or nobody subscribed. Their
+/// deliveries go with them. Returns the number of events deleted.
+pub async fn prune(pool: &SqlitePool, retention_days: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM webhook_events
+         WHERE created_at < datetime('now', ?)
+         AND NOT EXISTS (
+             SELECT 1 FROM webhook_deliveries d
+             WHERE d.event_id = webhook_events.id AND d.status = 'pending'
+         )",
+    )
+    .bind(format!("-{} days", retention_days))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Polls the outbox for due deliveries and sends them until the process
+/// exits. Failed deliveries are retried with exponential backoff and marked
+/// `dead` after `max_attempts`. Once an hour, finished events older than
+/// `retention_days` are pruned; `0` keeps them forever.
+pub async fn run_worker(
+    pool: SqlitePool,
+    poll_interval: Duration,
+    max_attempts: i64,
+    retention_days: i64,
+) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Webhook worker disabled: {}", e);
+            return;
+        }
+    };
+
+    let mut last_pruned: Option<Instant> = None;
+    loop {
+        if retention_days > 0 && last_pruned.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+            match prune(&pool, retention_days).await {
+                Ok(0) => {}
+                Ok(pruned) => log::info!("Pruned {} webhook events", pruned),
+                Err(e) => log::warn!("Webhook outbox pruning failed: {}", e),
+            }
+            last_pruned = Some(Instant::now());
+        }
+
+        match deliver_due(&pool, &client, max_attempts).await {
+            Ok(0) => tokio::time::sleep(poll_interval).await,
+            Ok(_) => {}
+            Err(e) => {
+                log::warn!("Webhook worker error: {}", e);
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+async fn deliver_due(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    max_attempts: i64,
+) -> Result<usize, sqlx::Error> {
+    let due = sqlx::query_as::<_, DueDelivery>(
+        "SELECT d.id, d.attempts, e.event_type, e.payload, w.url, w.secret
+         FROM webhook_deliveries d
+         JOIN webhook_events e ON e.id = d.event_id
+         JOIN webhooks w ON w.id = d.webhook_id
+         WHERE d.status = 'pending' AND d.next_attempt_at <= CURRENT_TIMESTAMP
+         AND w.is_active = 1
+         ORDER BY d.id
+         LIMIT ?",
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    for delivery in &due {
+        let response = client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Event", &delivery.event_type)
+            .header("X-Webhook-Delivery", delivery.id.to_string())
+            .header(
+                "X-Webhook-Signature",
+                sign(&delivery.secret, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (status_code, error) = match response {
+            Ok(response) if response.status().is_success() => {
+                sqlx::query(
+                    "UPDATE webhook_deliveries SET status = 'delivered', attempts = attempts + 1,
+                     last_status_code = ?, last_error = NULL, delivered_at = CURRENT_TIMESTAMP,
+                     updated_at = CURRENT_TIMESTAMP
+                     WHERE id = ?",
+                )
+                .bind(response.status().as_u16() as i64)
+                .bind(delivery.id)
+                .execute(pool)
+                .await?;
+                continue;
+            }
+            Ok(response) => (
+                Some(response.status().as_u16() as i64),
+                format!("Endpoint responded with {}", response.status()),
+            ),
+            Err(e) => (None, e.to_string()),
+        };
+
+        let attempts = delivery.attempts + 1;
+        let status = if attempts >= max_attempts {
+            "dead"
+        } else {
+            "pending"
+        };
+        let delay = (RETRY_BASE_SECONDS << (attempts - 1).min(20)).min(RETRY_MAX_SECONDS);
+
+        sqlx::query(
+            "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_status_code = ?,
+             last_error = ?, next_attempt_at = datetime('now', ?), updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(status)
+        .bind(attempts)
+        .bind(status_code)
+        .bind(error)
+        .bind(format!("+{} seconds", delay))
+        .bind(delivery.id)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(due.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    struct Received {
+        headers: HashMap<String, String>,
+        body: String,
+    }
+
+    /// A local HTTP endpoint that answers every request with `status` and
+    /// keeps what it received.
+    struct StandIn {
+        url: String,
+        status: Arc<AtomicU16>,
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> Option<Received> {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let read = socket.read(&mut chunk).await.ok()?;
+            if read == 0 {
+                return None;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+
+            let Some(head_end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") else {
+                continue;
+            };
+            let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+            let headers: HashMap<String, String> = head
+                .lines()
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                .collect();
+            let length: usize = headers
+                .get("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0);
+            if buffer.len() >= head_end + 4 + length {
+                let body = &buffer[head_end + 4..head_end + 4 + length];
+                return Some(Received {
+                    headers,
+                    body: String::from_utf8_lossy(body).to_string(),
+                });
+            }
+        }
+    }
+
+    async fn stand_in(status: u16) -> StandIn {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let stand_in = StandIn {
+            url,
+            status: Arc::new(AtomicU16::new(status)),
+            received: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let (status, received) = (stand_in.status.clone(), stand_in.received.clone());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let Some(request) = read_request(&mut socket).await else {
+                    continue;
+                };
+                received.lock().unwrap().push(request);
+                let response = format!(
+                    "HTTP/1.1 {} Stand-in\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status.load(Ordering::SeqCst)
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        stand_in
+    }
+
+    async fn pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        pool
+    }
+
+    async fn subscribe(pool: &SqlitePool, url: &str) {
+        sqlx::query("INSERT INTO webhooks (url, secret, events) VALUES (?, 'topsecret', '*')")
+            .bind(url)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn publish(pool: &SqlitePool) {
+        let mut conn = pool.acquire().await.unwrap();
+        enqueue(&mut conn, "page.created", &json!({"event": "page.created"}))
+            .await
+            .unwrap();
+    }
+
+    async fn delivery(pool: &SqlitePool, id: i64) -> (String, i64, Option<i64>) {
+        sqlx::query_as(
+            "SELECT status, attempts, last_status_code FROM webhook_deliveries WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn make_due(pool: &SqlitePool) {
+        sqlx::query(
+            "UPDATE webhook_deliveries SET next_attempt_at = datetime('now', '-1 seconds')",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_events() {
+        let pool = pool().await;
+        let endpoint = stand_in(200).await;
+        subscribe(&pool, &endpoint.url).await;
+        publish(&pool).await;
+
+        let client = reqwest::Client::new();
+        assert_eq!(deliver_due(&pool, &client, 3).await.unwrap(), 1);
+
+        let received = std::mem::take(&mut *endpoint.received.lock().unwrap());
+        assert_eq!(received.len(), 1);
+        let request = &received[0];
+        assert_eq!(request.body, r#"{"event":"page.created"}"#);
+        assert_eq!(request.headers["x-webhook-event"], "page.created");
+        assert_eq!(request.headers["x-webhook-delivery"], "1");
+        // HMAC-SHA256 of the body with key `topsecret`, computed independently.
+        assert_eq!(
+            request.headers["x-webhook-signature"],
+            "sha256=5c90f9ba20bb1c406413beefb9e3bd022156c210b51ea741200ae5168af75b8a"
+        );
+
+        assert_eq!(
+            delivery(&pool, 1).await,
+            ("delivered".to_string(), 1, Some(200))
+        );
+        assert_eq!(deliver_due(&pool, &client, 3).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn retries_until_dead_and_redelivers() {
+        let pool = pool().await;
+        let endpoint = stand_in(500).await;
+        subscribe(&pool, &endpoint.url).await;
+        publish(&pool).await;
+        let client = reqwest::Client::new();
+
+        assert_eq!(deliver_due(&pool, &client, 2).await.unwrap(), 1);
+        assert_eq!(
+            delivery(&pool, 1).await,
+            ("pending".to_string(), 1, Some(500))
+        );
+        // Backed off: not due again yet.
+        assert_eq!(deliver_due(&pool, &client, 2).await.unwrap(), 0);
+
+        make_due(&pool).await;
+        assert_eq!(deliver_due(&pool, &client, 2).await.unwrap(), 1);
+        assert_eq!(delivery(&pool, 1).await, ("dead".to_string(), 2, Some(500)));
+        make_due(&pool).await;
+        assert_eq!(deliver_due(&pool, &client, 2).await.unwrap(), 0);
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(requeue(&mut conn, 1).await.unwrap());
+        assert!(!requeue(&mut conn, 99).await.unwrap());
+        drop(conn);
+        assert_eq!(
+            delivery(&pool, 1).await,
+            ("pending".to_string(), 0, Some(500))
+        );
+
+        endpoint.status.store(204, Ordering::SeqCst);
+        assert_eq!(deliver_due(&pool, &client, 2).await.unwrap(), 1);
+        assert_eq!(
+            delivery(&pool, 1).await,
+            ("delivered".to_string(), 1, Some(204))
+        );
+        assert_eq!(endpoint.received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn prunes_finished_events_after_retention() {
+        let pool = pool().await;
+        // Events 1 to 3 and 5 get a delivery; event 4 is published while
+        // nobody is subscribed.
+        subscribe(&pool, "http://127.0.0.1:9/unused").await;
+        for _ in 0..3 {
+            publish(&pool).await;
+        }
+        sqlx::query("UPDATE webhooks SET is_active = 0")
+            .execute(&pool)
+            .await
+            .unwrap();
+        publish(&pool).await;
+        sqlx::query("UPDATE webhooks SET is_active = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        publish(&pool).await;
+
+        sqlx::query(
+            "UPDATE webhook_deliveries SET status = CASE event_id
+             WHEN 1 THEN 'delivered' WHEN 2 THEN 'dead' WHEN 5 THEN 'delivered'
+             ELSE status END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE webhook_events SET created_at = datetime('now', '-31 days') WHERE id < 5",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(prune(&pool, 30).await.unwrap(), 3);
+        let events: Vec<i64> = sqlx::query_scalar("SELECT id FROM webhook_events ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        // The old event still pending and the recent one stay.
+        assert_eq!(events, vec![3, 5]);
+        let deliveries: Vec<i64> =
+            sqlx::query_scalar("SELECT event_id FROM webhook_deliveries ORDER BY event_id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(deliveries, vec![3, 5]);
+    }
+}
@@ -112,10 +112,12 @@ export default function DetailView({
                       </div>
                     )}
 
-                    {content.long_desc && (
-                      <div className="prose prose-slate max-w-none text-slate-700 leading-relaxed text-lg whitespace-pre-wrap">
-                        {content.long_desc}
-                      </div>
+                    {content.long_desc_html && (
+                      // Rendered and sanitized by the server.
+                      <div
+                        className="prose prose-slate max-w-none text-slate-700 leading-relaxed text-lg"
+                        dangerouslySetInnerHTML={{ __html: content.long_desc_html }}
+                      />
                     )}
                   </div>
 
@@ -1,13 +1,15 @@
 import { useState, useEffect, FormEvent } from 'react';
 import { apiService } from '../services/api';
-import { Page, Content } from '../types';
+import { Page, Content, ContentFormat, Language } from '../types';
 import { ArrowLeft, Plus, Trash2, Upload, X, Save } from 'lucide-react';
 
 interface ContentEntry {
   id?: number;
+  version?: number;
   title: string;
   short_desc: string;
   long_desc: string;
+  format: ContentFormat;
   images: { file?: File; url?: string; uploaded?: boolean }[];
 }
 
@@ -24,16 +26,30 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
 
   const [pageName, setPageName] = useState('');
   const [sectionName, setSectionName] = useState('');
-  const [lang, setLang] = useState('en');
+  const [lang, setLang] = useState('');
+  const [languages, setLanguages] = useState<Language[]>([]);
   const [contentType, setContentType] = useState('txt');
   const [visible, setVisible] = useState(true);
   const [displayOrder, setDisplayOrder] = useState(0);
   const [attributes, setAttributes] = useState('');
 
   const [contents, setContents] = useState<ContentEntry[]>([
-    { title: '', short_desc: '', long_desc: '', images: [] },
+    { title: '', short_desc: '', long_desc: '', format: 'plain', images: [] },
   ]);
 
+  useEffect(() => {
+    apiService
+      .getLanguages()
+      .then((data) => {
+        setLanguages(data);
+        if (!page) {
+          const fallback = data.find((language) => language.is_default) ?? data[0];
+          setLang(fallback?.code ?? '');
+        }
+      })
+      .catch((err) => console.error('Failed to load languages', err));
+  }, [page]);
+
   useEffect(() => {
     if (page) {
       setPageName(page.page_name);
@@ -54,9 +70,11 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
         setContents(
           data.map((content) => ({
             id: content.id,
+            version: content.version,
             title: content.title || '',
             short_desc: content.short_desc || '',
             long_desc: content.long_desc || '',
+            format: content.format,
             images: content.image_path
               ? content.image_path.split(',').map((url) => ({ url: url.trim(), uploaded: true }))
               : [],
@@ -69,10 +87,10 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
   };
 
   const addContent = () => {
-    setContents([...contents, { title: '', short_desc: '', long_desc: '', images: [] }]);
+    setContents([...contents, { title: '', short_desc: '', long_desc: '', format: 'plain', images: [] }]);
   };
   const addimage = () => {
-    setContents([...contents, { title: '', short_desc: '', long_desc: '', images: [] }]);
+    setContents([...contents, { title: '', short_desc: '', long_desc: '', format: 'plain', images: [] }]);
   };
   const removeContent = (index: number) => {
     setContents(contents.filter((_, i) => i !== index));
@@ -106,6 +124,14 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
     e.preventDefault();
     setError('');
     setSuccess('');
+
+    // Updates are checked against the version they were loaded at; without
+    // it the server cannot tell whether someone else changed the content.
+    if (contents.some((content) => content.id && content.version === undefined)) {
+      setError('Some contents were loaded without their version. Reload the page and try again.');
+      return;
+    }
+
     setLoading(true);
 
     try {
@@ -121,7 +147,7 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
 
       let pageId: number;
       if (page) {
-        await apiService.updatePage(page.id, pageData);
+        await apiService.updatePage(page.id, page.version, pageData);
         pageId = page.id;
       } else {
         const newPage = await apiService.createPage(pageData);
@@ -145,11 +171,12 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
           title: content.title || undefined,
           short_desc: content.short_desc || undefined,
           long_desc: content.long_desc || undefined,
+          format: content.format,
           image_path: uploadedUrls.length > 0 ? uploadedUrls.join(', ') : undefined,
         };
 
-        if (content.id) {
-          await apiService.updateContent(content.id, contentData);
+        if (content.id && content.version !== undefined) {
+          await apiService.updateContent(content.id, content.version, contentData);
         } else {
           await apiService.createContent(contentData);
         }
@@ -243,10 +270,13 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
                   className="w-full px-4 py-2.5 border border-slate-300 rounded-lg focus:ring-2 focus:ring-slate-900 focus:border-transparent"
                   required
                 >
-                  <option value="en">English (EN)</option>
-                  <option value="es">Spanish (ES)</option>
-                  <option value="fr">French (FR)</option>
-                  <option value="de">German (DE)</option>
+                  {languages
+                    .filter((language) => language.enabled || language.code === page?.lang)
+                    .map((language) => (
+                      <option key={language.code} value={language.code}>
+                        {language.name} ({language.code})
+                      </option>
+                    ))}
                 </select>
               </div>
 
@@ -361,9 +391,20 @@ export default function PageForm({ page, onBack, onSuccess }: PageFormProps) {
                     </div>
 
                     <div>
-                      <label className="block text-sm font-medium text-slate-700 mb-2">
-                        Long Description
-                      </label>
+                      <div className="flex justify-between items-center mb-2">
+                        <label className="block text-sm font-medium text-slate-700">
+                          Long Description
+                        </label>
+                        <select
+                          value={content.format}
+                          onChange={(e) => updateContent(contentIndex, 'format', e.target.value)}
+                          className="px-2 py-1 text-sm border border-slate-300 rounded-lg bg-white"
+                        >
+                          <option value="plain">Plain text</option>
+                          <option value="markdown">Markdown</option>
+                          <option value="html">HTML</option>
+                        </select>
+                      </div>
                       <textarea
                         value={content.long_desc}
                         onChange={(e) => updateContent(contentIndex, 'long_desc', e.target.value)}
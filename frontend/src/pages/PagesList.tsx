@@ -24,9 +24,53 @@ export default function PagesList({
     loadPages();
   }, []);
 
-  const loadPages = async () => {
+  // Reload the list whenever another editor changes a page.
+  useEffect(() => {
+    let source: EventSource | undefined;
+    let lastEventId: string | undefined;
+    let retry: ReturnType<typeof setTimeout> | undefined;
+    let closed = false;
+
+    const onChange = (event: MessageEvent) => {
+      lastEventId = event.lastEventId;
+      loadPages(false);
+    };
+
+    const connect = async () => {
+      try {
+        source = await apiService.openChangeStream("LP", lastEventId);
+      } catch (err) {
+        retry = setTimeout(connect, 5000);
+        return;
+      }
+      if (closed) {
+        source.close();
+        return;
+      }
+      for (const type of ["page.created", "page.updated", "page.deleted", "reset"]) {
+        source.addEventListener(type, onChange);
+      }
+      // The token in the URL expires, so reconnect with a fresh one rather
+      // than letting EventSource retry the old URL.
+      source.onerror = () => {
+        source?.close();
+        retry = setTimeout(connect, 3000);
+      };
+    };
+
+    connect();
+    return () => {
+      closed = true;
+      clearTimeout(retry);
+      source?.close();
+    };
+  }, []);
+
+  const loadPages = async (showLoading = true) => {
     try {
-      setLoading(true);
+      if (showLoading) {
+        setLoading(true);
+      }
       const data = await apiService.getPages("LP");
       setPages(data);
       setError("");
@@ -1,4 +1,4 @@
-import { LoginRequest, LoginResponse, Page, Content, CreatePageRequest, CreateContentRequest } from '../types';
+import { LoginRequest, LoginResponse, Page, Content, Language, CreatePageRequest, CreateContentRequest } from '../types';
 
 const API_BASE_URL = import.meta.env.VITE_API_BASE_URL || 'http://localhost:8080/api';
 
@@ -18,6 +18,13 @@ class ApiService {
     return headers;
   }
 
+  private getVersionedHeaders(version: number): HeadersInit {
+    return {
+      ...this.getHeaders(true),
+      'If-Match': `"${version}"`,
+    };
+  }
+
   async login(data: LoginRequest): Promise<LoginResponse> {
     const response = await fetch(`${API_BASE_URL}/auth/login`, {
       method: 'POST',
@@ -75,10 +82,10 @@ async getPages(sectionName?: string): Promise<Page[]> {
     return response.json();
   }
 
-  async updatePage(id: number, data: Partial<CreatePageRequest>): Promise<Page> {
+  async updatePage(id: number, version: number, data: Partial<CreatePageRequest>): Promise<Page> {
     const response = await fetch(`${API_BASE_URL}/pages/${id}`, {
       method: 'PUT',
-      headers: this.getHeaders(true),
+      headers: this.getVersionedHeaders(version),
       body: JSON.stringify(data),
     });
 
@@ -89,10 +96,10 @@ async getPages(sectionName?: string): Promise<Page[]> {
     return response.json();
   }
 
-  async deletePage(id: number): Promise<void> {
+  async deletePage(id: number, version: number): Promise<void> {
     const response = await fetch(`${API_BASE_URL}/pages/${id}`, {
       method: 'DELETE',
-      headers: this.getHeaders(true),
+      headers: this.getVersionedHeaders(version),
     });
 
     if (!response.ok) {
@@ -100,6 +107,18 @@ async getPages(sectionName?: string): Promise<Page[]> {
     }
   }
 
+  async getLanguages(): Promise<Language[]> {
+    const response = await fetch(`${API_BASE_URL}/languages`, {
+      headers: this.getHeaders(true),
+    });
+
+    if (!response.ok) {
+      throw new Error('Failed to fetch languages');
+    }
+
+    return response.json();
+  }
+
   async getContentsByRef(refId: number): Promise<Content[]> {
     const response = await fetch(`${API_BASE_URL}/contents/ref/${refId}`, {
       headers: this.getHeaders(true),
@@ -126,10 +145,10 @@ async getPages(sectionName?: string): Promise<Page[]> {
     return response.json();
   }
 
-  async updateContent(id: number, data: Partial<CreateContentRequest>): Promise<Content> {
+  async updateContent(id: number, version: number, data: Partial<CreateContentRequest>): Promise<Content> {
     const response = await fetch(`${API_BASE_URL}/contents/${id}`, {
       method: 'PUT',
-      headers: this.getHeaders(true),
+      headers: this.getVersionedHeaders(version),
       body: JSON.stringify(data),
     });
 
@@ -140,10 +159,10 @@ async getPages(sectionName?: string): Promise<Page[]> {
     return response.json();
   }
 
-  async deleteContent(id: number): Promise<void> {
+  async deleteContent(id: number, version: number): Promise<void> {
     const response = await fetch(`${API_BASE_URL}/contents/${id}`, {
       method: 'DELETE',
-      headers: this.getHeaders(true),
+      headers: this.getVersionedHeaders(version),
     });
 
     if (!response.ok) {
@@ -171,6 +190,35 @@ async getPages(sectionName?: string): Promise<Page[]> {
     return response.json();
   }
 
+  async getChangeStreamToken(): Promise<string> {
+    const response = await fetch(`${API_BASE_URL}/changes/stream-token`, {
+      method: 'POST',
+      headers: this.getHeaders(true),
+    });
+
+    if (!response.ok) {
+      throw new Error('Failed to get change stream token');
+    }
+
+    const { token } = await response.json();
+    return token;
+  }
+
+  // EventSource cannot send the Authorization header, so the stream is
+  // opened with a short-lived token in the URL instead.
+  async openChangeStream(sectionName?: string, since?: string): Promise<EventSource> {
+    const url = new URL(`${API_BASE_URL}/changes/stream`);
+    url.searchParams.append('token', await this.getChangeStreamToken());
+    if (sectionName) {
+      url.searchParams.append('section_name', sectionName);
+    }
+    if (since) {
+      url.searchParams.append('since', since);
+    }
+
+    return new EventSource(url.toString());
+  }
+
   getImageUrl(filename: string): string {
     return `${API_BASE_URL}/pre-view/images/${filename}`;
   }
@@ -15,17 +15,40 @@ export interface Page {
   visible: boolean;
   display_order: number;
   attributes?: string;
+  version: number;
+  translation_group?: string;
+  translation_of?: number;
+  parent_id?: number;
+  slug?: string;
+  path?: string;
   created_at: string;
   updated_at: string;
 }
 
+export interface Language {
+  code: string;
+  name: string;
+  direction: 'ltr' | 'rtl';
+  is_default: boolean;
+  enabled: boolean;
+  fallback: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export type ContentFormat = 'plain' | 'markdown' | 'html';
+
 export interface Content {
   id: number;
   ref_id: number;
   short_desc?: string;
   long_desc?: string;
+  format: ContentFormat;
+  long_desc_html?: string;
   image_path?: string;
   title?: string;
+  display_order: number;
+  version: number;
   created_at: string;
   updated_at: string;
 }
@@ -54,6 +77,7 @@ export interface CreateContentRequest {
   ref_id: number;
   short_desc?: string;
   long_desc?: string;
+  format?: ContentFormat;
   image_path?: string;
   title?: string;
 }
@@ -87,9 +87,14 @@ curl -s -X GET "$BASE_URL/search?q=test" \
 echo ""
 
 echo "9. Updating content..."
+# Writes need the ETag of the version being replaced, or they get 428.
+ETAG=$(curl -s -o /dev/null -D - "$BASE_URL/contents/$CONTENT_ID" \
+  -H "Authorization: Bearer $TOKEN" | grep -i '^etag:' | cut -d' ' -f2 | tr -d '\r')
+echo "ETag: $ETAG"
 curl -s -X PUT "$BASE_URL/contents/$CONTENT_ID" \
   -H "Authorization: Bearer $TOKEN" \
   -H "Content-Type: application/json" \
+  -H "If-Match: $ETAG" \
   -d '{
     "title": "Updated Test Content"
   }' | jq .